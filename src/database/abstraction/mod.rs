@@ -3,18 +3,28 @@
 // table
 
 use core::str;
+use std::cmp::Ordering;
 use std::error::Error;
+use std::fmt;
 
+use crate::database::parser::{
+    self, AlterTableAction, ColumnConstraint, ColumnDefinition, Expression, Literal, ParseError,
+    Statement,
+};
+use crate::database::transaction::Transaction;
 use crate::storage::{
     files::{
         FileExtension, FileStorage, TABLE_FILE_DATA_EXTENSION, TABLE_FILE_DESCRIPTOR_EXTENSION,
+        TABLE_FILE_INDEX_EXTENSION, TABLE_FILE_SEQUENCE_EXTENSION,
     },
+    heap::RowId,
+    locks::LockMode,
     persistence::DataHandler,
 };
 use serde_derive::{Deserialize, Serialize};
 
 pub trait DML {
-    fn insert(&mut self, record: Record) -> Result<u32, Box<dyn Error>>;
+    fn insert(&mut self, record: Record) -> Result<InsertResult, Box<dyn Error>>;
     fn select(&self, query: Query) -> Result<ResultSet, Box<dyn Error>>;
     fn update(&mut self, record: Record, query: Query) -> Result<u32, Box<dyn Error>>;
     fn delete(&mut self, query: Query) -> Result<u32, Box<dyn Error>>;
@@ -25,6 +35,8 @@ pub struct Table {
     database: Box<Database>,
     name: String,
     columns: Vec<Column>,
+    #[serde(default)]
+    indexes: Vec<Index>,
 }
 
 impl Default for Table {
@@ -33,6 +45,7 @@ impl Default for Table {
             database: Box::new(Database::new("default", FileStorage::new("default"))),
             name: "default".to_string(),
             columns: Vec::new(),
+            indexes: Vec::new(),
         }
     }
 }
@@ -43,6 +56,7 @@ impl Table {
             database,
             name: name.to_string(),
             columns: Vec::new(),
+            indexes: Vec::new(),
         }
     }
 
@@ -71,6 +85,14 @@ impl Table {
         None
     }
 
+    // columns flagged as primary key, several columns make a composite key
+    pub fn get_primary_key(&self) -> Vec<&Column> {
+        self.columns
+            .iter()
+            .filter(|column| column.is_primary_key)
+            .collect()
+    }
+
     pub fn remove_column(&mut self, name: &str) {
         if let Some(index) = self.get_column_index(name) {
             self.columns.remove(index);
@@ -90,7 +112,60 @@ impl Table {
     }
 
     pub fn set_database(&mut self, database: Database) {
-        self.database = Box::new(database);
+        *self.database = database;
+    }
+
+    pub fn get_indexes(&self) -> &Vec<Index> {
+        &self.indexes
+    }
+
+    pub fn set_indexes(&mut self, indexes: Vec<Index>) {
+        self.indexes = indexes;
+    }
+
+    pub fn get_index(&self, name: &str) -> Option<&Index> {
+        self.indexes.iter().find(|&index| index.name == name)
+    }
+}
+
+// secondary index on one or more columns of a table, its B+ tree is kept in the file <name>.idx.
+// The primary key and each unique column get an index of their own, flagged as a constraint index,
+// so that new keys are looked up instead of compared to every row
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Index {
+    name: String,
+    columns: Vec<String>,
+    #[serde(default)]
+    constraint: bool,
+}
+
+impl Index {
+    pub fn new(name: &str, columns: &[&str]) -> Index {
+        Index {
+            name: name.to_string(),
+            columns: columns.iter().map(|column| column.to_string()).collect(),
+            constraint: false,
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_columns(&self) -> &Vec<String> {
+        &self.columns
+    }
+
+    pub fn set_columns(&mut self, columns: Vec<String>) {
+        self.columns = columns;
+    }
+
+    pub fn is_constraint(&self) -> bool {
+        self.constraint
+    }
+
+    pub fn set_constraint(&mut self, constraint: bool) {
+        self.constraint = constraint;
     }
 }
 
@@ -101,6 +176,66 @@ pub struct Column {
     data_type: DataType,
     is_primary_key: bool,
     is_nullable: bool,
+    #[serde(default)]
+    is_auto_increment: bool,
+    #[serde(default)]
+    is_unique: bool,
+    #[serde(default)]
+    default: Option<ColumnDefault>,
+    // SQL boolean expression every row must satisfy
+    #[serde(default)]
+    check: Option<String>,
+    #[serde(default)]
+    references: Option<ForeignKey>,
+}
+
+// value used when an inserted record does not provide the column
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum ColumnDefault {
+    Literal(Value),
+    // SQL expression computed at insert time
+    Expression(String),
+}
+
+// reference from a column to a column of another table of the same database
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ForeignKey {
+    table: String,
+    // None references the primary key of the table
+    column: Option<String>,
+    #[serde(default)]
+    on_delete: ReferentialAction,
+}
+
+// what happens to referencing rows when the referenced row is deleted
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
+pub enum ReferentialAction {
+    #[default]
+    Restrict,
+    Cascade,
+    SetNull,
+}
+
+impl ForeignKey {
+    pub fn new(table: &str, column: Option<&str>, on_delete: ReferentialAction) -> ForeignKey {
+        ForeignKey {
+            table: table.to_string(),
+            column: column.map(|column| column.to_string()),
+            on_delete,
+        }
+    }
+
+    pub fn get_table(&self) -> &str {
+        &self.table
+    }
+
+    pub fn get_column(&self) -> Option<&str> {
+        self.column.as_deref()
+    }
+
+    pub fn get_on_delete(&self) -> ReferentialAction {
+        self.on_delete
+    }
 }
 
 impl Column {
@@ -118,6 +253,11 @@ impl Column {
             data_type,
             is_primary_key,
             is_nullable,
+            is_auto_increment: false,
+            is_unique: false,
+            default: None,
+            check: None,
+            references: None,
         })
     }
 
@@ -152,6 +292,106 @@ impl Column {
     pub fn set_nullable(&mut self, is_nullable: bool) {
         self.is_nullable = is_nullable;
     }
+
+    pub fn is_auto_increment(&self) -> bool {
+        self.is_auto_increment
+    }
+
+    // only an integer primary key column can generate its values
+    pub fn set_auto_increment(&mut self, is_auto_increment: bool) -> Result<(), String> {
+        if is_auto_increment && (!self.is_primary_key || self.data_type != DataType::Integer) {
+            return Err(format!(
+                "Auto increment column {} must be an integer primary key",
+                self.name
+            ));
+        }
+        self.is_auto_increment = is_auto_increment;
+        Ok(())
+    }
+
+    pub fn is_unique(&self) -> bool {
+        self.is_unique
+    }
+
+    pub fn set_unique(&mut self, is_unique: bool) {
+        self.is_unique = is_unique;
+    }
+
+    pub fn get_default(&self) -> Option<&ColumnDefault> {
+        self.default.as_ref()
+    }
+
+    pub fn set_default(&mut self, default: Option<ColumnDefault>) -> Result<(), ParseError> {
+        if let Some(ColumnDefault::Expression(expression)) = &default {
+            parser::parse_expression(expression)?;
+        }
+        self.default = default;
+        Ok(())
+    }
+
+    pub fn get_check(&self) -> Option<&str> {
+        self.check.as_deref()
+    }
+
+    pub fn set_check(&mut self, check: Option<&str>) -> Result<(), ParseError> {
+        if let Some(check) = check {
+            parser::parse_expression(check)?;
+        }
+        self.check = check.map(|check| check.to_string());
+        Ok(())
+    }
+
+    pub fn get_references(&self) -> Option<&ForeignKey> {
+        self.references.as_ref()
+    }
+
+    pub fn set_references(&mut self, references: Option<ForeignKey>) {
+        self.references = references;
+    }
+
+    // build a column from a parsed column definition
+    pub fn from_definition(definition: &ColumnDefinition) -> Result<Column, String> {
+        let is_primary_key = definition
+            .constraints
+            .contains(&ColumnConstraint::PrimaryKey);
+        let is_nullable =
+            !is_primary_key && !definition.constraints.contains(&ColumnConstraint::NotNull);
+        if is_primary_key && definition.constraints.contains(&ColumnConstraint::Null) {
+            return Err("Primary key column cannot be nullable".to_string());
+        }
+        let mut column = Column::new(
+            &definition.name,
+            definition.data_type.clone(),
+            is_primary_key,
+            is_nullable,
+        )?;
+        for constraint in definition.constraints.iter() {
+            match constraint {
+                ColumnConstraint::AutoIncrement => column.set_auto_increment(true)?,
+                ColumnConstraint::Unique => column.is_unique = true,
+                ColumnConstraint::Default(Expression::Literal(literal)) => {
+                    column.default = Some(ColumnDefault::Literal(Value::from_literal(literal)))
+                }
+                ColumnConstraint::Default(expression) => {
+                    column.default = Some(ColumnDefault::Expression(expression.to_string()))
+                }
+                ColumnConstraint::Check(expression) => column.check = Some(expression.to_string()),
+                ColumnConstraint::References {
+                    table,
+                    column: referenced_column,
+                    on_delete,
+                } => {
+                    column.references = Some(ForeignKey::new(
+                        table,
+                        referenced_column.as_deref(),
+                        *on_delete,
+                    ))
+                }
+                _ => {}
+            }
+        }
+        Ok(column)
+    }
 }
 
 impl PartialEq for Column {
@@ -160,6 +400,11 @@ impl PartialEq for Column {
             && self.data_type == other.data_type
             && self.is_primary_key == other.is_primary_key
             && self.is_nullable == other.is_nullable
+            && self.is_auto_increment == other.is_auto_increment
+            && self.is_unique == other.is_unique
+            && self.default == other.default
+            && self.check == other.check
+            && self.references == other.references
     }
 }
 
@@ -172,11 +417,150 @@ pub enum DataType {
     Blob,
 }
 
+// typed column value, matching DataType
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Value {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    // convert a value to the given column type, conversions must not lose information
+    pub fn cast_to(&self, data_type: &DataType) -> Result<Value, String> {
+        let invalid = || format!("Invalid value {} for type {:?}", self, data_type);
+        match (data_type, self) {
+            (_, Value::Null) => Ok(Value::Null),
+            (DataType::Integer, Value::Integer(_)) => Ok(self.clone()),
+            (DataType::Integer, Value::Real(value)) => {
+                if value.fract() == 0.0 && *value >= i64::MIN as f64 && *value < i64::MAX as f64 {
+                    Ok(Value::Integer(*value as i64))
+                } else {
+                    Err(invalid())
+                }
+            }
+            (DataType::Integer, Value::Text(value)) => match value.trim().parse::<i64>() {
+                Ok(value) => Ok(Value::Integer(value)),
+                Err(_) => Err(invalid()),
+            },
+            (DataType::Real, Value::Integer(value)) => Ok(Value::Real(*value as f64)),
+            (DataType::Real, Value::Real(_)) => Ok(self.clone()),
+            (DataType::Real, Value::Text(value)) => match value.trim().parse::<f64>() {
+                Ok(value) => Ok(Value::Real(value)),
+                Err(_) => Err(invalid()),
+            },
+            (DataType::Text(_), Value::Text(_)) => Ok(self.clone()),
+            (DataType::Text(_), Value::Integer(value)) => Ok(Value::Text(value.to_string())),
+            (DataType::Text(_), Value::Real(value)) => Ok(Value::Text(value.to_string())),
+            (DataType::Blob, Value::Blob(_)) => Ok(self.clone()),
+            _ => Err(invalid()),
+        }
+    }
+
+    pub fn from_literal(literal: &Literal) -> Value {
+        match literal {
+            Literal::Null => Value::Null,
+            Literal::Boolean(value) => Value::Integer(*value as i64),
+            Literal::Integer(value) => Value::Integer(*value),
+            Literal::Real(value) => Value::Real(*value),
+            Literal::Text(value) => Value::Text(value.clone()),
+            Literal::Blob(value) => Value::Blob(value.clone()),
+        }
+    }
+
+    // rank of the value type in the sort order: NULL < numbers < text < blob
+    fn type_rank(&self) -> u8 {
+        match self {
+            Value::Null => 0,
+            Value::Integer(_) | Value::Real(_) => 1,
+            Value::Text(_) => 2,
+            Value::Blob(_) => 3,
+        }
+    }
+}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Value::Integer(left), Value::Integer(right)) => left.cmp(right),
+            (Value::Real(left), Value::Real(right)) => left.total_cmp(right),
+            (Value::Integer(left), Value::Real(right)) => compare_integer_real(*left, *right),
+            (Value::Real(left), Value::Integer(right)) => compare_integer_real(*right, *left).reverse(),
+            (Value::Text(left), Value::Text(right)) => left.cmp(right),
+            (Value::Blob(left), Value::Blob(right)) => left.cmp(right),
+            _ => self.type_rank().cmp(&other.type_rank()),
+        }
+    }
+}
+
+// compare an integer with a real exactly, casting the integer would merge integers above 2^53.
+// Reals outside the integer range and NaN sort like total_cmp does, -0.0 below 0
+fn compare_integer_real(integer: i64, real: f64) -> Ordering {
+    if real.is_nan() {
+        return if real.is_sign_negative() { Ordering::Greater } else { Ordering::Less };
+    }
+    // i64::MIN is a power of two, exact as a real
+    if real >= -(i64::MIN as f64) {
+        return Ordering::Less;
+    }
+    if real < i64::MIN as f64 {
+        return Ordering::Greater;
+    }
+    let floor = real.floor();
+    match integer.cmp(&(floor as i64)) {
+        Ordering::Equal if real > floor => Ordering::Less,
+        Ordering::Equal if real == 0.0 && real.is_sign_negative() => Ordering::Greater,
+        ordering => ordering,
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Value {}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "NULL"),
+            Value::Integer(value) => write!(f, "{}", value),
+            Value::Real(value) => write!(f, "{}", value),
+            Value::Text(value) => write!(f, "'{}'", value.replace('\'', "''")),
+            Value::Blob(value) => {
+                write!(f, "X'")?;
+                for byte in value {
+                    write!(f, "{:02X}", byte)?;
+                }
+                write!(f, "'")
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Record {
     #[serde(skip)]
     table: Table,
-    values: Vec<(Column, Option<String>)>,
+    values: Vec<(Column, Value)>,
+    // location of the row in the table data file, None for a record not stored yet
+    #[serde(skip)]
+    row_id: Option<RowId>,
 }
 
 impl PartialEq for Record {
@@ -192,13 +576,13 @@ impl PartialEq for Record {
                 return false;
             }
         }
-        return true;
+        true
     }
 }
 
 impl Record {
-    pub fn new(table: Table, values: Vec<(Column, Option<String>)>) -> Record {
-        Record { table, values }
+    pub fn new(table: Table, values: Vec<(Column, Value)>) -> Record {
+        Record { table, values, row_id: None }
     }
 
     pub fn get_table(&self) -> &Table {
@@ -209,15 +593,30 @@ impl Record {
         self.table = table;
     }
 
-    pub fn get_values(&self) -> &Vec<(Column, Option<String>)> {
+    pub fn get_values(&self) -> &Vec<(Column, Value)> {
         &self.values
     }
 
-    pub fn set_values(&mut self, values: Vec<(Column, Option<String>)>) {
+    pub fn get_value(&self, column_name: &str) -> Option<&(Column, Value)> {
+        self.values
+            .iter()
+            .find(|(column, _)| column.get_name() == column_name)
+    }
+
+    pub fn set_values(&mut self, values: Vec<(Column, Value)>) {
         self.values = values;
     }
+
+    pub fn get_row_id(&self) -> Option<RowId> {
+        self.row_id
+    }
+
+    pub fn set_row_id(&mut self, row_id: Option<RowId>) {
+        self.row_id = row_id;
+    }
 }
 
+#[derive(Default)]
 pub struct ResultSet {
     records: Vec<Record>,
 }
@@ -227,12 +626,6 @@ impl ResultSet {
         ResultSet { records }
     }
 
-    pub fn default() -> ResultSet {
-        ResultSet {
-            records: Vec::new(),
-        }
-    }
-
     pub fn add_record(&mut self, record: Record) {
         self.records.push(record);
     }
@@ -246,16 +639,108 @@ impl ResultSet {
     }
 }
 
+// outcome of an insert, the id is the generated auto increment value
+// or the integer primary key of the inserted row
+#[derive(Clone, Debug, PartialEq)]
+pub struct InsertResult {
+    affected_rows: u32,
+    last_insert_id: Option<i64>,
+}
+
+impl InsertResult {
+    pub fn new(affected_rows: u32, last_insert_id: Option<i64>) -> InsertResult {
+        InsertResult {
+            affected_rows,
+            last_insert_id,
+        }
+    }
+
+    pub fn get_affected_rows(&self) -> u32 {
+        self.affected_rows
+    }
+
+    pub fn get_last_insert_id(&self) -> Option<i64> {
+        self.last_insert_id
+    }
+}
+
 pub struct Query {
     sql: String,
 }
 
+impl Query {
+    pub fn new(sql: &str) -> Query {
+        Query {
+            sql: sql.to_string(),
+        }
+    }
+
+    pub fn get_sql(&self) -> &str {
+        &self.sql
+    }
+
+    // Parse the query text into a single statement
+    pub fn parse(&self) -> Result<Statement, ParseError> {
+        parser::parse_statement(&self.sql)
+    }
+}
+
 pub trait DDL {
     fn create_database(&mut self, name: &str) -> Result<Database, Box<dyn Error>>;
     fn drop_database(&mut self, name: &str) -> Result<(), Box<dyn Error>>;
     fn create_table(&mut self, table: Table) -> Result<(), Box<dyn Error>>;
     fn drop_table(&mut self, table: Table) -> Result<(), Box<dyn Error>>;
-    fn alter_table(&mut self, table: Table, columns: Vec<Column>) -> Result<(), Box<dyn Error>>;
+    fn alter_table(
+        &mut self,
+        table: Table,
+        operations: Vec<AlterTableOperation>,
+    ) -> Result<(), Box<dyn Error>>;
+    fn create_index(&mut self, table: Table, index: Index) -> Result<(), Box<dyn Error>>;
+    fn drop_index(&mut self, name: &str) -> Result<(), Box<dyn Error>>;
+}
+
+// schema change applied by alter_table, existing rows are migrated accordingly
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlterTableOperation {
+    AddColumn {
+        column: Column,
+        default: Value,
+    },
+    DropColumn(String),
+    RenameColumn {
+        old_name: String,
+        new_name: String,
+    },
+    SetNullable {
+        name: String,
+        is_nullable: bool,
+    },
+}
+
+impl AlterTableOperation {
+    pub fn from_action(action: &AlterTableAction) -> Result<AlterTableOperation, String> {
+        Ok(match action {
+            AlterTableAction::AddColumn(definition) => AlterTableOperation::AddColumn {
+                column: Column::from_definition(definition)?,
+                default: Value::Null,
+            },
+            AlterTableAction::DropColumn(name) => AlterTableOperation::DropColumn(name.clone()),
+            AlterTableAction::RenameColumn { old_name, new_name } => {
+                AlterTableOperation::RenameColumn {
+                    old_name: old_name.clone(),
+                    new_name: new_name.clone(),
+                }
+            }
+            AlterTableAction::SetNotNull(name) => AlterTableOperation::SetNullable {
+                name: name.clone(),
+                is_nullable: false,
+            },
+            AlterTableAction::DropNotNull(name) => AlterTableOperation::SetNullable {
+                name: name.clone(),
+                is_nullable: true,
+            },
+        })
+    }
 }
 
 pub trait DatabaseTrait: DDL {
@@ -269,6 +754,8 @@ pub trait DatabaseTrait: DDL {
 pub struct RootDatabase {
     inner_database: Database,
     databases: Vec<Database>,
+    // mode the databases of the root are opened in
+    mode: LockMode,
 }
 
 impl DatabaseTrait for RootDatabase {
@@ -287,11 +774,11 @@ impl DatabaseTrait for RootDatabase {
     }
 
     fn get_root_dir(&self) -> &str {
-        &self.inner_database.get_storage().get_root_dir()
+        self.inner_database.get_storage().get_root_dir()
     }
 
     fn get_tables(&self) -> &Vec<Table> {
-        &self.inner_database.get_tables()
+        self.inner_database.get_tables()
     }
 }
 
@@ -300,11 +787,21 @@ impl RootDatabase {
         RootDatabase {
             inner_database: Database::new("root_database", FileStorage::new(root_dir)),
             databases: Vec::new(),
+            mode: LockMode::Exclusive,
         }
     }
 
+    // open a root directory in a mode, its databases are opened in the same mode on first use
+    pub fn open(root_dir: &str, mode: LockMode) -> Result<RootDatabase, Box<dyn std::error::Error>> {
+        Ok(RootDatabase {
+            inner_database: Database::open("root_database", root_dir, mode)?,
+            databases: Vec::new(),
+            mode,
+        })
+    }
+
     pub fn get_root_dir(&self) -> &str {
-        &self.inner_database.get_storage().get_root_dir()
+        self.inner_database.get_storage().get_root_dir()
     }
 
     pub fn load_databases(&mut self) -> Result<(), Box<dyn std::error::Error>> {
@@ -316,11 +813,11 @@ impl RootDatabase {
             }
             self.databases.push(Database::new(
                 &database,
-                FileStorage::new(&format!(
+                FileStorage::new_with_mode(&format!(
                     "{}/{}",
                     self.inner_database.get_storage().get_root_dir(),
                     database
-                )),
+                ), self.mode),
             ));
         }
         Ok(())
@@ -368,10 +865,22 @@ impl DDL for RootDatabase {
     // alter system table
     fn alter_table(
         &mut self,
-        _table: Table,
-        _columns: Vec<Column>,
+        table: Table,
+        operations: Vec<AlterTableOperation>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        todo!("Not implemented yet")
+        self.inner_database.alter_table(table, operations)?;
+        Ok(())
+    }
+
+    // index a system table
+    fn create_index(&mut self, table: Table, index: Index) -> Result<(), Box<dyn std::error::Error>> {
+        self.inner_database.create_index(table, index)?;
+        Ok(())
+    }
+
+    fn drop_index(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.inner_database.drop_index(name)?;
+        Ok(())
     }
 }
 
@@ -411,7 +920,7 @@ impl DatabaseTrait for Database {
     }
 
     fn get_root_dir(&self) -> &str {
-        &self.storage.get_root_dir()
+        self.storage.get_root_dir()
     }
 
     fn get_tables(&self) -> &Vec<Table> {
@@ -428,6 +937,17 @@ impl Database {
         }
     }
 
+    // open the database of a directory in a mode, the directory stays locked for the process until
+    // the last handle using it is closed
+    pub fn open(name: &str, root_dir: &str, mode: LockMode) -> Result<Database, Box<dyn std::error::Error>> {
+        Ok(Database::new(name, FileStorage::open(root_dir, mode)?))
+    }
+
+    // close the handle, its tables go along with it
+    pub fn close(self) {
+        drop(self);
+    }
+
     pub fn get_name(&self) -> &str {
         &self.name
     }
@@ -435,6 +955,43 @@ impl Database {
     pub fn get_storage(&self) -> &FileStorage {
         &self.storage
     }
+
+    // open a transaction, the statements run through it are committed together
+    pub fn begin_transaction(&self) -> Result<Transaction, Box<dyn std::error::Error>> {
+        Transaction::begin(self.storage.clone())
+    }
+
+    // delete the data, descriptor, index and sequence files of a table
+    fn delete_table_files(&self, table: &Table) -> Result<(), Box<dyn std::error::Error>> {
+        let data_handler = DataHandler::new_from_storage(self.storage.clone());
+        for (referencing_table, i) in data_handler.load_referencing_columns(table.get_name())? {
+            if referencing_table.get_name() != table.get_name() {
+                return Err(format!(
+                    "Table {} is referenced by {}.{}",
+                    table.get_name(),
+                    referencing_table.get_name(),
+                    referencing_table.get_columns()[i].get_name()
+                )
+                .into());
+            }
+        }
+        // indexes go along with their table
+        let table_description = data_handler.load_table_descriptor(table.get_name())?;
+        for index in table_description.get_indexes() {
+            self.storage
+                .delete_file(&(index.get_name().to_string() + "." + TABLE_FILE_INDEX_EXTENSION))?;
+        }
+        // Delete a file for table data and descriptor
+        self.storage
+            .delete_file(&(table.get_name().to_string() + "." + TABLE_FILE_DATA_EXTENSION))?;
+        self.storage
+            .delete_file(&(table.get_name().to_string() + "." + TABLE_FILE_DESCRIPTOR_EXTENSION))?;
+        let sequence_file = table.get_name().to_string() + "." + TABLE_FILE_SEQUENCE_EXTENSION;
+        if self.storage.file_exists(&sequence_file) {
+            self.storage.delete_file(&sequence_file)?;
+        }
+        Ok(())
+    }
 }
 
 impl DDL for Database {
@@ -454,224 +1011,50 @@ impl DDL for Database {
     }
 
     fn create_table(&mut self, table: Table) -> Result<(), Box<dyn std::error::Error>> {
-        // Create a file for table data and descriptor
+        // Create a file for table data and descriptor, the keys of the table get their indexes
         let data_handler = DataHandler::new_from_storage(self.storage.clone());
-        data_handler.persist_table_descriptor(&table)?;
-        data_handler.persist_new_table(&table)?;
-        Ok(())
+        data_handler.lock_tables(&data_handler.related_tables(&table)?)?;
+        let mut table = table;
+        let result = data_handler
+            .set_constraint_indexes(&mut table)
+            .and_then(|_| data_handler.persist_table_descriptor(&table))
+            .and_then(|_| data_handler.persist_new_table(&table));
+        self.storage.finish_work(result)
     }
 
     fn drop_table(&mut self, table: Table) -> Result<(), Box<dyn std::error::Error>> {
-        // Delete a file for table data and descriptor
-        self.storage
-            .delete_file(&(table.get_name().to_string() + "." + TABLE_FILE_DATA_EXTENSION))?;
-        self.storage
-            .delete_file(&(table.get_name().to_string() + "." + TABLE_FILE_DESCRIPTOR_EXTENSION))?;
-        Ok(())
+        let data_handler = DataHandler::new_from_storage(self.storage.clone());
+        data_handler.lock_tables(&data_handler.related_tables(&table)?)?;
+        let lock = self.storage.get_table_lock(table.get_name());
+        let _table = lock.write()?;
+        let result = self.delete_table_files(&table);
+        self.storage.finish_work(result)
     }
 
     fn alter_table(
         &mut self,
         table: Table,
-        changed_columns: Vec<Column>,
+        operations: Vec<AlterTableOperation>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        todo!("Not implemented yet")
-    }
-}
-
-// Indexes
-pub struct BPlusTree {
-    root_node: Option<NodeType>,
-    order: usize,
-}
-
-impl BPlusTree {
-    pub fn new(order: usize) -> BPlusTree {
-        BPlusTree {
-            root_node: None,
-            order,
-        }
-    }
-
-    pub fn get_order(&self) -> usize {
-        self.order
-    }
-
-    pub fn get_root_node(&self) -> Option<&NodeType> {
-        match &self.root_node {
-            None => None,
-            Some(node) => Some(&node),
-        }
-    }
-
-    pub fn insert(&mut self, key: i32) {
-        match &mut self.root_node {
-            None => {
-                let mut leaf_node = LeafNode::new(self.order);
-                leaf_node.keys.push(key);
-                self.root_node = Some(NodeType::Leaf(leaf_node));
-            }
-            Some(internal_or_leaf_node) => match internal_or_leaf_node.insert(key) {
-                None => {}
-                Some((key, new_child_node)) => {
-                    let mut new_internal_node = InternalNode::new(self.order);
-                    new_internal_node.keys.push(key);
-                    new_internal_node
-                        .children
-                        .push(self.root_node.take().unwrap());
-                    new_internal_node.children.push(new_child_node);
-                    self.root_node = Some(NodeType::Internal(new_internal_node));
-                }
-            },
-        }
-    }
-
-    pub fn get_tree_height(&self) -> usize {
-        match &self.root_node {
-            None => 0,
-            Some(node) => node.get_depth(),
-        }
-    }
-
-    /*
-    pub fn search(&self, key: i32) -> bool {
-        match &self.root_node {
-            None => false,
-            Some(node) => match node {
-                NodeType::Leaf(leaf_node) => leaf_node.keys.contains(&key),
-                NodeType::Internal(internal_node) => {
-                    let mut i = 0;
-                    while i < internal_node.keys.len() && key > internal_node.keys[i] {
-                        i += 1;
-                    }
-                    internal_node.children[i].search(key)
-                }
-            },
-        }
-    }
-    */
-}
-
-pub trait BtreeNode {
-    fn insert(&mut self, key: i32) -> Option<(i32, NodeType)>;
-    fn get_depth(&self) -> usize;
-}
-
-#[derive(Debug)]
-pub enum NodeType {
-    Leaf(LeafNode),
-    Internal(InternalNode),
-}
-
-impl BtreeNode for NodeType {
-    fn insert(&mut self, key: i32) -> Option<(i32, NodeType)> {
-        match self {
-            NodeType::Leaf(leaf_node) => leaf_node.insert(key),
-            NodeType::Internal(internal_node) => internal_node.insert(key),
-        }
-    }
-
-    fn get_depth(&self) -> usize {
-        match self {
-            NodeType::Leaf(leaf_node) => leaf_node.get_depth(),
-            NodeType::Internal(internal_node) => internal_node.get_depth(),
-        }
-    }
-}
-
-#[derive(Debug)]
-pub struct LeafNode {
-    order: usize,
-    keys: Vec<i32>,
-    next: Option<Box<LeafNode>>,
-}
-
-#[derive(Debug)]
-pub struct InternalNode {
-    order: usize,
-    keys: Vec<i32>,
-    children: Vec<NodeType>,
-}
-
-impl LeafNode {
-    pub fn new(order: usize) -> Self {
-        Self {
-            order,
-            keys: Vec::new(),
-            next: None,
-        }
-    }
-}
-
-impl BtreeNode for LeafNode {
-    fn insert(&mut self, key: i32) -> Option<(i32, NodeType)> {
-        self.keys.push(key);
-        self.keys.sort();
-        if self.keys.len() > self.order {
-            let mid = self.keys.len() / 2;
-            let mid_key = self.keys[mid];
-            let mut new_leaf_node = LeafNode::new(self.order);
-            new_leaf_node.keys = self.keys.split_off(mid);
-            // TODO self.next = Some(Box::new(new_leaf_node));
-            Some((mid_key, NodeType::Leaf(new_leaf_node)))
-        } else {
-            None
-        }
+        // Rewrite table descriptor and migrate table data
+        let data_handler = DataHandler::new_from_storage(self.storage.clone());
+        data_handler.alter_table(table.get_name(), &operations)?;
+        Ok(())
     }
 
-    fn get_depth(&self) -> usize {
-        1
+    // build an index over the rows of a table, the index is recorded in the table descriptor
+    fn create_index(&mut self, table: Table, index: Index) -> Result<(), Box<dyn std::error::Error>> {
+        let data_handler = DataHandler::new_from_storage(self.storage.clone());
+        data_handler.create_index(table.get_name(), index)?;
+        Ok(())
     }
-}
 
-impl InternalNode {
-    pub fn new(order: usize) -> Self {
-        Self {
-            order,
-            keys: Vec::new(),
-            children: Vec::new(),
-        }
+    fn drop_index(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let data_handler = DataHandler::new_from_storage(self.storage.clone());
+        data_handler.drop_index(name)?;
+        Ok(())
     }
 }
 
-impl BtreeNode for InternalNode {
-    fn insert(&mut self, key: i32) -> Option<(i32, NodeType)> {
-        let mut i = 0;
-        while i < self.keys.len() && self.keys[i] < key {
-            i += 1;
-        }
-        if i < self.keys.len() {
-            match self.children[i].insert(key) {
-                None => (),
-                Some((mid_key, mid_node)) => {
-                    self.keys.insert(i, mid_key);
-                    self.children.insert(i + 1, mid_node);
-                }
-            };
-        } else {
-            let size = self.children.len();
-            match self.children[size - 1].insert(key) {
-                None => (),
-                Some((mid_key, mid_node)) => {
-                    self.keys.push(mid_key);
-                    self.children.push(mid_node);
-                }
-            };
-        }
-        if self.keys.len() > self.order {
-            let mid = self.keys.len() / 2;
-            let mid_key = self.keys[mid];
-            let mut new_internal_node = InternalNode::new(self.order);
-            new_internal_node.keys = self.keys.split_off(mid);
-            new_internal_node.children = self.children.split_off(mid + 1);
-            Some((mid_key, NodeType::Internal(new_internal_node)))
-        } else {
-            None
-        }
-    }
-
-    fn get_depth(&self) -> usize {
-        // B+tree caracteristics: All leaf nodes are at the same depth, ensuring uniform access times for all data entries.
-        self.children[0].get_depth() + 1
-    }
-}
+// Indexes
+pub use crate::database::btree::{BPlusTree, InternalNode, LeafNode, NodeId, NodeType};
@@ -0,0 +1,871 @@
+// btree is a module that contains the B+ tree used for indexes.
+// leaves are linked to their right sibling, a tree lives in memory or in an index file of fixed-size pages
+
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::ops::{Bound, RangeBounds};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize as SerializeValue;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::storage::files::FileStorage;
+
+pub type NodeId = usize;
+
+// size of a page of an index file, a node must fit in one page
+pub const INDEX_PAGE_SIZE: usize = 4096;
+
+// encoded size of a node besides its entries: the page and node tags, and the leaf link or the first child
+const NODE_OVERHEAD: usize = 69;
+
+// encoded size of the largest node id
+const NODE_ID_SIZE: usize = 20;
+
+// highest order for which a node fits in a page when its keys and values encode in at most the given
+// sizes, None when not even a node of two keys fits
+pub fn page_order(max_key_size: usize, max_value_size: usize) -> Option<usize> {
+    // an entry is a key with its value or child id, each followed by a comma
+    let entry_size = max_key_size + max_value_size.max(NODE_ID_SIZE) + 2;
+    let order = (INDEX_PAGE_SIZE - 4 - NODE_OVERHEAD) / entry_size;
+    (order >= 2).then_some(order)
+}
+
+// ordered map from keys to values, such as row ids or page/slot locations
+pub struct BPlusTree<K, V> {
+    // nodes by id, None for a free id or a page not read yet
+    nodes: Vec<Option<NodeType<K, V>>>,
+    // ids of removed nodes, reused by the next splits
+    free_nodes: Vec<NodeId>,
+    root_node: Option<NodeId>,
+    height: usize,
+    // maximum number of keys in a node
+    order: usize,
+    // index file holding the nodes, None for a tree living in memory only
+    pager: Option<Box<dyn NodePager<K, V>>>,
+    // nodes changed since the last flush
+    dirty_nodes: BTreeSet<NodeId>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum NodeType<K, V> {
+    Leaf(LeafNode<K, V>),
+    Internal(InternalNode<K>),
+}
+
+// values[i] is the value of keys[i]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeafNode<K, V> {
+    keys: Vec<K>,
+    values: Vec<V>,
+    next: Option<NodeId>,
+}
+
+// children[i] holds the keys lower than keys[i], children[i + 1] the keys greater or equal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InternalNode<K> {
+    keys: Vec<K>,
+    children: Vec<NodeId>,
+}
+
+// content of a page of an index file
+#[derive(Serialize, Deserialize)]
+enum Page<K, V> {
+    Header {
+        order: usize,
+        root_node: Option<NodeId>,
+        height: usize,
+        node_count: usize,
+        first_free: Option<NodeId>,
+    },
+    Node(NodeType<K, V>),
+    Free {
+        next: Option<NodeId>,
+    },
+}
+
+// pages are encoded apart from being written so that a flush fails before writing anything
+trait NodePager<K, V> {
+    fn read_page(&self, page_id: usize) -> Result<Page<K, V>, Box<dyn Error>>;
+    fn encode_page(&self, page: &Page<K, V>) -> Result<Vec<u8>, Box<dyn Error>>;
+    fn write_page(&self, page_id: usize, block: &[u8]) -> Result<(), Box<dyn Error>>;
+}
+
+// pages are a 4 bytes little endian length followed by the JSON content, padded with zeros,
+// they go through the buffer pool of the storage
+struct FilePager {
+    storage: FileStorage,
+    file_name: String,
+}
+
+impl<K, V> NodePager<K, V> for FilePager
+where
+    K: SerializeValue + DeserializeOwned,
+    V: SerializeValue + DeserializeOwned,
+{
+    fn read_page(&self, page_id: usize) -> Result<Page<K, V>, Box<dyn Error>> {
+        let offset = (page_id * INDEX_PAGE_SIZE) as u64;
+        let block = self.storage.read_page(&self.file_name, offset, INDEX_PAGE_SIZE)?;
+        let length = u32::from_le_bytes([block[0], block[1], block[2], block[3]]) as usize;
+        if length == 0 || length > INDEX_PAGE_SIZE - 4 {
+            return Err(format!("Invalid page {} in index file {}", page_id, self.file_name).into());
+        }
+        Ok(serde_json::from_slice(&block[4..4 + length])?)
+    }
+
+    fn encode_page(&self, page: &Page<K, V>) -> Result<Vec<u8>, Box<dyn Error>> {
+        let content = serde_json::to_vec(page)?;
+        if content.len() > INDEX_PAGE_SIZE - 4 {
+            return Err(format!(
+                "Index node of {} bytes does not fit in a page of {} bytes, use a lower order",
+                content.len(),
+                INDEX_PAGE_SIZE
+            )
+            .into());
+        }
+        let mut block = Vec::with_capacity(INDEX_PAGE_SIZE);
+        block.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        block.extend_from_slice(&content);
+        block.resize(INDEX_PAGE_SIZE, 0);
+        Ok(block)
+    }
+
+    fn write_page(&self, page_id: usize, block: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.storage
+            .write_page(&self.file_name, (page_id * INDEX_PAGE_SIZE) as u64, block)
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> BPlusTree<K, V> {
+    // tree living in memory only
+    pub fn new(order: usize) -> BPlusTree<K, V> {
+        BPlusTree {
+            nodes: Vec::new(),
+            free_nodes: Vec::new(),
+            root_node: None,
+            height: 0,
+            // a node must split in two non-empty halves
+            order: order.max(2),
+            pager: None,
+            dirty_nodes: BTreeSet::new(),
+        }
+    }
+
+    pub fn get_order(&self) -> usize {
+        self.order
+    }
+
+    pub fn get_root_node(&self) -> Option<&NodeType<K, V>> {
+        self.get_node(self.root_node?)
+    }
+
+    // None when the node does not exist or its page was not read yet
+    pub fn get_node(&self, node_id: NodeId) -> Option<&NodeType<K, V>> {
+        self.nodes.get(node_id)?.as_ref()
+    }
+
+    pub fn get_tree_height(&self) -> usize {
+        self.height
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root_node.is_none()
+    }
+
+    // add a key, returns the previous value when the key was already in the tree
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, Box<dyn Error>> {
+        let root = match self.root_node {
+            None => {
+                let mut leaf_node = LeafNode::new();
+                leaf_node.keys.push(key);
+                leaf_node.values.push(value);
+                self.root_node = Some(self.add_node(NodeType::Leaf(leaf_node)));
+                self.height = 1;
+                return Ok(None);
+            }
+            Some(root) => root,
+        };
+        // an existing key keeps its place, only its value changes
+        let leaf = self.find_leaf(&key)?.unwrap();
+        if let Ok(position) = self.leaf(leaf).keys.binary_search(&key) {
+            let leaf_node = self.leaf_mut(leaf);
+            return Ok(Some(std::mem::replace(&mut leaf_node.values[position], value)));
+        }
+        if let Some((separator, right_node)) = self.insert_into(root, key, value)? {
+            // the root split, the tree grows by one level
+            let mut new_root = InternalNode::new();
+            new_root.keys.push(separator);
+            new_root.children.push(root);
+            new_root.children.push(right_node);
+            self.root_node = Some(self.add_node(NodeType::Internal(new_root)));
+            self.height += 1;
+        }
+        Ok(None)
+    }
+
+    // remove a key, returns its value when the key was in the tree
+    pub fn remove(&mut self, key: &K) -> Result<Option<V>, Box<dyn Error>> {
+        let root = match self.root_node {
+            None => return Ok(None),
+            Some(root) => root,
+        };
+        let value = match self.remove_from(root, key)? {
+            None => return Ok(None),
+            Some(value) => value,
+        };
+        // the root may hold less keys than the other nodes, the tree shrinks once it is empty
+        match self.node(root) {
+            NodeType::Leaf(leaf_node) if leaf_node.keys.is_empty() => {
+                self.free_node(root);
+                self.root_node = None;
+                self.height = 0;
+            }
+            NodeType::Internal(internal_node) if internal_node.keys.is_empty() => {
+                let child = internal_node.children[0];
+                self.free_node(root);
+                self.root_node = Some(child);
+                self.height -= 1;
+                self.load(child)?;
+            }
+            _ => {}
+        }
+        Ok(Some(value))
+    }
+
+    // exact-match lookup
+    pub fn search(&mut self, key: &K) -> Result<Option<V>, Box<dyn Error>> {
+        let leaf = match self.find_leaf(key)? {
+            None => return Ok(None),
+            Some(leaf) => self.leaf(leaf),
+        };
+        Ok(leaf
+            .keys
+            .binary_search(key)
+            .ok()
+            .map(|position| leaf.values[position].clone()))
+    }
+
+    pub fn contains_key(&mut self, key: &K) -> Result<bool, Box<dyn Error>> {
+        Ok(self.search(key)?.is_some())
+    }
+
+    // entries within the bounds in key order, e.g. range(3..=7) or range((Excluded(3), Unbounded))
+    pub fn range<R: RangeBounds<K>>(&mut self, range: R) -> RangeIter<'_, K, V> {
+        RangeIter {
+            start: Some(range.start_bound().cloned()),
+            end: range.end_bound().cloned(),
+            tree: self,
+            leaf: None,
+            position: 0,
+        }
+    }
+
+    // every entry in key order
+    pub fn iter(&mut self) -> RangeIter<'_, K, V> {
+        self.range(..)
+    }
+
+    // write the changed nodes and the header to the index file, nothing to do for a tree in memory
+    pub fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        let pager = match &self.pager {
+            None => return Ok(()),
+            Some(pager) => pager,
+        };
+        let mut blocks = Vec::new();
+        for node_id in self.dirty_nodes.iter() {
+            if let Some(node) = &self.nodes[*node_id] {
+                blocks.push((node_id + 1, pager.encode_page(&Page::Node(node.clone()))?));
+            }
+        }
+        // free nodes are chained through their pages
+        for (i, node_id) in self.free_nodes.iter().enumerate() {
+            let next = self.free_nodes.get(i + 1).copied();
+            blocks.push((node_id + 1, pager.encode_page(&Page::Free { next })?));
+        }
+        let header = Page::Header {
+            order: self.order,
+            root_node: self.root_node,
+            height: self.height,
+            node_count: self.nodes.len(),
+            first_free: self.free_nodes.first().copied(),
+        };
+        blocks.push((0, pager.encode_page(&header)?));
+        for (page_id, block) in blocks {
+            pager.write_page(page_id, &block)?;
+        }
+        self.dirty_nodes.clear();
+        Ok(())
+    }
+
+    // check the structure of the tree: key order and bounds, node fill, leaf depth and sibling links
+    pub fn check_invariants(&mut self) -> Result<(), String> {
+        let root = match self.root_node {
+            None => return Ok(()),
+            Some(root) => root,
+        };
+        self.load_subtree(root).map_err(|e| e.to_string())?;
+        let mut leaves = Vec::new();
+        self.check_node(root, None, None, 1, &mut leaves)?;
+
+        if leaves.iter().any(|(_, depth)| *depth != self.height) {
+            return Err(format!("Leaves are not all at the height {} of the tree", self.height));
+        }
+        let mut leaf = Some(leaves[0].0);
+        for (expected, _) in leaves.iter() {
+            if leaf != Some(*expected) {
+                return Err(format!("Leaf {} is not linked in key order", expected));
+            }
+            leaf = self.leaf(*expected).next;
+        }
+        if leaf.is_some() {
+            return Err("Last leaf has a next sibling".to_string());
+        }
+        Ok(())
+    }
+
+    // read the page of a node that is not in memory yet
+    fn load(&mut self, node_id: NodeId) -> Result<(), Box<dyn Error>> {
+        if self.get_node(node_id).is_some() {
+            return Ok(());
+        }
+        let pager = match &self.pager {
+            Some(pager) if node_id < self.nodes.len() => pager,
+            _ => return Err(format!("Node {} does not exist", node_id).into()),
+        };
+        match pager.read_page(node_id + 1)? {
+            Page::Node(node) => {
+                self.nodes[node_id] = Some(node);
+                Ok(())
+            }
+            _ => Err(format!("Page {} does not hold a node", node_id + 1).into()),
+        }
+    }
+
+    fn load_subtree(&mut self, node_id: NodeId) -> Result<(), Box<dyn Error>> {
+        self.load(node_id)?;
+        if let NodeType::Internal(internal_node) = self.node(node_id) {
+            for child in internal_node.children.clone() {
+                self.load_subtree(child)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn node(&self, node_id: NodeId) -> &NodeType<K, V> {
+        match self.get_node(node_id) {
+            Some(node) => node,
+            None => panic!("Node {} is not loaded", node_id),
+        }
+    }
+
+    fn leaf(&self, node_id: NodeId) -> &LeafNode<K, V> {
+        match self.node(node_id) {
+            NodeType::Leaf(leaf_node) => leaf_node,
+            NodeType::Internal(_) => panic!("Node {} is not a leaf", node_id),
+        }
+    }
+
+    fn leaf_mut(&mut self, node_id: NodeId) -> &mut LeafNode<K, V> {
+        self.dirty_nodes.insert(node_id);
+        match self.nodes[node_id].as_mut() {
+            Some(NodeType::Leaf(leaf_node)) => leaf_node,
+            _ => panic!("Node {} is not a loaded leaf", node_id),
+        }
+    }
+
+    fn internal_mut(&mut self, node_id: NodeId) -> &mut InternalNode<K> {
+        self.dirty_nodes.insert(node_id);
+        match self.nodes[node_id].as_mut() {
+            Some(NodeType::Internal(internal_node)) => internal_node,
+            _ => panic!("Node {} is not a loaded internal node", node_id),
+        }
+    }
+
+    fn add_node(&mut self, node: NodeType<K, V>) -> NodeId {
+        let node_id = match self.free_nodes.pop() {
+            Some(node_id) => node_id,
+            None => {
+                self.nodes.push(None);
+                self.nodes.len() - 1
+            }
+        };
+        self.nodes[node_id] = Some(node);
+        self.dirty_nodes.insert(node_id);
+        node_id
+    }
+
+    fn free_node(&mut self, node_id: NodeId) {
+        self.nodes[node_id] = None;
+        self.dirty_nodes.remove(&node_id);
+        self.free_nodes.push(node_id);
+    }
+
+    // fewest keys a node other than the root may hold, what the smaller half of a split gets
+    fn min_keys(&self) -> usize {
+        self.order / 2
+    }
+
+    fn key_count(&self, node_id: NodeId) -> usize {
+        match self.node(node_id) {
+            NodeType::Leaf(leaf_node) => leaf_node.keys.len(),
+            NodeType::Internal(internal_node) => internal_node.keys.len(),
+        }
+    }
+
+    // move a loaded node out of the tree to change it along with another node
+    fn take_node(&mut self, node_id: NodeId) -> NodeType<K, V> {
+        self.dirty_nodes.insert(node_id);
+        match self.nodes[node_id].take() {
+            Some(node) => node,
+            None => panic!("Node {} is not loaded", node_id),
+        }
+    }
+
+    fn take_internal(&mut self, node_id: NodeId) -> InternalNode<K> {
+        match self.take_node(node_id) {
+            NodeType::Internal(internal_node) => internal_node,
+            NodeType::Leaf(_) => panic!("Node {} is not an internal node", node_id),
+        }
+    }
+
+    fn put_node(&mut self, node_id: NodeId, node: NodeType<K, V>) {
+        self.nodes[node_id] = Some(node);
+    }
+
+    // the leaf where the key is or would be, every node on the way is loaded
+    fn find_leaf(&mut self, key: &K) -> Result<Option<NodeId>, Box<dyn Error>> {
+        let mut node_id = match self.root_node {
+            None => return Ok(None),
+            Some(root) => root,
+        };
+        loop {
+            self.load(node_id)?;
+            match self.node(node_id) {
+                NodeType::Leaf(_) => return Ok(Some(node_id)),
+                NodeType::Internal(internal_node) => {
+                    node_id = internal_node.children[internal_node.keys.partition_point(|k| k <= key)];
+                }
+            }
+        }
+    }
+
+    fn first_leaf(&mut self) -> Result<Option<NodeId>, Box<dyn Error>> {
+        let mut node_id = match self.root_node {
+            None => return Ok(None),
+            Some(root) => root,
+        };
+        loop {
+            self.load(node_id)?;
+            match self.node(node_id) {
+                NodeType::Leaf(_) => return Ok(Some(node_id)),
+                NodeType::Internal(internal_node) => node_id = internal_node.children[0],
+            }
+        }
+    }
+
+    // insert below a node, returns the separator key and the new right node when the node splits
+    fn insert_into(&mut self, node_id: NodeId, key: K, value: V) -> Result<Option<(K, NodeId)>, Box<dyn Error>> {
+        self.load(node_id)?;
+        let order = self.order;
+        let (i, child_id) = match self.node(node_id) {
+            NodeType::Leaf(_) => {
+                let leaf_node = self.leaf_mut(node_id);
+                let position = leaf_node.keys.partition_point(|k| *k < key);
+                leaf_node.keys.insert(position, key);
+                leaf_node.values.insert(position, value);
+                if leaf_node.keys.len() <= order {
+                    return Ok(None);
+                }
+                let mid = leaf_node.keys.len() / 2;
+                let right_leaf = LeafNode {
+                    keys: leaf_node.keys.split_off(mid),
+                    values: leaf_node.values.split_off(mid),
+                    next: leaf_node.next,
+                };
+                let separator = right_leaf.keys[0].clone();
+                let right_node = self.add_node(NodeType::Leaf(right_leaf));
+                self.leaf_mut(node_id).next = Some(right_node);
+                return Ok(Some((separator, right_node)));
+            }
+            NodeType::Internal(internal_node) => {
+                let i = internal_node.keys.partition_point(|k| *k <= key);
+                (i, internal_node.children[i])
+            }
+        };
+
+        let (separator, right_child) = match self.insert_into(child_id, key, value)? {
+            None => return Ok(None),
+            Some(split) => split,
+        };
+        let internal_node = self.internal_mut(node_id);
+        internal_node.keys.insert(i, separator);
+        internal_node.children.insert(i + 1, right_child);
+        if internal_node.keys.len() <= order {
+            return Ok(None);
+        }
+        // the middle key moves up, it is not kept in either half
+        let mid = internal_node.keys.len() / 2;
+        let right_internal = InternalNode {
+            keys: internal_node.keys.split_off(mid + 1),
+            children: internal_node.children.split_off(mid + 1),
+        };
+        let separator = internal_node.keys.pop().unwrap();
+        Ok(Some((separator, self.add_node(NodeType::Internal(right_internal)))))
+    }
+
+    // remove below a node, the caller rebalances the node when it becomes underfull
+    fn remove_from(&mut self, node_id: NodeId, key: &K) -> Result<Option<V>, Box<dyn Error>> {
+        self.load(node_id)?;
+        let (i, child) = match self.node(node_id) {
+            NodeType::Leaf(leaf_node) => {
+                let position = match leaf_node.keys.binary_search(key) {
+                    Ok(position) => position,
+                    Err(_) => return Ok(None),
+                };
+                let leaf_node = self.leaf_mut(node_id);
+                leaf_node.keys.remove(position);
+                return Ok(Some(leaf_node.values.remove(position)));
+            }
+            NodeType::Internal(internal_node) => {
+                let i = internal_node.keys.partition_point(|k| k <= key);
+                (i, internal_node.children[i])
+            }
+        };
+        let value = match self.remove_from(child, key)? {
+            None => return Ok(None),
+            Some(value) => value,
+        };
+        // separators are left as they are, a removed key still splits the key space correctly
+        if self.key_count(child) < self.min_keys() {
+            self.rebalance(node_id, i)?;
+        }
+        Ok(Some(value))
+    }
+
+    // refill the underfull child i of a node from a sibling, or merge it with a sibling
+    fn rebalance(&mut self, node_id: NodeId, i: usize) -> Result<(), Box<dyn Error>> {
+        let children = match self.node(node_id) {
+            NodeType::Internal(internal_node) => internal_node.children.clone(),
+            NodeType::Leaf(_) => panic!("Node {} is not an internal node", node_id),
+        };
+        if i > 0 {
+            self.load(children[i - 1])?;
+        }
+        if i + 1 < children.len() {
+            self.load(children[i + 1])?;
+        }
+
+        let mut parent = self.take_internal(node_id);
+        let min_keys = self.min_keys();
+        if i > 0 && self.key_count(children[i - 1]) > min_keys {
+            self.borrow_from_left(&mut parent, i);
+        } else if i + 1 < children.len() && self.key_count(children[i + 1]) > min_keys {
+            self.borrow_from_right(&mut parent, i);
+        } else if i > 0 {
+            self.merge_children(&mut parent, i - 1);
+        } else {
+            self.merge_children(&mut parent, i);
+        }
+        self.put_node(node_id, NodeType::Internal(parent));
+        Ok(())
+    }
+
+    fn borrow_from_left(&mut self, parent: &mut InternalNode<K>, i: usize) {
+        let left = self.take_node(parent.children[i - 1]);
+        let child = self.take_node(parent.children[i]);
+        let (left, child) = match (left, child) {
+            (NodeType::Leaf(mut left), NodeType::Leaf(mut child)) => {
+                child.keys.insert(0, left.keys.pop().unwrap());
+                child.values.insert(0, left.values.pop().unwrap());
+                parent.keys[i - 1] = child.keys[0].clone();
+                (NodeType::Leaf(left), NodeType::Leaf(child))
+            }
+            (NodeType::Internal(mut left), NodeType::Internal(mut child)) => {
+                // the separator comes down, the last key of the left sibling goes up
+                child.keys.insert(0, parent.keys[i - 1].clone());
+                child.children.insert(0, left.children.pop().unwrap());
+                parent.keys[i - 1] = left.keys.pop().unwrap();
+                (NodeType::Internal(left), NodeType::Internal(child))
+            }
+            _ => unreachable!("Siblings are at the same depth"),
+        };
+        self.put_node(parent.children[i - 1], left);
+        self.put_node(parent.children[i], child);
+    }
+
+    fn borrow_from_right(&mut self, parent: &mut InternalNode<K>, i: usize) {
+        let child = self.take_node(parent.children[i]);
+        let right = self.take_node(parent.children[i + 1]);
+        let (child, right) = match (child, right) {
+            (NodeType::Leaf(mut child), NodeType::Leaf(mut right)) => {
+                child.keys.push(right.keys.remove(0));
+                child.values.push(right.values.remove(0));
+                parent.keys[i] = right.keys[0].clone();
+                (NodeType::Leaf(child), NodeType::Leaf(right))
+            }
+            (NodeType::Internal(mut child), NodeType::Internal(mut right)) => {
+                // the separator comes down, the first key of the right sibling goes up
+                child.keys.push(parent.keys[i].clone());
+                child.children.push(right.children.remove(0));
+                parent.keys[i] = right.keys.remove(0);
+                (NodeType::Internal(child), NodeType::Internal(right))
+            }
+            _ => unreachable!("Siblings are at the same depth"),
+        };
+        self.put_node(parent.children[i], child);
+        self.put_node(parent.children[i + 1], right);
+    }
+
+    // merge the children i and i + 1 of a node into child i
+    fn merge_children(&mut self, parent: &mut InternalNode<K>, i: usize) {
+        let separator = parent.keys.remove(i);
+        let right_id = parent.children.remove(i + 1);
+        let left = self.take_node(parent.children[i]);
+        let right = self.take_node(right_id);
+        let merged = match (left, right) {
+            (NodeType::Leaf(mut left), NodeType::Leaf(right)) => {
+                left.keys.extend(right.keys);
+                left.values.extend(right.values);
+                left.next = right.next;
+                NodeType::Leaf(left)
+            }
+            (NodeType::Internal(mut left), NodeType::Internal(right)) => {
+                left.keys.push(separator);
+                left.keys.extend(right.keys);
+                left.children.extend(right.children);
+                NodeType::Internal(left)
+            }
+            _ => unreachable!("Siblings are at the same depth"),
+        };
+        self.put_node(parent.children[i], merged);
+        self.free_node(right_id);
+    }
+
+    // check a loaded subtree whose keys must be within [lower, upper), leaves are collected in key order
+    fn check_node(
+        &self,
+        node_id: NodeId,
+        lower: Option<&K>,
+        upper: Option<&K>,
+        depth: usize,
+        leaves: &mut Vec<(NodeId, usize)>,
+    ) -> Result<(), String> {
+        let is_root = self.root_node == Some(node_id);
+        let keys = match self.node(node_id) {
+            NodeType::Leaf(leaf_node) => &leaf_node.keys,
+            NodeType::Internal(internal_node) => &internal_node.keys,
+        };
+        if keys.len() > self.order {
+            return Err(format!("Node {} holds {} keys, more than the order {}", node_id, keys.len(), self.order));
+        }
+        if !is_root && keys.len() < self.min_keys() {
+            return Err(format!("Node {} holds {} keys, less than {}", node_id, keys.len(), self.min_keys()));
+        }
+        if keys.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(format!("Keys of node {} are not sorted", node_id));
+        }
+        let out_of_bounds = |key: &K| lower.is_some_and(|lower| key < lower) || upper.is_some_and(|upper| key >= upper);
+        if keys.iter().any(out_of_bounds) {
+            return Err(format!("Node {} holds keys out of its parent bounds", node_id));
+        }
+
+        match self.node(node_id) {
+            NodeType::Leaf(leaf_node) => {
+                if leaf_node.keys.is_empty() {
+                    return Err(format!("Leaf {} is empty", node_id));
+                }
+                if leaf_node.values.len() != leaf_node.keys.len() {
+                    return Err(format!("Leaf {} has {} keys and {} values", node_id, leaf_node.keys.len(), leaf_node.values.len()));
+                }
+                leaves.push((node_id, depth));
+            }
+            NodeType::Internal(internal_node) => {
+                if internal_node.keys.is_empty() {
+                    return Err(format!("Internal node {} has no keys", node_id));
+                }
+                if internal_node.children.len() != internal_node.keys.len() + 1 {
+                    return Err(format!("Internal node {} has {} keys and {} children",
+                        node_id, internal_node.keys.len(), internal_node.children.len()));
+                }
+                for (i, child) in internal_node.children.iter().enumerate() {
+                    let child_lower = if i == 0 { lower } else { Some(&internal_node.keys[i - 1]) };
+                    let child_upper = internal_node.keys.get(i).or(upper);
+                    self.check_node(*child, child_lower, child_upper, depth + 1, leaves)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<K, V> BPlusTree<K, V>
+where
+    K: Ord + Clone + SerializeValue + DeserializeOwned + 'static,
+    V: Clone + SerializeValue + DeserializeOwned + 'static,
+{
+    // create an empty tree in a new index file
+    pub fn create(storage: &FileStorage, file_name: &str, order: usize) -> Result<BPlusTree<K, V>, Box<dyn Error>> {
+        storage.create_file(file_name)?;
+        let mut tree = BPlusTree::new(order);
+        tree.pager = Some(Box::new(FilePager {
+            storage: storage.clone(),
+            file_name: file_name.to_string(),
+        }));
+        tree.flush()?;
+        Ok(tree)
+    }
+
+    // open the tree of an index file, only the header, the free pages and the root are read
+    pub fn open(storage: &FileStorage, file_name: &str) -> Result<BPlusTree<K, V>, Box<dyn Error>> {
+        let pager = FilePager {
+            storage: storage.clone(),
+            file_name: file_name.to_string(),
+        };
+        let (order, root_node, height, node_count, first_free) = match NodePager::<K, V>::read_page(&pager, 0)? {
+            Page::Header { order, root_node, height, node_count, first_free } => (order, root_node, height, node_count, first_free),
+            _ => return Err(format!("File {} is not an index file", file_name).into()),
+        };
+        let mut free_nodes = Vec::new();
+        let mut next = first_free;
+        while let Some(node_id) = next {
+            match NodePager::<K, V>::read_page(&pager, node_id + 1)? {
+                Page::Free { next: next_free } => next = next_free,
+                _ => return Err(format!("Page {} is not a free page", node_id + 1).into()),
+            }
+            free_nodes.push(node_id);
+        }
+
+        let mut tree = BPlusTree {
+            nodes: (0..node_count).map(|_| None).collect(),
+            free_nodes,
+            root_node,
+            height,
+            order,
+            pager: Some(Box::new(pager)),
+            dirty_nodes: BTreeSet::new(),
+        };
+        if let Some(root) = root_node {
+            tree.load(root)?;
+        }
+        Ok(tree)
+    }
+}
+
+// ordered iteration over a key range, following the links between leaves.
+// Pages of an index file are read as the iteration reaches them.
+pub struct RangeIter<'a, K, V> {
+    tree: &'a mut BPlusTree<K, V>,
+    // lower bound until the first leaf is found
+    start: Option<Bound<K>>,
+    end: Bound<K>,
+    leaf: Option<NodeId>,
+    position: usize,
+}
+
+impl<K: Ord + Clone, V: Clone> RangeIter<'_, K, V> {
+    fn seek(&mut self, start: Bound<K>) -> Result<(), Box<dyn Error>> {
+        self.leaf = match &start {
+            Bound::Included(key) | Bound::Excluded(key) => self.tree.find_leaf(key)?,
+            Bound::Unbounded => self.tree.first_leaf()?,
+        };
+        if let Some(leaf) = self.leaf {
+            let keys = &self.tree.leaf(leaf).keys;
+            self.position = match &start {
+                Bound::Included(key) => keys.partition_point(|k| k < key),
+                Bound::Excluded(key) => keys.partition_point(|k| k <= key),
+                Bound::Unbounded => 0,
+            };
+        }
+        Ok(())
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Iterator for RangeIter<'_, K, V> {
+    type Item = Result<(K, V), Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(start) = self.start.take() {
+            if let Err(e) = self.seek(start) {
+                self.leaf = None;
+                return Some(Err(e));
+            }
+        }
+        loop {
+            let leaf_id = self.leaf?;
+            if let Err(e) = self.tree.load(leaf_id) {
+                self.leaf = None;
+                return Some(Err(e));
+            }
+            let leaf = self.tree.leaf(leaf_id);
+            if self.position < leaf.keys.len() {
+                let key = &leaf.keys[self.position];
+                let in_range = match &self.end {
+                    Bound::Included(end) => key <= end,
+                    Bound::Excluded(end) => key < end,
+                    Bound::Unbounded => true,
+                };
+                if !in_range {
+                    self.leaf = None;
+                    return None;
+                }
+                let entry = (key.clone(), leaf.values[self.position].clone());
+                self.position += 1;
+                return Some(Ok(entry));
+            }
+            self.leaf = leaf.next;
+            self.position = 0;
+        }
+    }
+}
+
+impl<K, V> LeafNode<K, V> {
+    pub fn new() -> Self {
+        Self {
+            keys: Vec::new(),
+            values: Vec::new(),
+            next: None,
+        }
+    }
+
+    pub fn get_keys(&self) -> &Vec<K> {
+        &self.keys
+    }
+
+    pub fn get_values(&self) -> &Vec<V> {
+        &self.values
+    }
+
+    // right sibling leaf
+    pub fn get_next(&self) -> Option<NodeId> {
+        self.next
+    }
+}
+
+impl<K, V> Default for LeafNode<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K> InternalNode<K> {
+    pub fn new() -> Self {
+        Self {
+            keys: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn get_keys(&self) -> &Vec<K> {
+        &self.keys
+    }
+
+    pub fn get_children(&self) -> &Vec<NodeId> {
+        &self.children
+    }
+}
+
+impl<K> Default for InternalNode<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,295 @@
+// evaluator is a module that computes parsed expressions against the values of a record
+
+use std::cmp::Ordering;
+use std::error::Error;
+
+use crate::database::abstraction::{Record, Value};
+use crate::database::parser::{BinaryOperator, Expression, UnaryOperator};
+
+// Check if a record satisfies a WHERE predicate, an unknown (NULL) result does not match
+pub fn matches(predicate: &Expression, record: &Record) -> Result<bool, Box<dyn Error>> {
+    let result = evaluate(predicate, record)?;
+    Ok(to_boolean(&result)? == Some(true))
+}
+
+// Check if a record satisfies a CHECK constraint, only a false result is a violation
+pub fn satisfies(check: &Expression, record: &Record) -> Result<bool, Box<dyn Error>> {
+    let result = evaluate(check, record)?;
+    Ok(to_boolean(&result)? != Some(false))
+}
+
+// Compute the value of an expression for a record, booleans are integers 1 and 0
+pub fn evaluate(expression: &Expression, record: &Record) -> Result<Value, Box<dyn Error>> {
+    match expression {
+        Expression::Literal(literal) => Ok(Value::from_literal(literal)),
+        Expression::Column(name) => match record.get_value(name) {
+            Some((_, value)) => Ok(value.clone()),
+            None => Err(format!("Unknown column {}", name).into()),
+        },
+        Expression::Unary { operator, operand } => {
+            let value = evaluate(operand, record)?;
+            evaluate_unary(*operator, value)
+        }
+        Expression::Binary {
+            left,
+            operator,
+            right,
+        } => match operator {
+            BinaryOperator::And => {
+                let left = to_boolean(&evaluate(left, record)?)?;
+                if left == Some(false) {
+                    return Ok(boolean(false));
+                }
+                let right = to_boolean(&evaluate(right, record)?)?;
+                Ok(match (left, right) {
+                    (_, Some(false)) => boolean(false),
+                    (Some(true), Some(true)) => boolean(true),
+                    _ => Value::Null,
+                })
+            }
+            BinaryOperator::Or => {
+                let left = to_boolean(&evaluate(left, record)?)?;
+                if left == Some(true) {
+                    return Ok(boolean(true));
+                }
+                let right = to_boolean(&evaluate(right, record)?)?;
+                Ok(match (left, right) {
+                    (_, Some(true)) => boolean(true),
+                    (Some(false), Some(false)) => boolean(false),
+                    _ => Value::Null,
+                })
+            }
+            _ => {
+                let left = evaluate(left, record)?;
+                let right = evaluate(right, record)?;
+                evaluate_binary(&left, *operator, &right)
+            }
+        },
+        Expression::IsNull { operand, negated } => {
+            let is_null = evaluate(operand, record)?.is_null();
+            Ok(boolean(is_null != *negated))
+        }
+        Expression::InList {
+            operand,
+            list,
+            negated,
+        } => {
+            let value = evaluate(operand, record)?;
+            if value.is_null() {
+                return Ok(Value::Null);
+            }
+            let mut has_null = false;
+            for item in list {
+                let item = evaluate(item, record)?;
+                match compare(&value, &item) {
+                    None => has_null = true,
+                    Some(Ordering::Equal) => return Ok(boolean(!*negated)),
+                    Some(_) => {}
+                }
+            }
+            if has_null {
+                Ok(Value::Null)
+            } else {
+                Ok(boolean(*negated))
+            }
+        }
+        Expression::Between {
+            operand,
+            low,
+            high,
+            negated,
+        } => {
+            let value = evaluate(operand, record)?;
+            let low = evaluate(low, record)?;
+            let high = evaluate(high, record)?;
+            let above_low = compare(&value, &low).map(|ordering| ordering != Ordering::Less);
+            let below_high = compare(&value, &high).map(|ordering| ordering != Ordering::Greater);
+            let result = match (above_low, below_high) {
+                (Some(false), _) | (_, Some(false)) => boolean(false),
+                (Some(true), Some(true)) => boolean(true),
+                _ => Value::Null,
+            };
+            if *negated {
+                evaluate_unary(UnaryOperator::Not, result)
+            } else {
+                Ok(result)
+            }
+        }
+        Expression::Like {
+            operand,
+            pattern,
+            negated,
+        } => {
+            let value = evaluate(operand, record)?;
+            let pattern = evaluate(pattern, record)?;
+            match (to_text(&value), to_text(&pattern)) {
+                (Some(value), Some(pattern)) => {
+                    let value: Vec<char> = value.to_lowercase().chars().collect();
+                    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+                    Ok(boolean(like(&value, &pattern) != *negated))
+                }
+                _ => Ok(Value::Null),
+            }
+        }
+    }
+}
+
+// Compare two values, None when one of them is NULL.
+// Text compared with a number is read as a number when it holds one.
+pub fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Null, _) | (_, Value::Null) => None,
+        (Value::Text(text), Value::Integer(_) | Value::Real(_)) => {
+            match text.trim().parse::<f64>() {
+                Ok(number) => Some(Value::Real(number).cmp(right)),
+                Err(_) => Some(left.cmp(right)),
+            }
+        }
+        (Value::Integer(_) | Value::Real(_), Value::Text(_)) => {
+            compare(right, left).map(Ordering::reverse)
+        }
+        _ => Some(left.cmp(right)),
+    }
+}
+
+fn boolean(value: bool) -> Value {
+    Value::Integer(value as i64)
+}
+
+fn to_boolean(value: &Value) -> Result<Option<bool>, Box<dyn Error>> {
+    match value {
+        Value::Null => Ok(None),
+        Value::Integer(value) => Ok(Some(*value != 0)),
+        Value::Real(value) => Ok(Some(*value != 0.0)),
+        _ => Err(format!("Value {} is not a boolean", value).into()),
+    }
+}
+
+fn to_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::Integer(value) => Some(value.to_string()),
+        Value::Real(value) => Some(value.to_string()),
+        Value::Text(value) => Some(value.clone()),
+        Value::Blob(value) => Some(String::from_utf8_lossy(value).to_string()),
+    }
+}
+
+fn evaluate_unary(operator: UnaryOperator, value: Value) -> Result<Value, Box<dyn Error>> {
+    match (operator, value) {
+        (_, Value::Null) => Ok(Value::Null),
+        (UnaryOperator::Not, value) => Ok(boolean(!to_boolean(&value)?.unwrap_or(false))),
+        (UnaryOperator::Plus, value @ (Value::Integer(_) | Value::Real(_))) => Ok(value),
+        (UnaryOperator::Minus, Value::Integer(value)) => match value.checked_neg() {
+            Some(value) => Ok(Value::Integer(value)),
+            None => Err("Integer overflow".into()),
+        },
+        (UnaryOperator::Minus, Value::Real(value)) => Ok(Value::Real(-value)),
+        (operator, value) => Err(format!("Cannot apply {:?} to {}", operator, value).into()),
+    }
+}
+
+fn evaluate_binary(
+    left: &Value,
+    operator: BinaryOperator,
+    right: &Value,
+) -> Result<Value, Box<dyn Error>> {
+    if left.is_null() || right.is_null() {
+        return Ok(Value::Null);
+    }
+    let comparison = |expected: fn(Ordering) -> bool| match compare(left, right) {
+        None => Value::Null,
+        Some(ordering) => boolean(expected(ordering)),
+    };
+    match operator {
+        BinaryOperator::Equal => Ok(comparison(|ordering| ordering == Ordering::Equal)),
+        BinaryOperator::NotEqual => Ok(comparison(|ordering| ordering != Ordering::Equal)),
+        BinaryOperator::Less => Ok(comparison(|ordering| ordering == Ordering::Less)),
+        BinaryOperator::LessOrEqual => Ok(comparison(|ordering| ordering != Ordering::Greater)),
+        BinaryOperator::Greater => Ok(comparison(|ordering| ordering == Ordering::Greater)),
+        BinaryOperator::GreaterOrEqual => Ok(comparison(|ordering| ordering != Ordering::Less)),
+        BinaryOperator::Concat => Ok(Value::Text(
+            to_text(left).unwrap_or_default() + &to_text(right).unwrap_or_default(),
+        )),
+        BinaryOperator::And | BinaryOperator::Or => {
+            Err(format!("Unexpected logical operator {:?}", operator).into())
+        }
+        _ => evaluate_arithmetic(left, operator, right),
+    }
+}
+
+fn evaluate_arithmetic(
+    left: &Value,
+    operator: BinaryOperator,
+    right: &Value,
+) -> Result<Value, Box<dyn Error>> {
+    if let (Value::Integer(left), Value::Integer(right)) = (left, right) {
+        let result = match operator {
+            BinaryOperator::Plus => left.checked_add(*right),
+            BinaryOperator::Minus => left.checked_sub(*right),
+            BinaryOperator::Multiply => left.checked_mul(*right),
+            BinaryOperator::Divide | BinaryOperator::Modulo if *right == 0 => {
+                return Err("Division by zero".into())
+            }
+            BinaryOperator::Divide => left.checked_div(*right),
+            BinaryOperator::Modulo => left.checked_rem(*right),
+            _ => return Err(format!("Unexpected operator {:?}", operator).into()),
+        };
+        return match result {
+            Some(result) => Ok(Value::Integer(result)),
+            None => Err("Integer overflow".into()),
+        };
+    }
+    let to_number = |value: &Value| match value {
+        Value::Integer(value) => Some(*value as f64),
+        Value::Real(value) => Some(*value),
+        _ => None,
+    };
+    let (left_number, right_number) = match (to_number(left), to_number(right)) {
+        (Some(left), Some(right)) => (left, right),
+        _ => {
+            return Err(format!("Cannot apply {:?} to {} and {}", operator, left, right).into())
+        }
+    };
+    let result = match operator {
+        BinaryOperator::Plus => left_number + right_number,
+        BinaryOperator::Minus => left_number - right_number,
+        BinaryOperator::Multiply => left_number * right_number,
+        BinaryOperator::Divide | BinaryOperator::Modulo if right_number == 0.0 => {
+            return Err("Division by zero".into())
+        }
+        BinaryOperator::Divide => left_number / right_number,
+        BinaryOperator::Modulo => left_number % right_number,
+        _ => return Err(format!("Unexpected operator {:?}", operator).into()),
+    };
+    Ok(Value::Real(result))
+}
+
+// SQL LIKE matching: '%' matches any sequence of characters, '_' exactly one.
+// A mismatch goes back to the last '%' only, taking one more character into it
+fn like(value: &[char], pattern: &[char]) -> bool {
+    let (mut v, mut p) = (0, 0);
+    // position of the last '%' in the pattern and of the value where it stopped matching
+    let mut wildcard: Option<(usize, usize)> = None;
+    while v < value.len() {
+        match pattern.get(p) {
+            Some('%') => {
+                wildcard = Some((p, v));
+                p += 1;
+            }
+            Some(c) if *c == '_' || *c == value[v] => {
+                v += 1;
+                p += 1;
+            }
+            _ => match wildcard {
+                Some((star, matched)) => {
+                    wildcard = Some((star, matched + 1));
+                    p = star + 1;
+                    v = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '%')
+}
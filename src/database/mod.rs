@@ -1 +1,7 @@
-pub mod abstraction;
\ No newline at end of file
+pub mod abstraction;
+pub mod btree;
+pub mod evaluator;
+pub mod parser;
+pub mod planner;
+pub mod shared;
+pub mod transaction;
\ No newline at end of file
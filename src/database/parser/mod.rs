@@ -0,0 +1,1579 @@
+// SQL parser: turns the text of a `Query` into a statement AST
+
+use std::fmt;
+
+use crate::database::abstraction::{DataType, ReferentialAction};
+
+// Default length of a TEXT column declared without an explicit size
+pub const DEFAULT_TEXT_LENGTH: u16 = u16::MAX;
+
+// Syntax error with the position (1-based line and column) where it happened
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    message: String,
+    line: usize,
+    column: usize,
+}
+
+impl ParseError {
+    pub fn new(message: &str, line: usize, column: usize) -> ParseError {
+        ParseError {
+            message: message.to_string(),
+            line,
+            column,
+        }
+    }
+
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn get_line(&self) -> usize {
+        self.line
+    }
+
+    pub fn get_column(&self) -> usize {
+        self.column
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.message, self.line, self.column
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Statements
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Select {
+        projection: Vec<SelectItem>,
+        table: String,
+        selection: Option<Expression>,
+        order_by: Vec<OrderByItem>,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    },
+    Insert {
+        table: String,
+        columns: Vec<String>,
+        rows: Vec<Vec<Expression>>,
+    },
+    Update {
+        table: String,
+        assignments: Vec<(String, Expression)>,
+        selection: Option<Expression>,
+    },
+    Delete {
+        table: String,
+        selection: Option<Expression>,
+    },
+    CreateTable {
+        table: String,
+        if_not_exists: bool,
+        columns: Vec<ColumnDefinition>,
+    },
+    DropTable {
+        table: String,
+        if_exists: bool,
+    },
+    AlterTable {
+        table: String,
+        actions: Vec<AlterTableAction>,
+    },
+    CreateIndex {
+        name: String,
+        table: String,
+        if_not_exists: bool,
+        columns: Vec<String>,
+    },
+    DropIndex {
+        name: String,
+        if_exists: bool,
+    },
+    CreateDatabase {
+        name: String,
+        if_not_exists: bool,
+    },
+    DropDatabase {
+        name: String,
+        if_exists: bool,
+    },
+    // plan of a statement instead of its result
+    Explain(Box<Statement>),
+    // transaction control
+    Begin,
+    Commit,
+    Rollback,
+}
+
+impl Statement {
+    // Name of the table the statement works on, if any
+    pub fn get_table_name(&self) -> Option<&str> {
+        match self {
+            Statement::Select { table, .. }
+            | Statement::Insert { table, .. }
+            | Statement::Update { table, .. }
+            | Statement::Delete { table, .. }
+            | Statement::CreateTable { table, .. }
+            | Statement::DropTable { table, .. }
+            | Statement::AlterTable { table, .. }
+            | Statement::CreateIndex { table, .. } => Some(table),
+            Statement::Explain(statement) => statement.get_table_name(),
+            Statement::DropIndex { .. }
+            | Statement::CreateDatabase { .. }
+            | Statement::DropDatabase { .. }
+            | Statement::Begin
+            | Statement::Commit
+            | Statement::Rollback => None,
+        }
+    }
+
+    // WHERE clause of the statement, if any
+    pub fn get_selection(&self) -> Option<&Expression> {
+        match self {
+            Statement::Select { selection, .. }
+            | Statement::Update { selection, .. }
+            | Statement::Delete { selection, .. } => selection.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectItem {
+    Wildcard,
+    Expression {
+        expression: Expression,
+        alias: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderByItem {
+    pub expression: Expression,
+    pub ascending: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnDefinition {
+    pub name: String,
+    pub data_type: DataType,
+    pub constraints: Vec<ColumnConstraint>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnConstraint {
+    PrimaryKey,
+    AutoIncrement,
+    NotNull,
+    Null,
+    Unique,
+    Default(Expression),
+    Check(Expression),
+    // the referenced column defaults to the primary key of the referenced table
+    References {
+        table: String,
+        column: Option<String>,
+        on_delete: ReferentialAction,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlterTableAction {
+    AddColumn(ColumnDefinition),
+    DropColumn(String),
+    RenameColumn { old_name: String, new_name: String },
+    SetNotNull(String),
+    DropNotNull(String),
+}
+
+// Expressions
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Literal(Literal),
+    Column(String),
+    Unary {
+        operator: UnaryOperator,
+        operand: Box<Expression>,
+    },
+    Binary {
+        left: Box<Expression>,
+        operator: BinaryOperator,
+        right: Box<Expression>,
+    },
+    IsNull {
+        operand: Box<Expression>,
+        negated: bool,
+    },
+    InList {
+        operand: Box<Expression>,
+        list: Vec<Expression>,
+        negated: bool,
+    },
+    Between {
+        operand: Box<Expression>,
+        low: Box<Expression>,
+        high: Box<Expression>,
+        negated: bool,
+    },
+    Like {
+        operand: Box<Expression>,
+        pattern: Box<Expression>,
+        negated: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOperator {
+    Not,
+    Minus,
+    Plus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Or,
+    And,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
+    Modulo,
+    Concat,
+}
+
+// SQL text of an expression, parentheses are only added where precedence requires them
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::Literal(literal) => write!(f, "{}", literal),
+            Expression::Column(name) => write_identifier(f, name),
+            Expression::Unary { operator, operand } => {
+                let (text, minimum) = match operator {
+                    UnaryOperator::Not => ("NOT ", PRECEDENCE_NOT),
+                    // signed operands are parenthesized so that "--" never starts a comment
+                    UnaryOperator::Minus => ("-", PRECEDENCE_ATOM),
+                    UnaryOperator::Plus => ("+", PRECEDENCE_ATOM),
+                };
+                write!(f, "{}", text)?;
+                write_operand(f, operand, minimum)
+            }
+            Expression::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let precedence = binary_precedence(*operator);
+                // comparisons do not chain, other operators are left associative
+                let left_minimum = if precedence == PRECEDENCE_COMPARISON {
+                    precedence + 1
+                } else {
+                    precedence
+                };
+                write_operand(f, left, left_minimum)?;
+                write!(f, " {} ", binary_operator_text(*operator))?;
+                write_operand(f, right, precedence + 1)
+            }
+            Expression::IsNull { operand, negated } => {
+                write_operand(f, operand, PRECEDENCE_ADDITIVE)?;
+                write!(f, "{}", if *negated { " IS NOT NULL" } else { " IS NULL" })
+            }
+            Expression::InList {
+                operand,
+                list,
+                negated,
+            } => {
+                write_operand(f, operand, PRECEDENCE_ADDITIVE)?;
+                write!(f, "{}", if *negated { " NOT IN (" } else { " IN (" })?;
+                for (i, item) in list.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, ")")
+            }
+            Expression::Between {
+                operand,
+                low,
+                high,
+                negated,
+            } => {
+                write_operand(f, operand, PRECEDENCE_ADDITIVE)?;
+                write!(f, "{}", if *negated { " NOT BETWEEN " } else { " BETWEEN " })?;
+                write_operand(f, low, PRECEDENCE_ADDITIVE)?;
+                write!(f, " AND ")?;
+                write_operand(f, high, PRECEDENCE_ADDITIVE)
+            }
+            Expression::Like {
+                operand,
+                pattern,
+                negated,
+            } => {
+                write_operand(f, operand, PRECEDENCE_ADDITIVE)?;
+                write!(f, "{}", if *negated { " NOT LIKE " } else { " LIKE " })?;
+                write_operand(f, pattern, PRECEDENCE_ADDITIVE)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Null => write!(f, "NULL"),
+            Literal::Boolean(value) => write!(f, "{}", if *value { "TRUE" } else { "FALSE" }),
+            Literal::Integer(value) => write!(f, "{}", value),
+            // debug formatting keeps the decimal point of whole numbers
+            Literal::Real(value) => write!(f, "{:?}", value),
+            Literal::Text(value) => write!(f, "'{}'", value.replace('\'', "''")),
+            Literal::Blob(value) => {
+                write!(f, "X'")?;
+                for byte in value {
+                    write!(f, "{:02X}", byte)?;
+                }
+                write!(f, "'")
+            }
+        }
+    }
+}
+
+const PRECEDENCE_OR: u8 = 1;
+const PRECEDENCE_AND: u8 = 2;
+const PRECEDENCE_NOT: u8 = 3;
+const PRECEDENCE_COMPARISON: u8 = 4;
+const PRECEDENCE_ADDITIVE: u8 = 5;
+const PRECEDENCE_MULTIPLICATIVE: u8 = 6;
+const PRECEDENCE_UNARY: u8 = 7;
+const PRECEDENCE_ATOM: u8 = 8;
+
+fn binary_precedence(operator: BinaryOperator) -> u8 {
+    match operator {
+        BinaryOperator::Or => PRECEDENCE_OR,
+        BinaryOperator::And => PRECEDENCE_AND,
+        BinaryOperator::Equal
+        | BinaryOperator::NotEqual
+        | BinaryOperator::Less
+        | BinaryOperator::LessOrEqual
+        | BinaryOperator::Greater
+        | BinaryOperator::GreaterOrEqual => PRECEDENCE_COMPARISON,
+        BinaryOperator::Plus | BinaryOperator::Minus | BinaryOperator::Concat => {
+            PRECEDENCE_ADDITIVE
+        }
+        BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo => {
+            PRECEDENCE_MULTIPLICATIVE
+        }
+    }
+}
+
+fn binary_operator_text(operator: BinaryOperator) -> &'static str {
+    match operator {
+        BinaryOperator::Or => "OR",
+        BinaryOperator::And => "AND",
+        BinaryOperator::Equal => "=",
+        BinaryOperator::NotEqual => "<>",
+        BinaryOperator::Less => "<",
+        BinaryOperator::LessOrEqual => "<=",
+        BinaryOperator::Greater => ">",
+        BinaryOperator::GreaterOrEqual => ">=",
+        BinaryOperator::Plus => "+",
+        BinaryOperator::Minus => "-",
+        BinaryOperator::Multiply => "*",
+        BinaryOperator::Divide => "/",
+        BinaryOperator::Modulo => "%",
+        BinaryOperator::Concat => "||",
+    }
+}
+
+fn expression_precedence(expression: &Expression) -> u8 {
+    match expression {
+        Expression::Literal(Literal::Integer(value)) if *value < 0 => PRECEDENCE_UNARY,
+        Expression::Literal(Literal::Real(value)) if *value < 0.0 => PRECEDENCE_UNARY,
+        Expression::Literal(_) | Expression::Column(_) => PRECEDENCE_ATOM,
+        Expression::Unary {
+            operator: UnaryOperator::Not,
+            ..
+        } => PRECEDENCE_NOT,
+        Expression::Unary { .. } => PRECEDENCE_UNARY,
+        Expression::Binary { operator, .. } => binary_precedence(*operator),
+        Expression::IsNull { .. }
+        | Expression::InList { .. }
+        | Expression::Between { .. }
+        | Expression::Like { .. } => PRECEDENCE_COMPARISON,
+    }
+}
+
+fn write_operand(f: &mut fmt::Formatter<'_>, operand: &Expression, minimum: u8) -> fmt::Result {
+    if expression_precedence(operand) < minimum {
+        write!(f, "({})", operand)
+    } else {
+        write!(f, "{}", operand)
+    }
+}
+
+fn write_identifier(f: &mut fmt::Formatter<'_>, name: &str) -> fmt::Result {
+    let is_plain = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && !RESERVED_WORDS
+            .iter()
+            .any(|reserved| name.eq_ignore_ascii_case(reserved));
+    if is_plain {
+        write!(f, "{}", name)
+    } else {
+        write!(f, "\"{}\"", name.replace('"', "\"\""))
+    }
+}
+
+// Parse a script made of one or more statements separated by semicolons
+pub fn parse(sql: &str) -> Result<Vec<Statement>, ParseError> {
+    let mut parser = Parser::new(sql)?;
+    let mut statements = Vec::new();
+    loop {
+        while parser.consume_symbol(Symbol::Semicolon) {}
+        if parser.is_at_end() {
+            break;
+        }
+        statements.push(parser.parse_statement()?);
+        if !parser.is_at_end() && !parser.check_symbol(Symbol::Semicolon) {
+            return Err(parser.unexpected("';' or end of input"));
+        }
+    }
+    Ok(statements)
+}
+
+// Parse exactly one statement, an optional trailing semicolon is allowed
+pub fn parse_statement(sql: &str) -> Result<Statement, ParseError> {
+    let mut parser = Parser::new(sql)?;
+    if parser.is_at_end() {
+        return Err(parser.unexpected("a statement"));
+    }
+    let statement = parser.parse_statement()?;
+    parser.consume_symbol(Symbol::Semicolon);
+    if !parser.is_at_end() {
+        return Err(parser.unexpected("end of input"));
+    }
+    Ok(statement)
+}
+
+// Parse a standalone expression, such as a WHERE predicate
+pub fn parse_expression(sql: &str) -> Result<Expression, ParseError> {
+    let mut parser = Parser::new(sql)?;
+    let expression = parser.parse_expression()?;
+    if !parser.is_at_end() {
+        return Err(parser.unexpected("end of input"));
+    }
+    Ok(expression)
+}
+
+// Lexer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Symbol {
+    LeftParenthesis,
+    RightParenthesis,
+    Comma,
+    Semicolon,
+    Asterisk,
+    Plus,
+    Minus,
+    Slash,
+    Percent,
+    Concat,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Word(String),
+    QuotedIdentifier(String),
+    Text(String),
+    Integer(i64),
+    Real(f64),
+    Blob(Vec<u8>),
+    Symbol(Symbol),
+    End,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    line: usize,
+    column: usize,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match &self.kind {
+            TokenKind::Word(word) => format!("'{}'", word),
+            TokenKind::QuotedIdentifier(name) => format!("identifier \"{}\"", name),
+            TokenKind::Text(_) => "string literal".to_string(),
+            TokenKind::Integer(value) => format!("number {}", value),
+            TokenKind::Real(value) => format!("number {}", value),
+            TokenKind::Blob(_) => "blob literal".to_string(),
+            TokenKind::Symbol(symbol) => format!("'{}'", symbol_text(*symbol)),
+            TokenKind::End => "end of input".to_string(),
+        }
+    }
+}
+
+fn symbol_text(symbol: Symbol) -> &'static str {
+    match symbol {
+        Symbol::LeftParenthesis => "(",
+        Symbol::RightParenthesis => ")",
+        Symbol::Comma => ",",
+        Symbol::Semicolon => ";",
+        Symbol::Asterisk => "*",
+        Symbol::Plus => "+",
+        Symbol::Minus => "-",
+        Symbol::Slash => "/",
+        Symbol::Percent => "%",
+        Symbol::Concat => "||",
+        Symbol::Equal => "=",
+        Symbol::NotEqual => "<>",
+        Symbol::Less => "<",
+        Symbol::LessOrEqual => "<=",
+        Symbol::Greater => ">",
+        Symbol::GreaterOrEqual => ">=",
+    }
+}
+
+struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(sql: &'a str) -> Lexer<'a> {
+        Lexer {
+            chars: sql.chars().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn tokenize(mut self) -> Result<Vec<Token>, ParseError> {
+        let mut tokens = Vec::new();
+        loop {
+            self.skip_whitespace_and_comments()?;
+            let (line, column) = (self.line, self.column);
+            let kind = match self.peek() {
+                None => {
+                    tokens.push(Token {
+                        kind: TokenKind::End,
+                        line,
+                        column,
+                    });
+                    return Ok(tokens);
+                }
+                Some(c) => self.next_token(c, line, column)?,
+            };
+            tokens.push(Token { kind, line, column });
+        }
+    }
+
+    fn skip_whitespace_and_comments(&mut self) -> Result<(), ParseError> {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.next_char();
+                }
+                Some('-') => {
+                    let mut lookahead = self.chars.clone();
+                    lookahead.next();
+                    if lookahead.next() != Some('-') {
+                        return Ok(());
+                    }
+                    while let Some(c) = self.next_char() {
+                        if c == '\n' {
+                            break;
+                        }
+                    }
+                }
+                Some('/') => {
+                    let mut lookahead = self.chars.clone();
+                    lookahead.next();
+                    if lookahead.next() != Some('*') {
+                        return Ok(());
+                    }
+                    let (line, column) = (self.line, self.column);
+                    self.next_char();
+                    self.next_char();
+                    let mut previous = ' ';
+                    loop {
+                        match self.next_char() {
+                            None => {
+                                return Err(ParseError::new("Unterminated comment", line, column))
+                            }
+                            Some('/') if previous == '*' => break,
+                            Some(c) => previous = c,
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn next_token(&mut self, c: char, line: usize, column: usize) -> Result<TokenKind, ParseError> {
+        if c == 'x' || c == 'X' {
+            let mut lookahead = self.chars.clone();
+            lookahead.next();
+            if lookahead.next() == Some('\'') {
+                self.next_char();
+                return self.read_blob(line, column);
+            }
+        }
+        if c.is_alphabetic() || c == '_' {
+            let mut word = String::new();
+            while let Some(c) = self.peek() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                word.push(c);
+                self.next_char();
+            }
+            return Ok(TokenKind::Word(word));
+        }
+        if c.is_ascii_digit() || c == '.' {
+            return self.read_number(line, column);
+        }
+        match c {
+            '\'' => Ok(TokenKind::Text(self.read_quoted('\'', line, column)?)),
+            '"' => Ok(TokenKind::QuotedIdentifier(
+                self.read_quoted('"', line, column)?,
+            )),
+            '`' => Ok(TokenKind::QuotedIdentifier(
+                self.read_quoted('`', line, column)?,
+            )),
+            _ => {
+                self.next_char();
+                let symbol = match c {
+                    '(' => Symbol::LeftParenthesis,
+                    ')' => Symbol::RightParenthesis,
+                    ',' => Symbol::Comma,
+                    ';' => Symbol::Semicolon,
+                    '*' => Symbol::Asterisk,
+                    '+' => Symbol::Plus,
+                    '-' => Symbol::Minus,
+                    '/' => Symbol::Slash,
+                    '%' => Symbol::Percent,
+                    '=' => {
+                        if self.peek() == Some('=') {
+                            self.next_char();
+                        }
+                        Symbol::Equal
+                    }
+                    '|' if self.peek() == Some('|') => {
+                        self.next_char();
+                        Symbol::Concat
+                    }
+                    '!' if self.peek() == Some('=') => {
+                        self.next_char();
+                        Symbol::NotEqual
+                    }
+                    '<' => match self.peek() {
+                        Some('=') => {
+                            self.next_char();
+                            Symbol::LessOrEqual
+                        }
+                        Some('>') => {
+                            self.next_char();
+                            Symbol::NotEqual
+                        }
+                        _ => Symbol::Less,
+                    },
+                    '>' => {
+                        if self.peek() == Some('=') {
+                            self.next_char();
+                            Symbol::GreaterOrEqual
+                        } else {
+                            Symbol::Greater
+                        }
+                    }
+                    _ => {
+                        return Err(ParseError::new(
+                            &format!("Unexpected character '{}'", c),
+                            line,
+                            column,
+                        ))
+                    }
+                };
+                Ok(TokenKind::Symbol(symbol))
+            }
+        }
+    }
+
+    fn read_quoted(&mut self, quote: char, line: usize, column: usize) -> Result<String, ParseError> {
+        self.next_char();
+        let mut content = String::new();
+        loop {
+            match self.next_char() {
+                None => {
+                    return Err(ParseError::new(
+                        "Unterminated quoted string",
+                        line,
+                        column,
+                    ))
+                }
+                Some(c) if c == quote => {
+                    // a doubled quote stands for the quote character itself
+                    if self.peek() == Some(quote) {
+                        self.next_char();
+                        content.push(quote);
+                    } else {
+                        return Ok(content);
+                    }
+                }
+                Some(c) => content.push(c),
+            }
+        }
+    }
+
+    fn read_blob(&mut self, line: usize, column: usize) -> Result<TokenKind, ParseError> {
+        let hex = self.read_quoted('\'', line, column)?;
+        if hex.len() % 2 != 0 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(ParseError::new(
+                "Blob literal must contain an even number of hexadecimal digits",
+                line,
+                column,
+            ));
+        }
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect();
+        Ok(TokenKind::Blob(bytes))
+    }
+
+    fn read_number(&mut self, line: usize, column: usize) -> Result<TokenKind, ParseError> {
+        let mut number = String::new();
+        let mut is_real = false;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() {
+                number.push(c);
+            } else if c == '.' && !is_real {
+                is_real = true;
+                number.push(c);
+            } else if (c == 'e' || c == 'E') && !number.is_empty() {
+                is_real = true;
+                number.push(c);
+                self.next_char();
+                if let Some(sign) = self.peek().filter(|s| *s == '+' || *s == '-') {
+                    number.push(sign);
+                    self.next_char();
+                }
+                continue;
+            } else {
+                break;
+            }
+            self.next_char();
+        }
+        if let Some(c) = self.peek().filter(|c| c.is_alphabetic() || *c == '_') {
+            return Err(ParseError::new(
+                &format!("Invalid character '{}' in number", c),
+                self.line,
+                self.column,
+            ));
+        }
+        if is_real {
+            match number.parse::<f64>() {
+                Ok(value) => Ok(TokenKind::Real(value)),
+                Err(_) => Err(ParseError::new(
+                    &format!("Invalid number '{}'", number),
+                    line,
+                    column,
+                )),
+            }
+        } else {
+            match number.parse::<i64>() {
+                Ok(value) => Ok(TokenKind::Integer(value)),
+                Err(_) => Err(ParseError::new(
+                    &format!("Integer '{}' is out of range", number),
+                    line,
+                    column,
+                )),
+            }
+        }
+    }
+}
+
+// Words that cannot be used as unquoted identifiers
+const RESERVED_WORDS: &[&str] = &[
+    "ADD", "ALTER", "AND", "AS", "ASC", "AUTOINCREMENT", "AUTO_INCREMENT", "BEGIN", "BETWEEN", "BY", "CHECK", "COLUMN", "COMMIT", "CREATE",
+    "DATABASE", "DEFAULT", "DELETE", "DESC", "DROP", "EXISTS", "EXPLAIN", "FALSE", "FROM", "IF", "IN",
+    "INDEX", "INSERT", "INTO", "IS", "KEY", "LIKE", "LIMIT", "NOT", "NULL", "OFFSET", "ON", "OR",
+    "ORDER", "PRIMARY", "REFERENCES", "RENAME", "ROLLBACK", "SELECT", "SET", "TABLE", "TO", "TRUE",
+    "UNIQUE", "UPDATE", "VALUES", "WHERE",
+];
+
+// Parser
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn new(sql: &str) -> Result<Parser, ParseError> {
+        Ok(Parser {
+            tokens: Lexer::new(sql).tokenize()?,
+            position: 0,
+        })
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position]
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.position].clone();
+        if token.kind != TokenKind::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.peek().kind == TokenKind::End
+    }
+
+    fn unexpected(&self, expected: &str) -> ParseError {
+        let token = self.peek();
+        ParseError::new(
+            &format!("Expected {}, found {}", expected, token.describe()),
+            token.line,
+            token.column,
+        )
+    }
+
+    fn check_keyword(&self, keyword: &str) -> bool {
+        matches!(&self.peek().kind, TokenKind::Word(word) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn consume_keyword(&mut self, keyword: &str) -> bool {
+        if self.check_keyword(keyword) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        if self.consume_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.unexpected(keyword))
+        }
+    }
+
+    fn check_symbol(&self, symbol: Symbol) -> bool {
+        self.peek().kind == TokenKind::Symbol(symbol)
+    }
+
+    fn consume_symbol(&mut self, symbol: Symbol) -> bool {
+        if self.check_symbol(symbol) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: Symbol) -> Result<(), ParseError> {
+        if self.consume_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("'{}'", symbol_text(symbol))))
+        }
+    }
+
+    fn parse_identifier(&mut self) -> Result<String, ParseError> {
+        match &self.peek().kind {
+            TokenKind::QuotedIdentifier(name) => {
+                let name = name.clone();
+                self.advance();
+                Ok(name)
+            }
+            TokenKind::Word(word)
+                if !RESERVED_WORDS
+                    .iter()
+                    .any(|reserved| word.eq_ignore_ascii_case(reserved)) =>
+            {
+                let word = word.clone();
+                self.advance();
+                Ok(word)
+            }
+            _ => Err(self.unexpected("an identifier")),
+        }
+    }
+
+    fn parse_identifier_list(&mut self) -> Result<Vec<String>, ParseError> {
+        self.expect_symbol(Symbol::LeftParenthesis)?;
+        let mut identifiers = vec![self.parse_identifier()?];
+        while self.consume_symbol(Symbol::Comma) {
+            identifiers.push(self.parse_identifier()?);
+        }
+        self.expect_symbol(Symbol::RightParenthesis)?;
+        Ok(identifiers)
+    }
+
+    fn parse_unsigned_integer(&mut self) -> Result<u64, ParseError> {
+        match self.peek().kind {
+            TokenKind::Integer(value) if value >= 0 => {
+                self.advance();
+                Ok(value as u64)
+            }
+            _ => Err(self.unexpected("a non-negative integer")),
+        }
+    }
+
+    fn parse_statement(&mut self) -> Result<Statement, ParseError> {
+        if self.check_keyword("SELECT") {
+            self.parse_select()
+        } else if self.check_keyword("INSERT") {
+            self.parse_insert()
+        } else if self.check_keyword("UPDATE") {
+            self.parse_update()
+        } else if self.check_keyword("DELETE") {
+            self.parse_delete()
+        } else if self.check_keyword("CREATE") {
+            self.parse_create()
+        } else if self.check_keyword("DROP") {
+            self.parse_drop()
+        } else if self.check_keyword("ALTER") {
+            self.parse_alter()
+        } else if self.consume_keyword("EXPLAIN") {
+            if self.check_keyword("EXPLAIN") {
+                return Err(self.unexpected("a statement to explain"));
+            }
+            Ok(Statement::Explain(Box::new(self.parse_statement()?)))
+        } else if self.consume_keyword("BEGIN") {
+            self.consume_keyword("TRANSACTION");
+            Ok(Statement::Begin)
+        } else if self.consume_keyword("COMMIT") {
+            self.consume_keyword("TRANSACTION");
+            Ok(Statement::Commit)
+        } else if self.consume_keyword("ROLLBACK") {
+            self.consume_keyword("TRANSACTION");
+            Ok(Statement::Rollback)
+        } else {
+            Err(self.unexpected("a statement"))
+        }
+    }
+
+    fn parse_optional_where(&mut self) -> Result<Option<Expression>, ParseError> {
+        if self.consume_keyword("WHERE") {
+            Ok(Some(self.parse_expression()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn parse_select(&mut self) -> Result<Statement, ParseError> {
+        self.expect_keyword("SELECT")?;
+        let mut projection = Vec::new();
+        loop {
+            if self.consume_symbol(Symbol::Asterisk) {
+                projection.push(SelectItem::Wildcard);
+            } else {
+                let expression = self.parse_expression()?;
+                let has_alias = self.consume_keyword("AS")
+                    || matches!(self.peek().kind, TokenKind::QuotedIdentifier(_))
+                    || (matches!(self.peek().kind, TokenKind::Word(_))
+                        && !self.check_keyword("FROM"));
+                let alias = if has_alias {
+                    Some(self.parse_identifier()?)
+                } else {
+                    None
+                };
+                projection.push(SelectItem::Expression { expression, alias });
+            }
+            if !self.consume_symbol(Symbol::Comma) {
+                break;
+            }
+        }
+        self.expect_keyword("FROM")?;
+        let table = self.parse_identifier()?;
+        let selection = self.parse_optional_where()?;
+
+        let mut order_by = Vec::new();
+        if self.consume_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            loop {
+                let expression = self.parse_expression()?;
+                let ascending = if self.consume_keyword("DESC") {
+                    false
+                } else {
+                    self.consume_keyword("ASC");
+                    true
+                };
+                order_by.push(OrderByItem {
+                    expression,
+                    ascending,
+                });
+                if !self.consume_symbol(Symbol::Comma) {
+                    break;
+                }
+            }
+        }
+
+        let mut limit = None;
+        let mut offset = None;
+        if self.consume_keyword("LIMIT") {
+            limit = Some(self.parse_unsigned_integer()?);
+            if self.consume_keyword("OFFSET") {
+                offset = Some(self.parse_unsigned_integer()?);
+            }
+        }
+
+        Ok(Statement::Select {
+            projection,
+            table,
+            selection,
+            order_by,
+            limit,
+            offset,
+        })
+    }
+
+    fn parse_insert(&mut self) -> Result<Statement, ParseError> {
+        self.expect_keyword("INSERT")?;
+        self.expect_keyword("INTO")?;
+        let table = self.parse_identifier()?;
+        let columns = if self.check_symbol(Symbol::LeftParenthesis) {
+            self.parse_identifier_list()?
+        } else {
+            Vec::new()
+        };
+        self.expect_keyword("VALUES")?;
+        let mut rows = Vec::new();
+        loop {
+            self.expect_symbol(Symbol::LeftParenthesis)?;
+            let mut row = vec![self.parse_expression()?];
+            while self.consume_symbol(Symbol::Comma) {
+                row.push(self.parse_expression()?);
+            }
+            self.expect_symbol(Symbol::RightParenthesis)?;
+            rows.push(row);
+            if !self.consume_symbol(Symbol::Comma) {
+                break;
+            }
+        }
+        Ok(Statement::Insert {
+            table,
+            columns,
+            rows,
+        })
+    }
+
+    fn parse_update(&mut self) -> Result<Statement, ParseError> {
+        self.expect_keyword("UPDATE")?;
+        let table = self.parse_identifier()?;
+        self.expect_keyword("SET")?;
+        let mut assignments = Vec::new();
+        loop {
+            let column = self.parse_identifier()?;
+            self.expect_symbol(Symbol::Equal)?;
+            assignments.push((column, self.parse_expression()?));
+            if !self.consume_symbol(Symbol::Comma) {
+                break;
+            }
+        }
+        let selection = self.parse_optional_where()?;
+        Ok(Statement::Update {
+            table,
+            assignments,
+            selection,
+        })
+    }
+
+    fn parse_delete(&mut self) -> Result<Statement, ParseError> {
+        self.expect_keyword("DELETE")?;
+        self.expect_keyword("FROM")?;
+        let table = self.parse_identifier()?;
+        let selection = self.parse_optional_where()?;
+        Ok(Statement::Delete { table, selection })
+    }
+
+    fn parse_if_not_exists(&mut self) -> Result<bool, ParseError> {
+        if self.consume_keyword("IF") {
+            self.expect_keyword("NOT")?;
+            self.expect_keyword("EXISTS")?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn parse_if_exists(&mut self) -> Result<bool, ParseError> {
+        if self.consume_keyword("IF") {
+            self.expect_keyword("EXISTS")?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn parse_create(&mut self) -> Result<Statement, ParseError> {
+        self.expect_keyword("CREATE")?;
+        if self.consume_keyword("DATABASE") {
+            let if_not_exists = self.parse_if_not_exists()?;
+            let name = self.parse_identifier()?;
+            return Ok(Statement::CreateDatabase {
+                name,
+                if_not_exists,
+            });
+        }
+        if self.consume_keyword("INDEX") {
+            let if_not_exists = self.parse_if_not_exists()?;
+            let name = self.parse_identifier()?;
+            self.expect_keyword("ON")?;
+            let table = self.parse_identifier()?;
+            let columns = self.parse_identifier_list()?;
+            return Ok(Statement::CreateIndex {
+                name,
+                table,
+                if_not_exists,
+                columns,
+            });
+        }
+        if !self.consume_keyword("TABLE") {
+            return Err(self.unexpected("TABLE, INDEX or DATABASE"));
+        }
+        let if_not_exists = self.parse_if_not_exists()?;
+        let table = self.parse_identifier()?;
+        self.expect_symbol(Symbol::LeftParenthesis)?;
+        let mut columns = vec![self.parse_column_definition()?];
+        while self.consume_symbol(Symbol::Comma) {
+            columns.push(self.parse_column_definition()?);
+        }
+        self.expect_symbol(Symbol::RightParenthesis)?;
+        Ok(Statement::CreateTable {
+            table,
+            if_not_exists,
+            columns,
+        })
+    }
+
+    fn parse_drop(&mut self) -> Result<Statement, ParseError> {
+        self.expect_keyword("DROP")?;
+        if self.consume_keyword("DATABASE") {
+            let if_exists = self.parse_if_exists()?;
+            let name = self.parse_identifier()?;
+            return Ok(Statement::DropDatabase { name, if_exists });
+        }
+        if self.consume_keyword("INDEX") {
+            let if_exists = self.parse_if_exists()?;
+            let name = self.parse_identifier()?;
+            return Ok(Statement::DropIndex { name, if_exists });
+        }
+        if !self.consume_keyword("TABLE") {
+            return Err(self.unexpected("TABLE, INDEX or DATABASE"));
+        }
+        let if_exists = self.parse_if_exists()?;
+        let table = self.parse_identifier()?;
+        Ok(Statement::DropTable { table, if_exists })
+    }
+
+    fn parse_alter(&mut self) -> Result<Statement, ParseError> {
+        self.expect_keyword("ALTER")?;
+        self.expect_keyword("TABLE")?;
+        let table = self.parse_identifier()?;
+        let mut actions = Vec::new();
+        loop {
+            actions.push(self.parse_alter_table_action()?);
+            if !self.consume_symbol(Symbol::Comma) {
+                break;
+            }
+        }
+        Ok(Statement::AlterTable { table, actions })
+    }
+
+    fn parse_alter_table_action(&mut self) -> Result<AlterTableAction, ParseError> {
+        if self.consume_keyword("ADD") {
+            self.consume_keyword("COLUMN");
+            return Ok(AlterTableAction::AddColumn(
+                self.parse_column_definition()?,
+            ));
+        }
+        if self.consume_keyword("DROP") {
+            self.consume_keyword("COLUMN");
+            return Ok(AlterTableAction::DropColumn(self.parse_identifier()?));
+        }
+        if self.consume_keyword("RENAME") {
+            self.consume_keyword("COLUMN");
+            let old_name = self.parse_identifier()?;
+            self.expect_keyword("TO")?;
+            let new_name = self.parse_identifier()?;
+            return Ok(AlterTableAction::RenameColumn { old_name, new_name });
+        }
+        if self.consume_keyword("ALTER") {
+            self.consume_keyword("COLUMN");
+            let name = self.parse_identifier()?;
+            if self.consume_keyword("SET") {
+                self.expect_keyword("NOT")?;
+                self.expect_keyword("NULL")?;
+                return Ok(AlterTableAction::SetNotNull(name));
+            }
+            if self.consume_keyword("DROP") {
+                self.expect_keyword("NOT")?;
+                self.expect_keyword("NULL")?;
+                return Ok(AlterTableAction::DropNotNull(name));
+            }
+            return Err(self.unexpected("SET NOT NULL or DROP NOT NULL"));
+        }
+        Err(self.unexpected("ADD, DROP, RENAME or ALTER"))
+    }
+
+    fn parse_data_type(&mut self) -> Result<DataType, ParseError> {
+        let token = self.peek().clone();
+        let name = match &token.kind {
+            TokenKind::Word(word) => word.to_ascii_uppercase(),
+            _ => return Err(self.unexpected("a data type")),
+        };
+        self.advance();
+        match name.as_str() {
+            "INTEGER" | "INT" | "BIGINT" | "SMALLINT" => Ok(DataType::Integer),
+            "REAL" | "FLOAT" | "DOUBLE" => Ok(DataType::Real),
+            "BLOB" => Ok(DataType::Blob),
+            "TEXT" | "VARCHAR" | "CHAR" => {
+                if !self.consume_symbol(Symbol::LeftParenthesis) {
+                    return Ok(DataType::Text(DEFAULT_TEXT_LENGTH));
+                }
+                let size_token = self.peek().clone();
+                let size = self.parse_unsigned_integer()?;
+                if size == 0 || size > u16::MAX as u64 {
+                    return Err(ParseError::new(
+                        &format!("Text length must be between 1 and {}", u16::MAX),
+                        size_token.line,
+                        size_token.column,
+                    ));
+                }
+                self.expect_symbol(Symbol::RightParenthesis)?;
+                Ok(DataType::Text(size as u16))
+            }
+            _ => Err(ParseError::new(
+                &format!("Unknown data type '{}'", name),
+                token.line,
+                token.column,
+            )),
+        }
+    }
+
+    fn parse_column_definition(&mut self) -> Result<ColumnDefinition, ParseError> {
+        let name = self.parse_identifier()?;
+        let data_type = self.parse_data_type()?;
+        let mut constraints = Vec::new();
+        loop {
+            if self.consume_keyword("PRIMARY") {
+                self.expect_keyword("KEY")?;
+                constraints.push(ColumnConstraint::PrimaryKey);
+            } else if self.consume_keyword("AUTOINCREMENT") || self.consume_keyword("AUTO_INCREMENT") {
+                constraints.push(ColumnConstraint::AutoIncrement);
+            } else if self.consume_keyword("NOT") {
+                self.expect_keyword("NULL")?;
+                constraints.push(ColumnConstraint::NotNull);
+            } else if self.consume_keyword("NULL") {
+                constraints.push(ColumnConstraint::Null);
+            } else if self.consume_keyword("UNIQUE") {
+                constraints.push(ColumnConstraint::Unique);
+            } else if self.consume_keyword("DEFAULT") {
+                // a literal, a signed number or an expression between parentheses
+                constraints.push(ColumnConstraint::Default(self.parse_unary()?));
+            } else if self.consume_keyword("CHECK") {
+                self.expect_symbol(Symbol::LeftParenthesis)?;
+                constraints.push(ColumnConstraint::Check(self.parse_expression()?));
+                self.expect_symbol(Symbol::RightParenthesis)?;
+            } else if self.consume_keyword("REFERENCES") {
+                constraints.push(self.parse_references()?);
+            } else {
+                break;
+            }
+        }
+        Ok(ColumnDefinition {
+            name,
+            data_type,
+            constraints,
+        })
+    }
+
+    // REFERENCES table [(column)] [ON DELETE RESTRICT | NO ACTION | CASCADE | SET NULL]
+    fn parse_references(&mut self) -> Result<ColumnConstraint, ParseError> {
+        let table = self.parse_identifier()?;
+        let mut column = None;
+        if self.consume_symbol(Symbol::LeftParenthesis) {
+            column = Some(self.parse_identifier()?);
+            self.expect_symbol(Symbol::RightParenthesis)?;
+        }
+        let mut on_delete = ReferentialAction::Restrict;
+        if self.consume_keyword("ON") {
+            self.expect_keyword("DELETE")?;
+            on_delete = if self.consume_keyword("RESTRICT") {
+                ReferentialAction::Restrict
+            } else if self.consume_keyword("NO") {
+                self.expect_keyword("ACTION")?;
+                ReferentialAction::Restrict
+            } else if self.consume_keyword("CASCADE") {
+                ReferentialAction::Cascade
+            } else if self.consume_keyword("SET") {
+                self.expect_keyword("NULL")?;
+                ReferentialAction::SetNull
+            } else {
+                return Err(self.unexpected("RESTRICT, NO ACTION, CASCADE or SET NULL"));
+            };
+        }
+        Ok(ColumnConstraint::References {
+            table,
+            column,
+            on_delete,
+        })
+    }
+
+    // Expressions, from the lowest to the highest precedence
+    fn parse_expression(&mut self) -> Result<Expression, ParseError> {
+        self.parse_or()
+    }
+
+    fn parse_or(&mut self) -> Result<Expression, ParseError> {
+        let mut left = self.parse_and()?;
+        while self.consume_keyword("OR") {
+            let right = self.parse_and()?;
+            left = binary(left, BinaryOperator::Or, right);
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expression, ParseError> {
+        let mut left = self.parse_not()?;
+        while self.consume_keyword("AND") {
+            let right = self.parse_not()?;
+            left = binary(left, BinaryOperator::And, right);
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Expression, ParseError> {
+        if self.consume_keyword("NOT") {
+            let operand = self.parse_not()?;
+            return Ok(Expression::Unary {
+                operator: UnaryOperator::Not,
+                operand: Box::new(operand),
+            });
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expression, ParseError> {
+        let left = self.parse_additive()?;
+
+        if self.consume_keyword("IS") {
+            let negated = self.consume_keyword("NOT");
+            self.expect_keyword("NULL")?;
+            return Ok(Expression::IsNull {
+                operand: Box::new(left),
+                negated,
+            });
+        }
+
+        let negated = if self.check_keyword("NOT") {
+            let next = self.tokens.get(self.position + 1).map(|token| &token.kind);
+            match next {
+                Some(TokenKind::Word(word))
+                    if ["IN", "BETWEEN", "LIKE"]
+                        .iter()
+                        .any(|keyword| word.eq_ignore_ascii_case(keyword)) =>
+                {
+                    self.advance();
+                    true
+                }
+                _ => false,
+            }
+        } else {
+            false
+        };
+        if self.consume_keyword("IN") {
+            self.expect_symbol(Symbol::LeftParenthesis)?;
+            let mut list = vec![self.parse_expression()?];
+            while self.consume_symbol(Symbol::Comma) {
+                list.push(self.parse_expression()?);
+            }
+            self.expect_symbol(Symbol::RightParenthesis)?;
+            return Ok(Expression::InList {
+                operand: Box::new(left),
+                list,
+                negated,
+            });
+        }
+        if self.consume_keyword("BETWEEN") {
+            let low = self.parse_additive()?;
+            self.expect_keyword("AND")?;
+            let high = self.parse_additive()?;
+            return Ok(Expression::Between {
+                operand: Box::new(left),
+                low: Box::new(low),
+                high: Box::new(high),
+                negated,
+            });
+        }
+        if self.consume_keyword("LIKE") {
+            let pattern = self.parse_additive()?;
+            return Ok(Expression::Like {
+                operand: Box::new(left),
+                pattern: Box::new(pattern),
+                negated,
+            });
+        }
+
+        let operator = match self.peek().kind {
+            TokenKind::Symbol(Symbol::Equal) => BinaryOperator::Equal,
+            TokenKind::Symbol(Symbol::NotEqual) => BinaryOperator::NotEqual,
+            TokenKind::Symbol(Symbol::Less) => BinaryOperator::Less,
+            TokenKind::Symbol(Symbol::LessOrEqual) => BinaryOperator::LessOrEqual,
+            TokenKind::Symbol(Symbol::Greater) => BinaryOperator::Greater,
+            TokenKind::Symbol(Symbol::GreaterOrEqual) => BinaryOperator::GreaterOrEqual,
+            _ => return Ok(left),
+        };
+        self.advance();
+        let right = self.parse_additive()?;
+        Ok(binary(left, operator, right))
+    }
+
+    fn parse_additive(&mut self) -> Result<Expression, ParseError> {
+        let mut left = self.parse_multiplicative()?;
+        loop {
+            let operator = match self.peek().kind {
+                TokenKind::Symbol(Symbol::Plus) => BinaryOperator::Plus,
+                TokenKind::Symbol(Symbol::Minus) => BinaryOperator::Minus,
+                TokenKind::Symbol(Symbol::Concat) => BinaryOperator::Concat,
+                _ => return Ok(left),
+            };
+            self.advance();
+            let right = self.parse_multiplicative()?;
+            left = binary(left, operator, right);
+        }
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expression, ParseError> {
+        let mut left = self.parse_unary()?;
+        loop {
+            let operator = match self.peek().kind {
+                TokenKind::Symbol(Symbol::Asterisk) => BinaryOperator::Multiply,
+                TokenKind::Symbol(Symbol::Slash) => BinaryOperator::Divide,
+                TokenKind::Symbol(Symbol::Percent) => BinaryOperator::Modulo,
+                _ => return Ok(left),
+            };
+            self.advance();
+            let right = self.parse_unary()?;
+            left = binary(left, operator, right);
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression, ParseError> {
+        let operator = if self.consume_symbol(Symbol::Minus) {
+            UnaryOperator::Minus
+        } else if self.consume_symbol(Symbol::Plus) {
+            UnaryOperator::Plus
+        } else {
+            return self.parse_primary();
+        };
+        let operand = self.parse_unary()?;
+        // fold signs into numeric literals so that "-5" is a literal
+        match (operator, operand) {
+            (UnaryOperator::Minus, Expression::Literal(Literal::Integer(value))) => {
+                Ok(Expression::Literal(Literal::Integer(-value)))
+            }
+            (UnaryOperator::Minus, Expression::Literal(Literal::Real(value))) => {
+                Ok(Expression::Literal(Literal::Real(-value)))
+            }
+            (operator, operand) => Ok(Expression::Unary {
+                operator,
+                operand: Box::new(operand),
+            }),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, ParseError> {
+        let token = self.peek().clone();
+        let expression = match token.kind {
+            TokenKind::Integer(value) => Expression::Literal(Literal::Integer(value)),
+            TokenKind::Real(value) => Expression::Literal(Literal::Real(value)),
+            TokenKind::Text(value) => Expression::Literal(Literal::Text(value)),
+            TokenKind::Blob(value) => Expression::Literal(Literal::Blob(value)),
+            TokenKind::QuotedIdentifier(name) => Expression::Column(name),
+            TokenKind::Symbol(Symbol::LeftParenthesis) => {
+                self.advance();
+                let expression = self.parse_expression()?;
+                self.expect_symbol(Symbol::RightParenthesis)?;
+                return Ok(expression);
+            }
+            TokenKind::Word(word) => {
+                if word.eq_ignore_ascii_case("NULL") {
+                    Expression::Literal(Literal::Null)
+                } else if word.eq_ignore_ascii_case("TRUE") {
+                    Expression::Literal(Literal::Boolean(true))
+                } else if word.eq_ignore_ascii_case("FALSE") {
+                    Expression::Literal(Literal::Boolean(false))
+                } else {
+                    return Ok(Expression::Column(self.parse_identifier()?));
+                }
+            }
+            _ => return Err(self.unexpected("an expression")),
+        };
+        self.advance();
+        Ok(expression)
+    }
+}
+
+fn binary(left: Expression, operator: BinaryOperator, right: Expression) -> Expression {
+    Expression::Binary {
+        left: Box::new(left),
+        operator,
+        right: Box::new(right),
+    }
+}
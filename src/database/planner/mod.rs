@@ -0,0 +1,351 @@
+// planner is a module that decides how the rows of a SELECT statement are read.
+// a predicate on the leading columns of an index turns the table scan into an index scan
+
+use std::error::Error;
+use std::fmt;
+
+use crate::database::abstraction::{DataType, Index, Table, Value};
+use crate::database::parser::{BinaryOperator, Expression, Literal, OrderByItem, SelectItem, Statement, UnaryOperator};
+
+// share of the rows kept by an equality, a range bound and any other condition
+const EQUALITY_SELECTIVITY: f64 = 0.1;
+const RANGE_SELECTIVITY: f64 = 1.0 / 3.0;
+const DEFAULT_SELECTIVITY: f64 = 0.5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlanNode {
+    // read every row of the table
+    SeqScan {
+        table: String,
+        filter: Option<Expression>,
+        estimated_rows: u64,
+    },
+    // read the rows whose index key starts with the prefix values,
+    // the next index column being within the lower and upper values when given
+    IndexScan {
+        table: String,
+        index: Index,
+        prefix: Vec<Value>,
+        lower: Option<Value>,
+        upper: Option<Value>,
+        // conditions answered by the index, rows are checked against them as well
+        condition: Vec<Expression>,
+        filter: Option<Expression>,
+        estimated_rows: u64,
+    },
+    Sort {
+        order_by: Vec<OrderByItem>,
+        input: Box<PlanNode>,
+        estimated_rows: u64,
+    },
+    Limit {
+        limit: Option<u64>,
+        offset: Option<u64>,
+        input: Box<PlanNode>,
+        estimated_rows: u64,
+    },
+    Project {
+        projection: Vec<SelectItem>,
+        input: Box<PlanNode>,
+        estimated_rows: u64,
+    },
+}
+
+impl PlanNode {
+    pub fn get_estimated_rows(&self) -> u64 {
+        match self {
+            PlanNode::SeqScan { estimated_rows, .. }
+            | PlanNode::IndexScan { estimated_rows, .. }
+            | PlanNode::Sort { estimated_rows, .. }
+            | PlanNode::Limit { estimated_rows, .. }
+            | PlanNode::Project { estimated_rows, .. } => *estimated_rows,
+        }
+    }
+
+    pub fn get_input(&self) -> Option<&PlanNode> {
+        match self {
+            PlanNode::SeqScan { .. } | PlanNode::IndexScan { .. } => None,
+            PlanNode::Sort { input, .. } | PlanNode::Limit { input, .. } | PlanNode::Project { input, .. } => Some(input),
+        }
+    }
+
+    fn fmt_node(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let indent = "  ".repeat(depth);
+        match self {
+            PlanNode::SeqScan { table, filter, .. } => {
+                writeln!(f, "{}SeqScan on {} (rows={})", indent, table, self.get_estimated_rows())?;
+                if let Some(filter) = filter {
+                    writeln!(f, "{}  Filter: {}", indent, filter)?;
+                }
+            }
+            PlanNode::IndexScan { table, index, condition, filter, .. } => {
+                writeln!(f, "{}IndexScan using {} on {} (rows={})", indent, index.get_name(), table, self.get_estimated_rows())?;
+                writeln!(f, "{}  Index Cond: {}", indent, join(condition, " AND "))?;
+                if let Some(filter) = filter {
+                    writeln!(f, "{}  Filter: {}", indent, filter)?;
+                }
+            }
+            PlanNode::Sort { order_by, .. } => {
+                let keys: Vec<String> = order_by.iter()
+                    .map(|item| format!("{} {}", item.expression, if item.ascending { "ASC" } else { "DESC" }))
+                    .collect();
+                writeln!(f, "{}Sort by {} (rows={})", indent, keys.join(", "), self.get_estimated_rows())?;
+            }
+            PlanNode::Limit { limit, offset, .. } => {
+                let limit = limit.map(|limit| limit.to_string()).unwrap_or_else(|| "ALL".to_string());
+                writeln!(f, "{}Limit {} offset {} (rows={})", indent, limit, offset.unwrap_or(0), self.get_estimated_rows())?;
+            }
+            PlanNode::Project { projection, .. } => {
+                let items: Vec<String> = projection.iter()
+                    .map(|item| match item {
+                        SelectItem::Wildcard => "*".to_string(),
+                        SelectItem::Expression { expression, alias: Some(alias) } => format!("{} AS {}", expression, alias),
+                        SelectItem::Expression { expression, alias: None } => expression.to_string(),
+                    })
+                    .collect();
+                writeln!(f, "{}Project {} (rows={})", indent, items.join(", "), self.get_estimated_rows())?;
+            }
+        }
+        match self.get_input() {
+            Some(input) => input.fmt_node(f, depth + 1),
+            None => Ok(()),
+        }
+    }
+}
+
+// one line per node, inputs are indented below the node reading them
+impl fmt::Display for PlanNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_node(f, 0)
+    }
+}
+
+// plan a SELECT statement over a table holding row_count rows
+pub fn plan(statement: &Statement, table: &Table, row_count: u64) -> Result<PlanNode, Box<dyn Error>> {
+    let (projection, selection, order_by, limit, offset) = match statement {
+        Statement::Select { projection, selection, order_by, limit, offset, .. } => (projection, selection, order_by, limit, offset),
+        _ => return Err("Only SELECT statements can be planned".into()),
+    };
+    let conditions = match selection {
+        Some(selection) => conjuncts(selection),
+        None => Vec::new(),
+    };
+
+    let mut node = plan_scan(table, row_count, &conditions);
+    if !order_by.is_empty() {
+        let estimated_rows = node.get_estimated_rows();
+        node = PlanNode::Sort { order_by: order_by.clone(), input: Box::new(node), estimated_rows };
+    }
+    if limit.is_some() || offset.is_some() {
+        let estimated_rows = node.get_estimated_rows()
+            .saturating_sub(offset.unwrap_or(0))
+            .min(limit.unwrap_or(u64::MAX));
+        node = PlanNode::Limit { limit: *limit, offset: *offset, input: Box::new(node), estimated_rows };
+    }
+    let estimated_rows = node.get_estimated_rows();
+    Ok(PlanNode::Project { projection: projection.clone(), input: Box::new(node), estimated_rows })
+}
+
+// the cheapest index scan, a sequential scan when no index matches the conditions
+fn plan_scan(table: &Table, row_count: u64, conditions: &[&Expression]) -> PlanNode {
+    let mut best: Option<PlanNode> = None;
+    for index in table.get_indexes() {
+        if let Some(node) = plan_index_scan(table, index, row_count, conditions) {
+            if best.as_ref().is_none_or(|best| node.get_estimated_rows() < best.get_estimated_rows()) {
+                best = Some(node);
+            }
+        }
+    }
+    match best {
+        Some(node) => node,
+        None => {
+            let selectivity: f64 = conditions.iter().map(|condition| selectivity(table, condition)).product();
+            PlanNode::SeqScan {
+                table: table.get_name().to_string(),
+                filter: conjunction(conditions),
+                estimated_rows: estimate(row_count, selectivity),
+            }
+        }
+    }
+}
+
+// equalities on the leading index columns, then bounds on the next column
+fn plan_index_scan(table: &Table, index: &Index, row_count: u64, conditions: &[&Expression]) -> Option<PlanNode> {
+    let bounds: Vec<Option<(String, Bound)>> = conditions.iter().map(|condition| index_bound(table, condition)).collect();
+    let mut used = vec![false; conditions.len()];
+    let mut prefix = Vec::new();
+    let mut lower: Option<Value> = None;
+    let mut upper: Option<Value> = None;
+    let mut selectivity = 1.0;
+
+    for column in index.get_columns() {
+        let equality = bounds.iter().position(|bound| {
+            matches!(bound, Some((name, Bound::Equal(_))) if name == column)
+        });
+        if let Some(i) = equality {
+            if let Some((_, Bound::Equal(value))) = &bounds[i] {
+                prefix.push(value.clone());
+            }
+            used[i] = true;
+            selectivity *= EQUALITY_SELECTIVITY;
+            continue;
+        }
+        for (i, bound) in bounds.iter().enumerate() {
+            let (low, high) = match bound {
+                Some((name, Bound::Range(low, high))) if name == column => (low, high),
+                _ => continue,
+            };
+            // several bounds on a column keep the narrowest range
+            if let Some(low) = low {
+                lower = Some(lower.map_or(low.clone(), |lower| lower.max(low.clone())));
+                selectivity *= RANGE_SELECTIVITY;
+            }
+            if let Some(high) = high {
+                upper = Some(upper.map_or(high.clone(), |upper| upper.min(high.clone())));
+                selectivity *= RANGE_SELECTIVITY;
+            }
+            used[i] = true;
+        }
+        break;
+    }
+    if !used.contains(&true) {
+        return None;
+    }
+
+    // a unique column or the whole primary key matched by equalities gives at most one row
+    let equal_columns = &index.get_columns()[..prefix.len()];
+    let primary_key: Vec<&str> = table.get_primary_key().iter().map(|column| column.get_name()).collect();
+    let is_unique = (!primary_key.is_empty() && primary_key.iter().all(|column| equal_columns.iter().any(|name| name == column)))
+        || equal_columns.iter().any(|name| table.get_column(name).is_some_and(|column| column.is_unique()));
+    let mut estimated_rows = estimate(row_count, selectivity);
+    if is_unique {
+        estimated_rows = estimated_rows.min(1);
+    }
+
+    let condition: Vec<Expression> = conditions.iter().zip(&used).filter(|(_, used)| **used).map(|(condition, _)| (*condition).clone()).collect();
+    let remaining: Vec<&Expression> = conditions.iter().zip(&used).filter(|(_, used)| !**used).map(|(condition, _)| *condition).collect();
+    let filter_selectivity: f64 = remaining.iter().map(|condition| self::selectivity(table, condition)).product();
+    Some(PlanNode::IndexScan {
+        table: table.get_name().to_string(),
+        index: index.clone(),
+        prefix,
+        lower,
+        upper,
+        condition,
+        filter: conjunction(&remaining),
+        estimated_rows: estimate(estimated_rows, filter_selectivity),
+    })
+}
+
+// what a condition tells about the values of a column, bounds are inclusive
+enum Bound {
+    Equal(Value),
+    Range(Option<Value>, Option<Value>),
+}
+
+// the column bound of a comparison between a column and a constant of the same kind as the column,
+// other comparisons follow evaluation rules an index ordered by value does not
+fn index_bound(table: &Table, condition: &Expression) -> Option<(String, Bound)> {
+    let column_bound = |column: &Expression, value: &Expression| -> Option<(String, Value)> {
+        let name = match column {
+            Expression::Column(name) => name,
+            _ => return None,
+        };
+        let value = constant(value)?;
+        let is_comparable = matches!(
+            (table.get_column(name)?.get_data_type(), &value),
+            (DataType::Integer | DataType::Real, Value::Integer(_) | Value::Real(_))
+                | (DataType::Text(_), Value::Text(_))
+                | (DataType::Blob, Value::Blob(_))
+        );
+        if is_comparable { Some((name.clone(), value)) } else { None }
+    };
+
+    match condition {
+        Expression::Binary { left, operator, right } => {
+            // the constant may be on either side, "5 < a" reads "a > 5"
+            let (name, value, operator) = match column_bound(left, right) {
+                Some((name, value)) => (name, value, *operator),
+                None => {
+                    let (name, value) = column_bound(right, left)?;
+                    let operator = match operator {
+                        BinaryOperator::Less => BinaryOperator::Greater,
+                        BinaryOperator::LessOrEqual => BinaryOperator::GreaterOrEqual,
+                        BinaryOperator::Greater => BinaryOperator::Less,
+                        BinaryOperator::GreaterOrEqual => BinaryOperator::LessOrEqual,
+                        operator => *operator,
+                    };
+                    (name, value, operator)
+                }
+            };
+            let bound = match operator {
+                BinaryOperator::Equal => Bound::Equal(value),
+                BinaryOperator::Less | BinaryOperator::LessOrEqual => Bound::Range(None, Some(value)),
+                BinaryOperator::Greater | BinaryOperator::GreaterOrEqual => Bound::Range(Some(value), None),
+                _ => return None,
+            };
+            Some((name, bound))
+        }
+        Expression::Between { operand, low, high, negated: false } => {
+            let (name, low) = column_bound(operand, low)?;
+            let (_, high) = column_bound(operand, high)?;
+            Some((name, Bound::Range(Some(low), Some(high))))
+        }
+        _ => None,
+    }
+}
+
+// value of a non NULL literal, possibly signed
+fn constant(expression: &Expression) -> Option<Value> {
+    match expression {
+        Expression::Literal(Literal::Null) => None,
+        Expression::Literal(literal) => Some(Value::from_literal(literal)),
+        Expression::Unary { operator: UnaryOperator::Minus, operand } => match constant(operand)? {
+            Value::Integer(value) => Some(Value::Integer(value.checked_neg()?)),
+            Value::Real(value) => Some(Value::Real(-value)),
+            _ => None,
+        },
+        Expression::Unary { operator: UnaryOperator::Plus, operand } => match constant(operand)? {
+            value @ (Value::Integer(_) | Value::Real(_)) => Some(value),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn selectivity(table: &Table, condition: &Expression) -> f64 {
+    match index_bound(table, condition) {
+        Some((_, Bound::Equal(_))) => EQUALITY_SELECTIVITY,
+        Some((_, Bound::Range(Some(_), Some(_)))) => RANGE_SELECTIVITY * RANGE_SELECTIVITY,
+        Some((_, Bound::Range(_, _))) => RANGE_SELECTIVITY,
+        None => DEFAULT_SELECTIVITY,
+    }
+}
+
+fn estimate(row_count: u64, selectivity: f64) -> u64 {
+    (row_count as f64 * selectivity).ceil() as u64
+}
+
+// the conditions joined by AND of a predicate
+fn conjuncts(expression: &Expression) -> Vec<&Expression> {
+    match expression {
+        Expression::Binary { left, operator: BinaryOperator::And, right } => {
+            let mut conditions = conjuncts(left);
+            conditions.extend(conjuncts(right));
+            conditions
+        }
+        _ => vec![expression],
+    }
+}
+
+fn conjunction(conditions: &[&Expression]) -> Option<Expression> {
+    conditions.iter().map(|condition| (*condition).clone()).reduce(|left, right| Expression::Binary {
+        left: Box::new(left),
+        operator: BinaryOperator::And,
+        right: Box::new(right),
+    })
+}
+
+fn join(expressions: &[Expression], separator: &str) -> String {
+    expressions.iter().map(|expression| expression.to_string()).collect::<Vec<String>>().join(separator)
+}
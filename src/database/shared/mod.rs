@@ -0,0 +1,104 @@
+// shared is a module that contains the database handle shared between the threads of a process.
+
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::database::abstraction::{AlterTableOperation, Database, DatabaseTrait, Index, InsertResult, Query, Record, ResultSet, Table, DDL, DML};
+use crate::database::transaction::Transaction;
+use crate::storage::files::FileStorage;
+use crate::storage::persistence::DataHandler;
+
+#[derive(Debug)]
+pub struct SharedDatabase {
+    storage: FileStorage,
+    database: RwLock<Database>,
+}
+
+impl SharedDatabase {
+    pub fn new(database: Database) -> Arc<SharedDatabase> {
+        Arc::new(SharedDatabase { storage: database.get_storage().clone(), database: RwLock::new(database) })
+    }
+
+    pub fn get_name(&self) -> String {
+        self.read_database().get_name().to_string()
+    }
+
+    pub fn get_storage(&self) -> &FileStorage {
+        &self.storage
+    }
+
+    // tables of the database, as of the last schema change through the handle or the last load
+    pub fn get_tables(&self) -> Vec<Table> {
+        self.read_database().get_tables().clone()
+    }
+
+    // list the tables of the database directory again
+    pub fn load_tables(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.write_database().load_tables()
+    }
+
+    fn read_database(&self) -> RwLockReadGuard<'_, Database> {
+        self.database.read().unwrap_or_else(|error| error.into_inner())
+    }
+
+    fn write_database(&self) -> RwLockWriteGuard<'_, Database> {
+        self.database.write().unwrap_or_else(|error| error.into_inner())
+    }
+
+    // run a schema change on the database of the handle, its tables are listed again afterwards
+    fn change_schema(&self, change: impl FnOnce(&mut Database) -> Result<(), Box<dyn std::error::Error>>) -> Result<(), Box<dyn std::error::Error>> {
+        let mut database = self.write_database();
+        let result = change(&mut database);
+        database.load_tables()?;
+        result
+    }
+
+    fn data_handler(&self) -> DataHandler {
+        DataHandler::new_from_storage(self.storage.clone())
+    }
+
+    pub fn insert(&self, record: Record) -> Result<InsertResult, Box<dyn std::error::Error>> {
+        self.data_handler().insert(record)
+    }
+
+    pub fn select(&self, query: Query) -> Result<ResultSet, Box<dyn std::error::Error>> {
+        self.data_handler().select(query)
+    }
+
+    pub fn update(&self, record: Record, query: Query) -> Result<u32, Box<dyn std::error::Error>> {
+        self.data_handler().update(record, query)
+    }
+
+    pub fn delete(&self, query: Query) -> Result<u32, Box<dyn std::error::Error>> {
+        self.data_handler().delete(query)
+    }
+
+    // open a transaction for the calling thread, its statements wait for the tables other work owns
+    pub fn begin_transaction(&self) -> Result<Transaction, Box<dyn std::error::Error>> {
+        Transaction::begin(self.storage.clone())
+    }
+
+    pub fn create_table(&self, table: Table) -> Result<(), Box<dyn std::error::Error>> {
+        self.change_schema(|database| database.create_table(table))
+    }
+
+    pub fn drop_table(&self, table: Table) -> Result<(), Box<dyn std::error::Error>> {
+        self.change_schema(|database| database.drop_table(table))
+    }
+
+    pub fn alter_table(&self, table: Table, operations: Vec<AlterTableOperation>) -> Result<(), Box<dyn std::error::Error>> {
+        self.change_schema(|database| database.alter_table(table, operations))
+    }
+
+    pub fn create_index(&self, table: Table, index: Index) -> Result<(), Box<dyn std::error::Error>> {
+        self.change_schema(|database| database.create_index(table, index))
+    }
+
+    pub fn drop_index(&self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.change_schema(|database| database.drop_index(name))
+    }
+
+    // remove the row versions of a table that no reader needs anymore
+    pub fn vacuum(&self, table_name: &str) -> Result<usize, Box<dyn std::error::Error>> {
+        self.data_handler().vacuum(table_name)
+    }
+}
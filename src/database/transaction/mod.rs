@@ -0,0 +1,70 @@
+// transaction is a module that contains the transactions of a database.
+// its statements are committed together, or undone when it is rolled back or dropped
+
+use crate::database::abstraction::{InsertResult, Query, Record, ResultSet, DML};
+use crate::storage::files::FileStorage;
+use crate::storage::persistence::DataHandler;
+
+pub struct Transaction {
+    id: u64,
+    storage: FileStorage,
+    data_handler: DataHandler,
+    finished: bool,
+}
+
+impl Transaction {
+    // open a transaction on the directory of a storage, one transaction at a time for the calling thread
+    pub fn begin(storage: FileStorage) -> Result<Transaction, Box<dyn std::error::Error>> {
+        let id = storage.begin_transaction()?;
+        // the reads of the transaction see its own work, other handles do not until it is committed
+        let mut data_handler = DataHandler::new_from_storage(storage.clone());
+        data_handler.set_transaction(Some(id));
+        Ok(Transaction {
+            id,
+            data_handler,
+            storage,
+            finished: false,
+        })
+    }
+
+    pub fn get_id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn commit(mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.finished = true;
+        self.storage.commit_transaction(self.id)
+    }
+
+    pub fn rollback(mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.finished = true;
+        self.storage.rollback_transaction(self.id)
+    }
+}
+
+impl DML for Transaction {
+    fn insert(&mut self, record: Record) -> Result<InsertResult, Box<dyn std::error::Error>> {
+        self.data_handler.insert(record)
+    }
+
+    fn select(&self, query: Query) -> Result<ResultSet, Box<dyn std::error::Error>> {
+        self.data_handler.select(query)
+    }
+
+    fn update(&mut self, record: Record, query: Query) -> Result<u32, Box<dyn std::error::Error>> {
+        self.data_handler.update(record, query)
+    }
+
+    fn delete(&mut self, query: Query) -> Result<u32, Box<dyn std::error::Error>> {
+        self.data_handler.delete(query)
+    }
+}
+
+impl Drop for Transaction {
+    // a transaction left without a commit is rolled back, unless it was already ended by a statement
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.storage.rollback_transaction(self.id);
+        }
+    }
+}
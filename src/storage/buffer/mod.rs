@@ -0,0 +1,283 @@
+// buffer is a module that contains the page cache shared by every file storage of a database directory.
+// pages are kept up to a memory budget, the least recently used page is evicted first
+
+use std::any::Any;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use super::directory_key;
+
+// memory budget of a new buffer pool
+pub const DEFAULT_BUFFER_POOL_BUDGET: usize = 8 * 1024 * 1024;
+
+// a page is found by its file and its offset in the file
+pub type PageKey = (String, u64);
+
+#[derive(Debug)]
+struct Frame {
+    data: Vec<u8>,
+    dirty: bool,
+    // tick of the last access, key of the frame in the LRU list
+    last_used: u64,
+}
+
+// a whole file parsed into a value of any type
+#[derive(Clone)]
+struct ParsedFile(Arc<dyn Any + Send + Sync>);
+
+impl fmt::Debug for ParsedFile {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("ParsedFile")
+    }
+}
+
+#[derive(Debug)]
+pub struct BufferPool {
+    memory_budget: usize,
+    memory_used: usize,
+    frames: HashMap<PageKey, Frame>,
+    // frames by tick of last access, the first one is the least recently used
+    lru: BTreeMap<u64, PageKey>,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+    // parsed files by name, forgotten along with the pages of their file
+    parsed_files: HashMap<String, ParsedFile>,
+    // names of the files of the directory, None until listed and once a file is added or removed
+    file_names: Option<Vec<String>>,
+}
+
+impl Default for BufferPool {
+    fn default() -> Self {
+        BufferPool::new(DEFAULT_BUFFER_POOL_BUDGET)
+    }
+}
+
+impl BufferPool {
+    pub fn new(memory_budget: usize) -> BufferPool {
+        BufferPool {
+            memory_budget,
+            memory_used: 0,
+            frames: HashMap::new(),
+            lru: BTreeMap::new(),
+            tick: 0,
+            hits: 0,
+            misses: 0,
+            evictions: 0,
+            parsed_files: HashMap::new(),
+            file_names: None,
+        }
+    }
+
+    pub fn get_memory_budget(&self) -> usize {
+        self.memory_budget
+    }
+
+    // change the memory budget, returns the dirty pages evicted to fit in it
+    pub fn set_memory_budget(&mut self, memory_budget: usize) -> Vec<(PageKey, Vec<u8>)> {
+        self.memory_budget = memory_budget;
+        self.evict(0)
+    }
+
+    pub fn get_memory_used(&self) -> usize {
+        self.memory_used
+    }
+
+    pub fn get_page_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn get_dirty_count(&self) -> usize {
+        self.frames.values().filter(|frame| frame.dirty).count()
+    }
+
+    pub fn get_hits(&self) -> u64 {
+        self.hits
+    }
+
+    pub fn get_misses(&self) -> u64 {
+        self.misses
+    }
+
+    pub fn get_evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    pub fn reset_counters(&mut self) {
+        self.hits = 0;
+        self.misses = 0;
+        self.evictions = 0;
+    }
+
+    pub fn contains(&self, key: &PageKey) -> bool {
+        self.frames.contains_key(key)
+    }
+
+    pub fn is_dirty(&self, key: &PageKey) -> bool {
+        self.frames.get(key).is_some_and(|frame| frame.dirty)
+    }
+
+    // content of a cached page, a miss is counted when the page is not in the pool
+    pub fn get(&mut self, key: &PageKey) -> Option<Vec<u8>> {
+        if !self.frames.contains_key(key) {
+            self.misses += 1;
+            return None;
+        }
+        self.hits += 1;
+        self.touch(key);
+        self.frames.get(key).map(|frame| frame.data.clone())
+    }
+
+    // cache a page, a dirty page is written back when evicted or checkpointed.
+    // Returns the dirty pages evicted to make room, a page larger than the budget is not cached
+    // and comes back at once when dirty
+    pub fn put(&mut self, key: PageKey, data: Vec<u8>, dirty: bool) -> Vec<(PageKey, Vec<u8>)> {
+        // a clean copy never replaces changes not written yet
+        let dirty = dirty || self.is_dirty(&key);
+        if let Some(frame) = self.remove(&key) {
+            self.memory_used -= frame.data.len();
+        }
+        if data.len() > self.memory_budget {
+            return if dirty { vec![(key, data)] } else { Vec::new() };
+        }
+        let evicted = self.evict(data.len());
+        self.tick += 1;
+        self.memory_used += data.len();
+        self.lru.insert(self.tick, key.clone());
+        self.frames.insert(key, Frame { data, dirty, last_used: self.tick });
+        evicted
+    }
+
+    // the dirty pages, marked clean as they are about to be written back
+    pub fn take_dirty(&mut self) -> Vec<(PageKey, Vec<u8>)> {
+        self.take_dirty_where(|_| true)
+    }
+
+    // the dirty pages of a file, marked clean
+    pub fn take_dirty_file(&mut self, file_name: &str) -> Vec<(PageKey, Vec<u8>)> {
+        self.take_dirty_where(|key| key.0 == file_name)
+    }
+
+    // the dirty pages of some files, marked clean
+    pub fn take_dirty_files(&mut self, file_names: &BTreeSet<String>) -> Vec<(PageKey, Vec<u8>)> {
+        self.take_dirty_where(|key| file_names.contains(&key.0))
+    }
+
+    // the value a file was parsed into, None when the file changed since
+    pub fn get_parsed_file<T: Clone + 'static>(&self, file_name: &str) -> Option<T> {
+        self.parsed_files.get(file_name).and_then(|parsed| parsed.0.downcast_ref::<T>()).cloned()
+    }
+
+    pub fn put_parsed_file<T: Send + Sync + 'static>(&mut self, file_name: &str, value: T) {
+        self.parsed_files.insert(file_name.to_string(), ParsedFile(Arc::new(value)));
+    }
+
+    pub fn get_file_names(&self) -> Option<&Vec<String>> {
+        self.file_names.as_ref()
+    }
+
+    pub fn set_file_names(&mut self, file_names: Vec<String>) {
+        self.file_names = Some(file_names);
+    }
+
+    // forget the names of the files, a file was added to the directory or removed from it
+    pub fn discard_file_names(&mut self) {
+        self.file_names = None;
+    }
+
+    // forget the pages of a file and its parsed value, changes not written back are lost
+    pub fn discard_file(&mut self, file_name: &str) {
+        self.parsed_files.remove(file_name);
+        let keys: Vec<PageKey> = self.frames.keys().filter(|key| key.0 == file_name).cloned().collect();
+        for key in keys {
+            if let Some(frame) = self.remove(&key) {
+                self.memory_used -= frame.data.len();
+            }
+        }
+    }
+
+    // forget the dirty pages, their changes are lost
+    pub fn discard_dirty(&mut self) {
+        self.discard_dirty_where(|_| true)
+    }
+
+    // forget the dirty pages of some files, their changes are lost
+    pub fn discard_dirty_files(&mut self, file_names: &BTreeSet<String>) {
+        self.discard_dirty_where(|key| file_names.contains(&key.0))
+    }
+
+    fn discard_dirty_where(&mut self, filter: impl Fn(&PageKey) -> bool) {
+        let keys: Vec<PageKey> = self.frames.iter().filter(|(key, frame)| frame.dirty && filter(key)).map(|(key, _)| key.clone()).collect();
+        for key in keys {
+            if let Some(frame) = self.remove(&key) {
+                self.memory_used -= frame.data.len();
+            }
+        }
+    }
+
+    fn take_dirty_where(&mut self, filter: impl Fn(&PageKey) -> bool) -> Vec<(PageKey, Vec<u8>)> {
+        let mut pages: Vec<(PageKey, Vec<u8>)> = self.frames.iter_mut()
+            .filter(|(key, frame)| frame.dirty && filter(key))
+            .map(|(key, frame)| {
+                frame.dirty = false;
+                (key.clone(), frame.data.clone())
+            })
+            .collect();
+        // pages are written in file order
+        pages.sort_by(|(left, _), (right, _)| left.cmp(right));
+        pages
+    }
+
+    fn touch(&mut self, key: &PageKey) {
+        self.tick += 1;
+        let frame = self.frames.get_mut(key).unwrap();
+        self.lru.remove(&frame.last_used);
+        frame.last_used = self.tick;
+        self.lru.insert(self.tick, key.clone());
+    }
+
+    fn remove(&mut self, key: &PageKey) -> Option<Frame> {
+        let frame = self.frames.remove(key)?;
+        self.lru.remove(&frame.last_used);
+        Some(frame)
+    }
+
+    // evict least recently used pages until room bytes fit in the budget
+    fn evict(&mut self, room: usize) -> Vec<(PageKey, Vec<u8>)> {
+        let mut evicted = Vec::new();
+        while self.memory_used + room > self.memory_budget {
+            let key = match self.lru.first_key_value() {
+                Some((_, key)) => key.clone(),
+                None => break,
+            };
+            let frame = self.remove(&key).unwrap();
+            self.memory_used -= frame.data.len();
+            self.evictions += 1;
+            if frame.dirty {
+                evicted.push((key, frame.data));
+            }
+        }
+        evicted
+    }
+}
+
+// buffer pools by database directory, every file storage of a directory shares its pool
+fn buffer_pools() -> &'static Mutex<HashMap<PathBuf, Arc<Mutex<BufferPool>>>> {
+    static BUFFER_POOLS: OnceLock<Mutex<HashMap<PathBuf, Arc<Mutex<BufferPool>>>>> = OnceLock::new();
+    BUFFER_POOLS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// the buffer pool of a directory, created on first use
+pub fn shared_buffer_pool(root_dir: &str) -> Arc<Mutex<BufferPool>> {
+    let mut pools = buffer_pools().lock().unwrap_or_else(|error| error.into_inner());
+    pools.entry(directory_key(root_dir)).or_default().clone()
+}
+
+// forget the buffer pool of a directory, once the directory is deleted
+pub fn release_buffer_pool(root_dir: &str) {
+    let mut pools = buffer_pools().lock().unwrap_or_else(|error| error.into_inner());
+    pools.remove(&directory_key(root_dir));
+}
@@ -1,25 +1,44 @@
 // files is a module that contains the file storage logic for the storage module.
 
-use std::{collections::HashMap, io::Write};
+use std::{collections::HashMap, io::{Read, Seek, SeekFrom, Write}, path::Path, sync::{Arc, Mutex, OnceLock}, thread::{self, ThreadId}};
 
 use serde_derive::{Deserialize, Serialize};
 
+use super::buffer::{self, BufferPool, PageKey};
+use super::locks::{self, DirectoryLocks, FileLock, LockMode, TableLock, LOCK_FILE_NAME};
+use super::mvcc::{Snapshot, VacuumHorizon};
+use super::wal::{self, Durability, LogRecord, WriteAheadLog};
+
 // Storage for files database
 
 // Texte file storage
 #[derive(Clone,Serialize,Deserialize,Debug)]
 pub struct FileStorage {
     root_dir: String,
+    // a handle opened for reading does not write to the directory
+    #[serde(skip)]
+    mode: LockMode,
+    // lock file of the directory, taken on first use and held by the handle and its clones
+    #[serde(skip)]
+    lock: Arc<OnceLock<Arc<FileLock>>>,
 }
 
 // File extension for table data and descriptor
 pub const TABLE_FILE_DATA_EXTENSION : &str = "data";
 pub const TABLE_FILE_DESCRIPTOR_EXTENSION: &str  = "desc";
+// last value generated for the auto increment column of a table
+pub const TABLE_FILE_SEQUENCE_EXTENSION: &str = "seq";
+// B+ tree of a secondary index, one file per index named after the index
+pub const TABLE_FILE_INDEX_EXTENSION: &str = "idx";
+// new content of a file, written beside it before replacing it
+pub const TEMP_FILE_EXTENSION: &str = "tmp";
 
 #[derive(Debug, Eq, PartialEq, Hash)]
 pub enum FileExtension {
     Data,
     Descriptor,
+    Sequence,
+    Index,
     Both
 }
 
@@ -28,16 +47,63 @@ pub fn get_file_type_and_extension() -> HashMap<FileExtension, String>
     let mut map = HashMap::new();
     map.insert(FileExtension::Data, TABLE_FILE_DATA_EXTENSION.to_owned());
     map.insert(FileExtension::Descriptor, TABLE_FILE_DESCRIPTOR_EXTENSION.to_owned());
+    map.insert(FileExtension::Sequence, TABLE_FILE_SEQUENCE_EXTENSION.to_owned());
+    map.insert(FileExtension::Index, TABLE_FILE_INDEX_EXTENSION.to_owned());
     map
 }
 
+// Roll back the work left in a directory and forget what the process knows of it, once the last handle
+// of the process lets the directory go. Committed work is written out by its commit already
+pub fn release_directory(root_dir: &str) {
+    let storage = FileStorage::new(root_dir);
+    {
+        let buffer_pool = storage.get_buffer_pool();
+        let mut buffer_pool = buffer_pool.lock().unwrap_or_else(|error| error.into_inner());
+        let wal = storage.get_write_ahead_log();
+        let mut wal = wal.lock().unwrap_or_else(|error| error.into_inner());
+        if wal.is_recovered() {
+            for thread in wal.get_work_threads() {
+                buffer_pool.discard_dirty_files(&wal.get_touched_files(thread));
+                let _ = wal.rollback(thread);
+            }
+        }
+    }
+    buffer::release_buffer_pool(root_dir);
+    wal::release_write_ahead_log(root_dir);
+}
+
 impl FileStorage {
     pub fn new(root_path: &str) -> FileStorage {
+        FileStorage::new_with_mode(root_path, LockMode::Exclusive)
+    }
+
+    // Handle of a database directory opened in a mode on first use
+    pub fn new_with_mode(root_path: &str, mode: LockMode) -> FileStorage {
         FileStorage {
             root_dir: root_path.to_string(),
+            mode,
+            lock: Arc::new(OnceLock::new()),
         }
     }
 
+    // Open a database directory, the handle takes the lock file of the directory now instead of on
+    // first use. A handle opened for reading does not write to the directory
+    pub fn open(root_path: &str, mode: LockMode) -> Result<FileStorage, Box<dyn std::error::Error>> {
+        let storage = FileStorage::new_with_mode(root_path, mode);
+        storage.with_directory(|_, _| Ok(()))?;
+        Ok(storage)
+    }
+
+    // Close the handle, the lock file of the directory is released along with the last handle of the
+    // process using it, clones of the handle included
+    pub fn close(self) {
+        drop(self);
+    }
+
+    pub fn get_mode(&self) -> LockMode {
+        self.mode
+    }
+
     pub fn get_root_dir(&self) -> &str {
         &self.root_dir
     }
@@ -50,51 +116,434 @@ impl FileStorage {
     }
 
     pub fn delete_dir(&self, dir_name: &str) -> Result<(), Box<dyn std::error::Error>> {
-        // Delete a directory if exists
+        // Delete a directory if exists, no other process may use it and no work may be in progress in it.
+        // The files of the log and the lock file go along with the directory, the table files are to be
+        // deleted first
         let path = format!("{}/{}", self.root_dir, dir_name);
-        std::fs::remove_dir(path)?;
+        let storage = FileStorage::open(&path, LockMode::Exclusive)?;
+        storage.with_directory(|_, wal| {
+            if wal.has_pending_work() || wal.has_log_records() {
+                return Err(format!("{}: database has work in progress", path).into());
+            }
+            Ok(())
+        })?;
+        let mut log_files = Vec::new();
+        for entry in std::fs::read_dir(&path)? {
+            let file_name = entry?.file_name().into_string().unwrap();
+            if wal::is_log_file(&file_name) {
+                log_files.push(file_name);
+            } else if file_name != LOCK_FILE_NAME {
+                return Err(format!("{}: database is not empty", path).into());
+            }
+        }
+        for file_name in log_files {
+            std::fs::remove_file(format!("{}/{}", path, file_name))?;
+        }
+        std::fs::remove_file(format!("{}/{}", path, LOCK_FILE_NAME))?;
+        std::fs::remove_dir(&path)?;
+        // the directory is gone, what the process knows of it goes too
+        storage.close();
+        buffer::release_buffer_pool(&path);
+        wal::release_write_ahead_log(&path);
+        locks::release_directory_locks(&path);
         Ok(())
     }
 
     pub fn create_file(&self, file_name: &str) -> Result<(), Box<dyn std::error::Error>> {
         // Create a file if not exists yet
         let path = format!("{}/{}", self.root_dir, file_name);
-        if std::fs::metadata(path.clone()).is_ok() {
-            return Err("File already exists".into());
-        }
-        std::fs::File::create(path)?;
-        Ok(())
+        self.with_directory(|buffer_pool, wal| {
+            if std::fs::metadata(path.clone()).is_ok() {
+                return Err("File already exists".into());
+            }
+            let record = wal.create_record(file_name);
+            wal.append(vec![record])?;
+            std::fs::File::create(path)?;
+            buffer_pool.discard_file_names();
+            Ok(())
+        })
+    }
+
+    pub fn file_exists(&self, file_name: &str) -> bool {
+        // a failed recovery is reported by the next operation on the directory
+        let _ = self.with_directory(|_, _| Ok(()));
+        let path = format!("{}/{}", self.root_dir, file_name);
+        std::fs::metadata(path).is_ok()
     }
 
     pub fn delete_file(&self, file_name: &str) -> Result<(), Box<dyn std::error::Error>> {
-        // Delete a file if exists
+        // Delete a file if exists, the file is kept aside until the work deleting it is committed
+        self.with_directory(|buffer_pool, wal| {
+            self.delete_logged_file(buffer_pool, wal, file_name)
+        })
+    }
+
+    fn delete_logged_file(&self, buffer_pool: &mut BufferPool, wal: &mut WriteAheadLog, file_name: &str) -> Result<(), Box<dyn std::error::Error>> {
         let path = format!("{}/{}", self.root_dir, file_name);
-        std::fs::remove_file(path)?;
+        if std::fs::metadata(&path).is_err() {
+            return Err(format!("File {} does not exist", file_name).into());
+        }
+        buffer_pool.discard_file(file_name);
+        let record = wal.delete_record(file_name);
+        let trash = match &record {
+            LogRecord::DeleteFile { trash, .. } => format!("{}/{}", self.root_dir, trash),
+            _ => unreachable!("A delete record names the trash file"),
+        };
+        wal.append(vec![record])?;
+        std::fs::rename(path, trash)?;
+        buffer_pool.discard_file_names();
         Ok(())
     }
 
+    // Replace a file by another one, the old content of the target is lost
+    pub fn rename_file(&self, file_name: &str, new_file_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.with_directory(|buffer_pool, wal| {
+            self.write_pages(wal, buffer_pool.take_dirty_file(file_name))?;
+            buffer_pool.discard_file(file_name);
+            if std::fs::metadata(format!("{}/{}", self.root_dir, new_file_name)).is_ok() {
+                self.delete_logged_file(buffer_pool, wal, new_file_name)?;
+            }
+            let record = wal.rename_record(file_name, new_file_name);
+            wal.append(vec![record])?;
+            let path = format!("{}/{}", self.root_dir, file_name);
+            let new_path = format!("{}/{}", self.root_dir, new_file_name);
+            std::fs::rename(path, new_path)?;
+            buffer_pool.discard_file(new_file_name);
+            buffer_pool.discard_file_names();
+            Ok(())
+        })
+    }
+
     pub fn read_file(&self, file_name: &str) -> Result<String, Box<dyn std::error::Error>> {
         // Read a file content
         let path = format!("{}/{}", self.root_dir, file_name);
-        let content = std::fs::read_to_string(path)?;
-        Ok(content)
+        self.with_directory(|_, _| Ok(std::fs::read_to_string(path)?))
     }
 
+    // Read a whole file parsed by a function, the parsed value is kept in the buffer pool until the file changes
+    pub fn read_parsed_file<T: Clone + Send + Sync + 'static>(&self, file_name: &str, parse: impl FnOnce(&str) -> Result<T, Box<dyn std::error::Error>>) -> Result<T, Box<dyn std::error::Error>> {
+        let path = format!("{}/{}", self.root_dir, file_name);
+        self.with_directory(|buffer_pool, _| {
+            if let Some(value) = buffer_pool.get_parsed_file(file_name) {
+                return Ok(value);
+            }
+            let value = parse(&std::fs::read_to_string(path)?)?;
+            buffer_pool.put_parsed_file(file_name, value.clone());
+            Ok(value)
+        })
+    }
+
+    // Replace the content of a file, a crash leaves either the old or the new content:
+    // the content is written and synced to a temporary file which is then renamed over the file
     pub fn write_file(&self, file_name: &str, content: &str) -> Result<(), Box<dyn std::error::Error>> {
         let path = format!("{}/{}", self.root_dir, file_name);
-        std::fs::write(path, content)?;
+        let temp_file_name = format!("{}.{}", file_name, TEMP_FILE_EXTENSION);
+        let temp_path = format!("{}/{}", self.root_dir, temp_file_name);
+        self.with_directory(|buffer_pool, wal| {
+            // a temporary file left by a crash is deleted on recovery
+            let records = vec![wal.file_record(file_name)?, wal.create_record(&temp_file_name)];
+            wal.append(records)?;
+            let is_new = std::fs::metadata(&path).is_err();
+            let mut file = std::fs::File::create(&temp_path)?;
+            file.write_all(content.as_bytes())?;
+            wal.sync_file(&file)?;
+            std::fs::rename(&temp_path, &path)?;
+            wal.sync_root_dir()?;
+            buffer_pool.discard_file(file_name);
+            if is_new {
+                buffer_pool.discard_file_names();
+            }
+            Ok(())
+        })
+    }
+
+    // How much of the writes to the directory is synced to disk, shared by every storage of the directory
+    pub fn get_durability(&self) -> Result<Durability, Box<dyn std::error::Error>> {
+        self.with_directory(|_, wal| Ok(wal.get_durability()))
+    }
+
+    pub fn set_durability(&self, durability: Durability) -> Result<(), Box<dyn std::error::Error>> {
+        self.with_directory(|_, wal| {
+            wal.set_durability(durability);
+            Ok(())
+        })
+    }
+
+    // Buffer pool shared by every storage of the same directory
+    pub fn get_buffer_pool(&self) -> Arc<Mutex<BufferPool>> {
+        buffer::shared_buffer_pool(&self.root_dir)
+    }
+
+    // Write-ahead log shared by every storage of the same directory
+    pub fn get_write_ahead_log(&self) -> Arc<Mutex<WriteAheadLog>> {
+        wal::shared_write_ahead_log(&self.root_dir)
+    }
+
+    // Locks shared by every storage of the same directory
+    pub fn get_directory_locks(&self) -> Arc<DirectoryLocks> {
+        locks::shared_directory_locks(&self.root_dir)
+    }
+
+    // Wait for the tables a statement writes, the unit of work of the calling thread owns them until it
+    // is committed or rolled back
+    pub fn lock_tables(&self, table_names: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+        if self.mode == LockMode::Shared {
+            return Err(format!("{}: database is opened for reading", self.root_dir).into());
+        }
+        let work = self.with_directory(|_, wal| Ok(wal.get_work_key()))?;
+        self.get_directory_locks().lock_tables(work, table_names)
+    }
+
+    // Reader/writer lock of a table of the directory
+    pub fn get_table_lock(&self, table_name: &str) -> Arc<TableLock> {
+        self.get_directory_locks().get_table_lock(table_name)
+    }
+
+    // Change the memory budget of the buffer pool, dirty pages evicted to fit in it are written
+    pub fn set_buffer_pool_budget(&self, memory_budget: usize) -> Result<(), Box<dyn std::error::Error>> {
+        self.with_directory(|buffer_pool, wal| self.write_pages(wal, buffer_pool.set_memory_budget(memory_budget)))
+    }
+
+    // lock file of the directory held by the handle, taken on first use
+    fn hold_directory(&self) -> Result<Arc<FileLock>, Box<dyn std::error::Error>> {
+        if let Some(lock) = self.lock.get() {
+            return Ok(lock.clone());
+        }
+        let lock = self.get_directory_locks().lock_file(&self.root_dir, self.mode)?;
+        Ok(self.lock.get_or_init(|| lock).clone())
+    }
+
+    // Run an action on the buffer pool and the log of the directory, both stay locked meanwhile.
+    // The directory is recovered from its log on first use
+    fn with_directory<T>(&self, action: impl FnOnce(&mut BufferPool, &mut WriteAheadLog) -> Result<T, Box<dyn std::error::Error>>) -> Result<T, Box<dyn std::error::Error>> {
+        let lock = self.hold_directory()?;
+        let buffer_pool = self.get_buffer_pool();
+        let mut buffer_pool = buffer_pool.lock().map_err(|_| "Buffer pool lock poisoned")?;
+        let wal = self.get_write_ahead_log();
+        let mut wal = wal.lock().map_err(|_| "Write-ahead log lock poisoned")?;
+        if !wal.is_recovered() {
+            if lock.get_mode() == LockMode::Shared && wal.has_log_records() {
+                return Err(format!("{}: database needs recovery by a writer", self.root_dir).into());
+            }
+            for file_name in wal.recover()? {
+                buffer_pool.discard_file(&file_name);
+            }
+            buffer_pool.discard_file_names();
+        }
+        action(&mut buffer_pool, &mut wal)
+    }
+
+    // Read a page of a file through the buffer pool, fails past the end of the file
+    pub fn read_page(&self, file_name: &str, offset: u64, length: usize) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let key: PageKey = (file_name.to_string(), offset);
+        self.with_directory(|buffer_pool, wal| {
+            if let Some(page) = buffer_pool.get(&key) {
+                if page.len() == length {
+                    return Ok(page);
+                }
+            }
+            let page = self.read_block(file_name, offset, length)?;
+            self.write_pages(wal, buffer_pool.put(key, page.clone(), false))?;
+            Ok(page)
+        })
+    }
+
+    // Write a page of a file into the buffer pool, the file is written when the page is evicted
+    // or the pool is checkpointed
+    pub fn write_page(&self, file_name: &str, offset: u64, page: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        self.with_directory(|buffer_pool, wal| {
+            wal.touch_file(file_name);
+            self.write_pages(wal, buffer_pool.put((file_name.to_string(), offset), page.to_vec(), true))
+        })
+    }
+
+    // Commit the work of the calling thread: its dirty pages are written to their file, the files it
+    // changed are synced and its records are closed in the log
+    pub fn checkpoint(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.commit_work(thread::current().id())
+    }
+
+    // Undo the work of the calling thread since its last checkpoint
+    pub fn rollback(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.rollback_work(thread::current().id())
+    }
+
+    fn commit_work(&self, thread: ThreadId) -> Result<(), Box<dyn std::error::Error>> {
+        self.with_directory(|buffer_pool, wal| {
+            self.write_pages(wal, buffer_pool.take_dirty_files(&wal.get_touched_files(thread)))?;
+            wal.commit(thread)
+        })
+    }
+
+    fn rollback_work(&self, thread: ThreadId) -> Result<(), Box<dyn std::error::Error>> {
+        self.with_directory(|buffer_pool, wal| {
+            buffer_pool.discard_dirty_files(&wal.get_touched_files(thread));
+            for file_name in wal.rollback(thread)? {
+                buffer_pool.discard_file(&file_name);
+            }
+            buffer_pool.discard_file_names();
+            Ok(())
+        })
+    }
+
+    // End a unit of work: it is committed when it succeeded, rolled back otherwise.
+    // Within a transaction, the work of a statement is kept for the commit of the transaction
+    // when it succeeded and is undone on its own otherwise
+    pub fn finish_work<T>(&self, result: Result<T, Box<dyn std::error::Error>>) -> Result<T, Box<dyn std::error::Error>> {
+        let in_transaction = self.get_transaction()?.is_some();
+        let error = match result {
+            Ok(value) => {
+                let finished = if in_transaction { self.end_statement() } else { self.checkpoint() };
+                match finished {
+                    Ok(()) => return Ok(value),
+                    Err(error) => error,
+                }
+            }
+            Err(error) => error,
+        };
+        let rolled_back = if in_transaction { self.rollback_statement() } else { self.rollback() };
+        match rolled_back {
+            Ok(()) => Err(error),
+            Err(rollback_error) => Err(format!("{} (rollback failed: {})", error, rollback_error).into()),
+        }
+    }
+
+    // Id of the transaction of the calling thread, every statement run by the thread belongs to it
+    pub fn get_transaction(&self) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+        self.with_directory(|_, wal| Ok(wal.get_transaction()))
+    }
+
+    // Snapshot of the committed row versions of the directory, the work of the own transaction of
+    // the reader is seen as well
+    pub fn take_snapshot(&self, own: Option<u64>) -> Result<Snapshot, Box<dyn std::error::Error>> {
+        self.with_directory(|_, wal| {
+            let (id, next_id, active) = wal.register_snapshot();
+            Ok(Snapshot::new(&self.root_dir, id, next_id, active, own))
+        })
+    }
+
+    // Transaction id of the work in progress, given on its first change
+    pub fn start_work(&self) -> Result<u64, Box<dyn std::error::Error>> {
+        self.with_directory(|_, wal| wal.start_work())
+    }
+
+    // Versions that the work in progress and the registered snapshots no longer need
+    pub fn get_vacuum_horizon(&self) -> Result<VacuumHorizon, Box<dyn std::error::Error>> {
+        self.with_directory(|_, wal| Ok(VacuumHorizon::new(wal.get_work_ids(), wal.get_snapshots())))
+    }
+
+    // Open a transaction for the calling thread, returns its id. The work of the thread done so far is
+    // committed first. The work of the transaction is seen by the handle running it only, until its commit
+    pub fn begin_transaction(&self) -> Result<u64, Box<dyn std::error::Error>> {
+        if self.get_transaction()?.is_none() {
+            self.checkpoint()?;
+        }
+        self.with_directory(|_, wal| wal.begin_transaction())
+    }
+
+    // Run the statements of an open transaction from the calling thread, the transaction may have been
+    // begun by another one
+    pub fn attach_transaction(&self, transaction: u64) -> Result<(), Box<dyn std::error::Error>> {
+        self.with_directory(|_, wal| wal.attach_transaction(transaction))
+    }
+
+    // Commit the work of a transaction, the tables it owns are released
+    pub fn commit_transaction(&self, transaction: u64) -> Result<(), Box<dyn std::error::Error>> {
+        let thread = self.get_transaction_thread(transaction)?;
+        match self.commit_work(thread) {
+            Ok(()) => self.with_directory(|_, wal| {
+                wal.end_transaction(thread);
+                Ok(())
+            }),
+            // a transaction that cannot be committed is rolled back
+            Err(error) => match self.rollback_transaction(transaction) {
+                Ok(()) => Err(error),
+                Err(rollback_error) => Err(format!("{} (rollback failed: {})", error, rollback_error).into()),
+            },
+        }
+    }
+
+    // Undo the work of a transaction, the tables it owns are released
+    pub fn rollback_transaction(&self, transaction: u64) -> Result<(), Box<dyn std::error::Error>> {
+        let thread = self.get_transaction_thread(transaction)?;
+        let result = self.rollback_work(thread);
+        self.with_directory(|_, wal| {
+            wal.end_transaction(thread);
+            Ok(())
+        })?;
+        result
+    }
+
+    fn get_transaction_thread(&self, transaction: u64) -> Result<ThreadId, Box<dyn std::error::Error>> {
+        match self.with_directory(|_, wal| Ok(wal.get_transaction_thread(transaction)))? {
+            Some(thread) => Ok(thread),
+            None => Err(format!("Transaction {} is not open", transaction).into()),
+        }
+    }
+
+    // Close a statement of the transaction that succeeded, its pages are written to their file
+    fn end_statement(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.with_directory(|buffer_pool, wal| {
+            self.write_pages(wal, buffer_pool.take_dirty_files(&wal.get_touched_files(thread::current().id())))?;
+            wal.end_statement();
+            Ok(())
+        })
+    }
+
+    // Undo the work of the current statement of the transaction
+    fn rollback_statement(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.with_directory(|buffer_pool, wal| {
+            buffer_pool.discard_dirty_files(&wal.get_touched_files(thread::current().id()));
+            for file_name in wal.rollback_statement()? {
+                buffer_pool.discard_file(&file_name);
+            }
+            buffer_pool.discard_file_names();
+            Ok(())
+        })
+    }
+
+    // pages reach their file once the log holds what they replace
+    fn write_pages(&self, wal: &mut WriteAheadLog, pages: Vec<(PageKey, Vec<u8>)>) -> Result<(), Box<dyn std::error::Error>> {
+        wal.log_pages(pages.iter().map(|((file_name, offset), page)| (file_name.as_str(), *offset, page.len())).collect())?;
+        for ((file_name, offset), page) in pages {
+            self.write_block(&file_name, offset, &page)?;
+        }
+        Ok(())
+    }
+
+    // Read length bytes at an offset of a file, fails past the end of the file.
+    // Blocks bypass the buffer pool and the log
+    pub fn read_block(&self, file_name: &str, offset: u64, length: usize) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let path = format!("{}/{}", self.root_dir, file_name);
+        let mut file = std::fs::File::open(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut block = vec![0; length];
+        file.read_exact(&mut block)?;
+        Ok(block)
+    }
+
+    // Overwrite bytes at an offset of an existing file, the file grows when writing past its end
+    pub fn write_block(&self, file_name: &str, offset: u64, block: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        let path = format!("{}/{}", self.root_dir, file_name);
+        let mut file = std::fs::OpenOptions::new().write(true).open(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(block)?;
         Ok(())
     }
 
     pub fn append_file(&self, file_name: &str, content: &str) -> Result<(), Box<dyn std::error::Error>> {
         // Append a file content if file exist. if file does not exists, it will throw an error
         let path = format!("{}/{}", self.root_dir, file_name);
-        if std::fs::metadata(path.clone()).is_err() {
-            return Err("File does not exist".into());
-        }
-        let mut file = std::fs::OpenOptions::new().append(true).open(path)?;
-        file.write_all(content.as_bytes())?;
-        Ok(())
+        self.with_directory(|_, wal| {
+            if std::fs::metadata(path.clone()).is_err() {
+                return Err("File does not exist".into());
+            }
+            let record = wal.file_record(file_name)?;
+            wal.append(vec![record])?;
+            let mut file = std::fs::OpenOptions::new().append(true).open(path)?;
+            file.write_all(content.as_bytes())?;
+            Ok(())
+        })
     }
 
     // List all files in the root directory
@@ -103,22 +552,33 @@ impl FileStorage {
     }
 
     pub fn list_files_with_extension(&self, extension: FileExtension) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-        // List files with specific extension in the root directory
-        let mut files = Vec::new();
+        // List files with specific extension in the root directory, once recovered from its log.
+        // The names are kept in the buffer pool until a file is added or removed, the files of the log are left out
+        let file_names = self.with_directory(|buffer_pool, _| {
+            if let Some(file_names) = buffer_pool.get_file_names() {
+                return Ok(file_names.clone());
+            }
+            let mut file_names = Vec::new();
+            for entry in std::fs::read_dir(&self.root_dir)? {
+                let entry = entry?;
+                let file_name = entry.file_name().into_string().unwrap();
+                if entry.path().is_file() && file_name != LOCK_FILE_NAME && !wal::is_log_file(&file_name) {
+                    file_names.push(file_name);
+                }
+            }
+            buffer_pool.set_file_names(file_names.clone());
+            Ok(file_names)
+        })?;
         let extensions = get_file_type_and_extension();
-        let _extension = if extensions.get(&extension).is_some() { 
+        let _extension = if extensions.contains_key(&extension) { 
             extensions.get(&extension).unwrap() 
         } else {
              "" 
         };
 
-        for entry in std::fs::read_dir(&self.root_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_file() && (extension == FileExtension::Both || path.extension().unwrap().to_str().unwrap() == _extension) {
-                files.push(entry.file_name().into_string().unwrap());
-            }
-        }
+        let files = file_names.into_iter()
+            .filter(|file_name| extension == FileExtension::Both || Path::new(file_name).extension().and_then(|extension| extension.to_str()) == Some(_extension))
+            .collect();
         Ok(files)
     }
 
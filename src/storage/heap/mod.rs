@@ -0,0 +1,676 @@
+// heap is a module that contains the binary page format of table data files.
+// a header page, then pages of slotted tuples, a tuple too large for a page goes on in overflow pages
+
+use std::borrow::Cow;
+use std::error::Error;
+
+use crate::database::abstraction::Value;
+use crate::storage::files::FileStorage;
+
+pub const HEAP_PAGE_SIZE: usize = 4096;
+
+// first bytes of a heap file, files without them hold the JSON records of older versions
+const HEAP_MAGIC: &[u8; 8] = b"REDHEAP2";
+// first bytes of a heap file without row versions
+const UNVERSIONED_HEAP_MAGIC: &[u8; 8] = b"REDHEAP1";
+const PAGE_HEADER_SIZE: usize = 4;
+const SLOT_SIZE: usize = 4;
+const TUPLE_HEADER_SIZE: usize = 3;
+// flag of the tuples holding a row version after their header
+const FLAG_VERSIONED: u8 = 1;
+const VERSION_SIZE: usize = 16;
+// flag of the tuples whose values are in overflow pages
+const FLAG_OVERFLOW: u8 = 2;
+// largest tuple kept whole in a data page, the largest one a page holds with its slot
+pub const MAX_INLINE_TUPLE_SIZE: usize = HEAP_PAGE_SIZE - PAGE_HEADER_SIZE - SLOT_SIZE;
+// slot count of the overflow pages, a data page never has as many slots
+const OVERFLOW_MARKER: usize = u16::MAX as usize;
+// marker, length of the data held by the page and next page of the chain, 0 for the last one
+const OVERFLOW_HEADER_SIZE: usize = 8;
+const OVERFLOW_DATA_SIZE: usize = HEAP_PAGE_SIZE - OVERFLOW_HEADER_SIZE;
+
+const TAG_NULL: u8 = 0;
+const TAG_INTEGER: u8 = 1;
+const TAG_REAL: u8 = 2;
+const TAG_TEXT: u8 = 3;
+const TAG_BLOB: u8 = 4;
+
+// a row of a heap file with its values
+pub type HeapRow = (RowId, Vec<Value>);
+// a row version of a heap file with its values
+pub type HeapVersion = (RowId, RowVersion, Vec<Value>);
+// the version of a row with its values
+pub type VersionedValues = (RowVersion, Vec<Value>);
+
+// transactions that created and expired a row version, 0 for a version created before transaction
+// ids were kept or not expired
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RowVersion {
+    created_by: u64,
+    expired_by: u64,
+}
+
+impl RowVersion {
+    pub fn new(created_by: u64, expired_by: u64) -> RowVersion {
+        RowVersion { created_by, expired_by }
+    }
+
+    pub fn get_created_by(&self) -> u64 {
+        self.created_by
+    }
+
+    pub fn get_expired_by(&self) -> u64 {
+        self.expired_by
+    }
+
+    // true once the row was replaced or deleted
+    pub fn is_expired(&self) -> bool {
+        self.expired_by != 0
+    }
+}
+
+// location of a row in a heap file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RowId {
+    page: u32,
+    slot: u16,
+}
+
+impl RowId {
+    pub fn new(page: u32, slot: u16) -> RowId {
+        RowId { page, slot }
+    }
+
+    pub fn get_page(&self) -> u32 {
+        self.page
+    }
+
+    pub fn get_slot(&self) -> u16 {
+        self.slot
+    }
+
+    // row ids keep their order as integers, indexes store them this way
+    pub fn to_u64(&self) -> u64 {
+        ((self.page as u64) << 16) | self.slot as u64
+    }
+
+    pub fn from_u64(value: u64) -> RowId {
+        RowId {
+            page: (value >> 16) as u32,
+            slot: (value & 0xFFFF) as u16,
+        }
+    }
+}
+
+pub struct HeapFile {
+    storage: FileStorage,
+    file_name: String,
+    // number of data pages, page 0 being the header
+    page_count: u32,
+    tuple_count: u64,
+    // first data page that may have room for a new row, 0 before the first one
+    insert_page: u32,
+}
+
+impl HeapFile {
+    // create an empty heap file
+    pub fn create(storage: &FileStorage, file_name: &str) -> Result<HeapFile, Box<dyn Error>> {
+        storage.create_file(file_name)?;
+        let heap_file = HeapFile {
+            storage: storage.clone(),
+            file_name: file_name.to_string(),
+            page_count: 0,
+            tuple_count: 0,
+            insert_page: 0,
+        };
+        heap_file.write_header()?;
+        Ok(heap_file)
+    }
+
+    pub fn open(storage: &FileStorage, file_name: &str) -> Result<HeapFile, Box<dyn Error>> {
+        if !HeapFile::is_heap_file(storage, file_name) {
+            return Err(format!("File {} is not a heap file", file_name).into());
+        }
+        HeapFile::read_header(storage, file_name)
+    }
+
+    // open a heap file without row versions, to read its rows only
+    pub fn open_unversioned(storage: &FileStorage, file_name: &str) -> Result<HeapFile, Box<dyn Error>> {
+        if !HeapFile::is_unversioned_heap_file(storage, file_name) {
+            return Err(format!("File {} is not a heap file without row versions", file_name).into());
+        }
+        HeapFile::read_header(storage, file_name)
+    }
+
+    fn read_header(storage: &FileStorage, file_name: &str) -> Result<HeapFile, Box<dyn Error>> {
+        let header = storage.read_page(file_name, 0, HEAP_PAGE_SIZE)?;
+        let page_count = u32::from_le_bytes(header[8..12].try_into()?);
+        // files written before overflow pages add rows to their last page
+        let insert_page = match u32::from_le_bytes(header[20..24].try_into()?) {
+            0 => page_count,
+            insert_page => insert_page,
+        };
+        Ok(HeapFile {
+            storage: storage.clone(),
+            file_name: file_name.to_string(),
+            page_count,
+            tuple_count: u64::from_le_bytes(header[12..20].try_into()?),
+            insert_page,
+        })
+    }
+
+    pub fn is_heap_file(storage: &FileStorage, file_name: &str) -> bool {
+        HeapFile::has_magic(storage, file_name, HEAP_MAGIC)
+    }
+
+    pub fn is_unversioned_heap_file(storage: &FileStorage, file_name: &str) -> bool {
+        HeapFile::has_magic(storage, file_name, UNVERSIONED_HEAP_MAGIC)
+    }
+
+    fn has_magic(storage: &FileStorage, file_name: &str, magic: &[u8]) -> bool {
+        match storage.read_page(file_name, 0, HEAP_PAGE_SIZE) {
+            Ok(header) => header.starts_with(magic),
+            Err(_) => false,
+        }
+    }
+
+    pub fn get_page_count(&self) -> u32 {
+        self.page_count
+    }
+
+    // number of rows in the file
+    pub fn get_tuple_count(&self) -> u64 {
+        self.tuple_count
+    }
+
+    // add a row visible to every transaction
+    pub fn insert(&mut self, values: &[Value]) -> Result<RowId, Box<dyn Error>> {
+        self.insert_version(values, 0)
+    }
+
+    // add a row version created by a transaction to the first page with room for it, from the insert page on.
+    // A new page is added when none has room
+    pub fn insert_version(&mut self, values: &[Value], created_by: u64) -> Result<RowId, Box<dyn Error>> {
+        let tuple = encode_versioned_tuple(values, RowVersion::new(created_by, 0))?;
+        let tuple = self.write_overflow(tuple)?;
+        self.insert_tuple(&tuple)
+    }
+
+    fn insert_tuple(&mut self, tuple: &[u8]) -> Result<RowId, Box<dyn Error>> {
+        // the pages passed over have no room for the tuple, the next rows look for room from the page taking it
+        for page_number in self.insert_page.max(1)..=self.page_count {
+            let mut page = self.read_page(page_number)?;
+            if page.is_overflow() {
+                continue;
+            }
+            if let Some(slot) = page.insert(tuple) {
+                self.write_page(page_number, &page)?;
+                self.insert_page = page_number;
+                return self.inserted(RowId::new(page_number, slot));
+            }
+        }
+        let mut page = Page::new();
+        let slot = page.insert(tuple).unwrap();
+        self.page_count += 1;
+        self.insert_page = self.page_count;
+        self.write_page(self.page_count, &page)?;
+        self.inserted(RowId::new(self.page_count, slot))
+    }
+
+    // values of a row, None when the row does not exist
+    pub fn get(&self, row_id: RowId) -> Result<Option<Vec<Value>>, Box<dyn Error>> {
+        Ok(self.get_version(row_id)?.map(|(_, values)| values))
+    }
+
+    // version and values of a row, None when the row does not exist
+    pub fn get_version(&self, row_id: RowId) -> Result<Option<VersionedValues>, Box<dyn Error>> {
+        if row_id.page == 0 || row_id.page > self.page_count {
+            return Ok(None);
+        }
+        match self.read_page(row_id.page)?.get(row_id.slot) {
+            Some(tuple) => Ok(Some((decode_version(tuple)?, decode_tuple(&self.read_overflow(tuple)?)?))),
+            None => Ok(None),
+        }
+    }
+
+    // mark a row version as replaced or deleted by a transaction, false when the row does not exist.
+    // The tuple keeps its size, the row keeps its id
+    pub fn expire(&mut self, row_id: RowId, expired_by: u64) -> Result<bool, Box<dyn Error>> {
+        if row_id.page == 0 || row_id.page > self.page_count {
+            return Ok(false);
+        }
+        let mut page = self.read_page(row_id.page)?;
+        let offset = match page.get(row_id.slot) {
+            Some(tuple) if tuple[0] & FLAG_VERSIONED == 0 => {
+                return Err(format!("Row {:?} of {} has no row version", row_id, self.file_name).into());
+            }
+            Some(_) => page.slot(row_id.slot).0 + TUPLE_HEADER_SIZE + 8,
+            None => return Ok(false),
+        };
+        page.bytes[offset..offset + 8].copy_from_slice(&expired_by.to_le_bytes());
+        self.write_page(row_id.page, &page)?;
+        Ok(true)
+    }
+
+    // replace the values of a row in place, the row keeps its version and moves to another page
+    // when its page has no room left
+    pub fn update(&mut self, row_id: RowId, values: &[Value]) -> Result<RowId, Box<dyn Error>> {
+        let missing = || format!("Row {:?} does not exist in {}", row_id, self.file_name);
+        if row_id.page == 0 || row_id.page > self.page_count {
+            return Err(missing().into());
+        }
+        let mut page = self.read_page(row_id.page)?;
+        let version = match page.get(row_id.slot) {
+            Some(tuple) => {
+                self.free_overflow(tuple)?;
+                decode_version(tuple)?
+            }
+            None => return Err(missing().into()),
+        };
+        let tuple = self.write_overflow(encode_versioned_tuple(values, version)?)?;
+        if page.update(row_id.slot, &tuple) {
+            self.write_page(row_id.page, &page)?;
+            // overflow pages may have been added or freed
+            self.write_header()?;
+            return Ok(row_id);
+        }
+        page.delete(row_id.slot);
+        self.write_page(row_id.page, &page)?;
+        self.freed(row_id.page);
+        self.tuple_count -= 1;
+        self.insert_tuple(&tuple)
+    }
+
+    // remove a row, false when the row does not exist
+    pub fn delete(&mut self, row_id: RowId) -> Result<bool, Box<dyn Error>> {
+        if row_id.page == 0 || row_id.page > self.page_count {
+            return Ok(false);
+        }
+        let mut page = self.read_page(row_id.page)?;
+        match page.get(row_id.slot) {
+            Some(tuple) => self.free_overflow(tuple)?,
+            None => return Ok(false),
+        }
+        page.delete(row_id.slot);
+        self.write_page(row_id.page, &page)?;
+        self.freed(row_id.page);
+        self.tuple_count -= 1;
+        self.write_header()?;
+        Ok(true)
+    }
+
+    // every row in page and slot order, whatever its version
+    pub fn scan(&self) -> Result<Vec<HeapRow>, Box<dyn Error>> {
+        Ok(self.scan_versions()?.into_iter().map(|(row_id, _, values)| (row_id, values)).collect())
+    }
+
+    // every row version in page and slot order
+    pub fn scan_versions(&self) -> Result<Vec<HeapVersion>, Box<dyn Error>> {
+        let mut rows = Vec::new();
+        for page_number in 1..=self.page_count {
+            let page = self.read_page(page_number)?;
+            for slot in 0..page.slot_count() {
+                if let Some(tuple) = page.get(slot) {
+                    rows.push((RowId::new(page_number, slot), decode_version(tuple)?, decode_tuple(&self.read_overflow(tuple)?)?));
+                }
+            }
+        }
+        Ok(rows)
+    }
+
+    // move the values of a large tuple to new overflow pages, returns the tuple to keep in a slot
+    fn write_overflow(&mut self, tuple: Vec<u8>) -> Result<Vec<u8>, Box<dyn Error>> {
+        if tuple.len() <= MAX_INLINE_TUPLE_SIZE {
+            return Ok(tuple);
+        }
+        let header_size = TUPLE_HEADER_SIZE + VERSION_SIZE;
+        let data = &tuple[header_size..];
+        let first_page = self.page_count + 1;
+        let chunks: Vec<&[u8]> = data.chunks(OVERFLOW_DATA_SIZE).collect();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let next = if i + 1 < chunks.len() { first_page + i as u32 + 1 } else { 0 };
+            let mut page = Page { bytes: vec![0; HEAP_PAGE_SIZE] };
+            page.write_u16(0, OVERFLOW_MARKER);
+            page.write_u16(2, chunk.len());
+            page.bytes[4..8].copy_from_slice(&next.to_le_bytes());
+            page.bytes[OVERFLOW_HEADER_SIZE..OVERFLOW_HEADER_SIZE + chunk.len()].copy_from_slice(chunk);
+            self.page_count += 1;
+            self.write_page(self.page_count, &page)?;
+        }
+        let mut stub = tuple[..header_size].to_vec();
+        stub[0] |= FLAG_OVERFLOW;
+        stub.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        stub.extend_from_slice(&first_page.to_le_bytes());
+        Ok(stub)
+    }
+
+    // the whole tuple kept in a slot, with its values read back from the overflow pages
+    fn read_overflow<'a>(&self, tuple: &'a [u8]) -> Result<Cow<'a, [u8]>, Box<dyn Error>> {
+        let (length, mut next) = match overflow_chain(tuple)? {
+            Some(chain) => chain,
+            None => return Ok(Cow::Borrowed(tuple)),
+        };
+        let header_size = TUPLE_HEADER_SIZE + VERSION_SIZE;
+        let mut whole = tuple[..header_size].to_vec();
+        whole[0] &= !FLAG_OVERFLOW;
+        while next != 0 {
+            let page = self.read_overflow_page(next)?;
+            let chunk_length = page.read_u16(2);
+            whole.extend_from_slice(&page.bytes[OVERFLOW_HEADER_SIZE..OVERFLOW_HEADER_SIZE + chunk_length]);
+            next = u32::from_le_bytes(page.bytes[4..8].try_into()?);
+        }
+        if whole.len() != header_size + length {
+            return Err(format!("Overflow pages of a tuple of {} are truncated", self.file_name).into());
+        }
+        Ok(Cow::Owned(whole))
+    }
+
+    // turn the overflow pages of a tuple into empty data pages
+    fn free_overflow(&mut self, tuple: &[u8]) -> Result<(), Box<dyn Error>> {
+        let mut next = match overflow_chain(tuple)? {
+            Some((_, first_page)) => first_page,
+            None => return Ok(()),
+        };
+        while next != 0 {
+            let page = self.read_overflow_page(next)?;
+            self.write_page(next, &Page::new())?;
+            self.freed(next);
+            next = u32::from_le_bytes(page.bytes[4..8].try_into()?);
+        }
+        Ok(())
+    }
+
+    // a page got room, new rows look for room from it on
+    fn freed(&mut self, page_number: u32) {
+        self.insert_page = self.insert_page.min(page_number);
+    }
+
+    fn read_overflow_page(&self, page_number: u32) -> Result<Page, Box<dyn Error>> {
+        let not_overflow = || format!("Page {} of {} is not an overflow page", page_number, self.file_name);
+        if page_number > self.page_count {
+            return Err(not_overflow().into());
+        }
+        let page = self.read_page(page_number)?;
+        if !page.is_overflow() {
+            return Err(not_overflow().into());
+        }
+        Ok(page)
+    }
+
+    fn inserted(&mut self, row_id: RowId) -> Result<RowId, Box<dyn Error>> {
+        self.tuple_count += 1;
+        self.write_header()?;
+        Ok(row_id)
+    }
+
+    fn write_header(&self) -> Result<(), Box<dyn Error>> {
+        let mut header = Vec::with_capacity(HEAP_PAGE_SIZE);
+        header.extend_from_slice(HEAP_MAGIC);
+        header.extend_from_slice(&self.page_count.to_le_bytes());
+        header.extend_from_slice(&self.tuple_count.to_le_bytes());
+        header.extend_from_slice(&self.insert_page.to_le_bytes());
+        header.resize(HEAP_PAGE_SIZE, 0);
+        self.storage.write_page(&self.file_name, 0, &header)
+    }
+
+    fn read_page(&self, page_number: u32) -> Result<Page, Box<dyn Error>> {
+        let offset = page_number as u64 * HEAP_PAGE_SIZE as u64;
+        Ok(Page { bytes: self.storage.read_page(&self.file_name, offset, HEAP_PAGE_SIZE)? })
+    }
+
+    fn write_page(&self, page_number: u32, page: &Page) -> Result<(), Box<dyn Error>> {
+        let offset = page_number as u64 * HEAP_PAGE_SIZE as u64;
+        self.storage.write_page(&self.file_name, offset, &page.bytes)
+    }
+}
+
+// data page of a heap file
+struct Page {
+    bytes: Vec<u8>,
+}
+
+impl Page {
+    fn new() -> Page {
+        let mut page = Page { bytes: vec![0; HEAP_PAGE_SIZE] };
+        page.set_free_end(HEAP_PAGE_SIZE);
+        page
+    }
+
+    fn read_u16(&self, offset: usize) -> usize {
+        u16::from_le_bytes([self.bytes[offset], self.bytes[offset + 1]]) as usize
+    }
+
+    fn write_u16(&mut self, offset: usize, value: usize) {
+        self.bytes[offset..offset + 2].copy_from_slice(&(value as u16).to_le_bytes());
+    }
+
+    // an overflow page has no slots for rows
+    fn slot_count(&self) -> u16 {
+        if self.is_overflow() {
+            return 0;
+        }
+        self.read_u16(0) as u16
+    }
+
+    fn is_overflow(&self) -> bool {
+        self.read_u16(0) == OVERFLOW_MARKER
+    }
+
+    fn set_slot_count(&mut self, slot_count: usize) {
+        self.write_u16(0, slot_count);
+    }
+
+    // start of the tuple area
+    fn free_end(&self) -> usize {
+        self.read_u16(2)
+    }
+
+    fn set_free_end(&mut self, free_end: usize) {
+        self.write_u16(2, free_end);
+    }
+
+    fn slot(&self, slot: u16) -> (usize, usize) {
+        let offset = PAGE_HEADER_SIZE + slot as usize * SLOT_SIZE;
+        (self.read_u16(offset), self.read_u16(offset + 2))
+    }
+
+    fn set_slot(&mut self, slot: u16, tuple_offset: usize, length: usize) {
+        let offset = PAGE_HEADER_SIZE + slot as usize * SLOT_SIZE;
+        self.write_u16(offset, tuple_offset);
+        self.write_u16(offset + 2, length);
+    }
+
+    fn get(&self, slot: u16) -> Option<&[u8]> {
+        if slot >= self.slot_count() {
+            return None;
+        }
+        match self.slot(slot) {
+            (_, 0) => None,
+            (offset, length) => Some(&self.bytes[offset..offset + length]),
+        }
+    }
+
+    // bytes between the slot directory and the tuple area
+    fn contiguous_free(&self) -> usize {
+        self.free_end() - PAGE_HEADER_SIZE - self.slot_count() as usize * SLOT_SIZE
+    }
+
+    // free bytes once deleted tuples are compacted away
+    fn total_free(&self) -> usize {
+        let live: usize = (0..self.slot_count()).map(|slot| self.slot(slot).1).sum();
+        HEAP_PAGE_SIZE - PAGE_HEADER_SIZE - self.slot_count() as usize * SLOT_SIZE - live
+    }
+
+    // store a tuple in a free slot, None when the page has no room for it
+    fn insert(&mut self, tuple: &[u8]) -> Option<u16> {
+        let free_slot = (0..self.slot_count()).find(|slot| self.slot(*slot).1 == 0);
+        let needed = tuple.len() + if free_slot.is_none() { SLOT_SIZE } else { 0 };
+        if self.total_free() < needed {
+            return None;
+        }
+        let slot = match free_slot {
+            Some(slot) => slot,
+            None => {
+                let slot = self.slot_count();
+                self.set_slot_count(slot as usize + 1);
+                self.set_slot(slot, 0, 0);
+                slot
+            }
+        };
+        self.place(slot, tuple);
+        Some(slot)
+    }
+
+    // replace a tuple within the page, false when the page has no room for the new tuple
+    fn update(&mut self, slot: u16, tuple: &[u8]) -> bool {
+        let (offset, length) = self.slot(slot);
+        if tuple.len() <= length {
+            self.bytes[offset..offset + tuple.len()].copy_from_slice(tuple);
+            self.set_slot(slot, offset, tuple.len());
+            return true;
+        }
+        if self.total_free() + length < tuple.len() {
+            return false;
+        }
+        self.set_slot(slot, 0, 0);
+        self.place(slot, tuple);
+        true
+    }
+
+    fn delete(&mut self, slot: u16) -> bool {
+        if self.get(slot).is_none() {
+            return false;
+        }
+        self.set_slot(slot, 0, 0);
+        true
+    }
+
+    // write a tuple at the start of the tuple area, compacting the page first when needed
+    fn place(&mut self, slot: u16, tuple: &[u8]) {
+        if self.contiguous_free() < tuple.len() {
+            self.compact();
+        }
+        let offset = self.free_end() - tuple.len();
+        self.bytes[offset..offset + tuple.len()].copy_from_slice(tuple);
+        self.set_free_end(offset);
+        self.set_slot(slot, offset, tuple.len());
+    }
+
+    // move the live tuples to the end of the page, slots keep their number
+    fn compact(&mut self) {
+        let tuples: Vec<(u16, Vec<u8>)> = (0..self.slot_count())
+            .filter_map(|slot| self.get(slot).map(|tuple| (slot, tuple.to_vec())))
+            .collect();
+        let mut free_end = HEAP_PAGE_SIZE;
+        for (slot, tuple) in tuples {
+            free_end -= tuple.len();
+            self.bytes[free_end..free_end + tuple.len()].copy_from_slice(&tuple);
+            self.set_slot(slot, free_end, tuple.len());
+        }
+        self.set_free_end(free_end);
+    }
+}
+
+pub fn encode_tuple(values: &[Value]) -> Result<Vec<u8>, Box<dyn Error>> {
+    encode_values(vec![0], values)
+}
+
+pub fn encode_versioned_tuple(values: &[Value], version: RowVersion) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut tuple = vec![FLAG_VERSIONED];
+    tuple.extend_from_slice(&(values.len() as u16).to_le_bytes());
+    tuple.extend_from_slice(&version.created_by.to_le_bytes());
+    tuple.extend_from_slice(&version.expired_by.to_le_bytes());
+    encode_values(tuple, values)
+}
+
+fn encode_values(header: Vec<u8>, values: &[Value]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut tuple = header;
+    if tuple.len() == 1 {
+        tuple.extend_from_slice(&(values.len() as u16).to_le_bytes());
+    }
+    for value in values {
+        match value {
+            Value::Null => tuple.push(TAG_NULL),
+            Value::Integer(value) => {
+                tuple.push(TAG_INTEGER);
+                tuple.extend_from_slice(&value.to_le_bytes());
+            }
+            Value::Real(value) => {
+                tuple.push(TAG_REAL);
+                tuple.extend_from_slice(&value.to_le_bytes());
+            }
+            Value::Text(value) => {
+                tuple.push(TAG_TEXT);
+                tuple.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                tuple.extend_from_slice(value.as_bytes());
+            }
+            Value::Blob(value) => {
+                tuple.push(TAG_BLOB);
+                tuple.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                tuple.extend_from_slice(value);
+            }
+        }
+    }
+    Ok(tuple)
+}
+
+// length of the values and first page of the overflow pages of a tuple, None for a tuple kept whole
+fn overflow_chain(tuple: &[u8]) -> Result<Option<(usize, u32)>, Box<dyn Error>> {
+    if tuple[0] & FLAG_OVERFLOW == 0 {
+        return Ok(None);
+    }
+    let header_size = TUPLE_HEADER_SIZE + VERSION_SIZE;
+    let chain = tuple.get(header_size..header_size + 8).ok_or("Truncated tuple")?;
+    Ok(Some((u32::from_le_bytes(chain[..4].try_into()?) as usize, u32::from_le_bytes(chain[4..].try_into()?))))
+}
+
+// version of the row held by a tuple, a tuple without version is visible to every transaction
+pub fn decode_version(tuple: &[u8]) -> Result<RowVersion, Box<dyn Error>> {
+    if tuple.first().is_none_or(|flags| flags & FLAG_VERSIONED == 0) {
+        return Ok(RowVersion::default());
+    }
+    let version = tuple.get(TUPLE_HEADER_SIZE..TUPLE_HEADER_SIZE + VERSION_SIZE).ok_or("Truncated tuple")?;
+    Ok(RowVersion::new(u64::from_le_bytes(version[..8].try_into()?), u64::from_le_bytes(version[8..].try_into()?)))
+}
+
+pub fn decode_tuple(tuple: &[u8]) -> Result<Vec<Value>, Box<dyn Error>> {
+    let truncated = || -> Box<dyn Error> { "Truncated tuple".into() };
+    if tuple.len() < TUPLE_HEADER_SIZE {
+        return Err(truncated());
+    }
+    let header_size = if tuple[0] & FLAG_VERSIONED == 0 { TUPLE_HEADER_SIZE } else { TUPLE_HEADER_SIZE + VERSION_SIZE };
+    let mut reader = TupleReader { tuple, position: header_size };
+    let value_count = u16::from_le_bytes([tuple[1], tuple[2]]) as usize;
+    let mut values = Vec::with_capacity(value_count);
+    for _ in 0..value_count {
+        let value = match reader.read(1).ok_or_else(truncated)?[0] {
+            TAG_NULL => Value::Null,
+            TAG_INTEGER => Value::Integer(i64::from_le_bytes(reader.read(8).ok_or_else(truncated)?.try_into()?)),
+            TAG_REAL => Value::Real(f64::from_le_bytes(reader.read(8).ok_or_else(truncated)?.try_into()?)),
+            TAG_TEXT => {
+                let length = u32::from_le_bytes(reader.read(4).ok_or_else(truncated)?.try_into()?) as usize;
+                Value::Text(String::from_utf8(reader.read(length).ok_or_else(truncated)?.to_vec())?)
+            }
+            TAG_BLOB => {
+                let length = u32::from_le_bytes(reader.read(4).ok_or_else(truncated)?.try_into()?) as usize;
+                Value::Blob(reader.read(length).ok_or_else(truncated)?.to_vec())
+            }
+            tag => return Err(format!("Unknown value tag {} in tuple", tag).into()),
+        };
+        values.push(value);
+    }
+    Ok(values)
+}
+
+struct TupleReader<'a> {
+    tuple: &'a [u8],
+    position: usize,
+}
+
+impl<'a> TupleReader<'a> {
+    fn read(&mut self, length: usize) -> Option<&'a [u8]> {
+        let bytes = self.tuple.get(self.position..self.position.checked_add(length)?)?;
+        self.position += length;
+        Some(bytes)
+    }
+}
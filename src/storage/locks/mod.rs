@@ -0,0 +1,242 @@
+// locks is a module that contains the locks shared by every handle of a database directory in a process.
+// tables are owned by one unit of work at a time, processes are kept apart by a lock file
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, OnceLock, Weak};
+use std::thread::{self, ThreadId};
+
+use super::{directory_key, files};
+
+// lock file of a database directory, next to the table files
+pub const LOCK_FILE_NAME: &str = "red.lock";
+
+// how a process holds the lock file of a directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LockMode {
+    // the process reads the directory, along with other readers
+    Shared,
+    // the process reads and writes the directory, alone
+    #[default]
+    Exclusive,
+}
+
+// the lock file held by the process, other processes see the lock until the last handle holding it drops it
+#[derive(Debug)]
+pub struct FileLock {
+    root_dir: String,
+    file: Option<File>,
+    mode: LockMode,
+}
+
+#[derive(Debug, Default)]
+pub struct DirectoryLocks {
+    owners: Mutex<TableOwners>,
+    owners_released: Condvar,
+    tables: Mutex<HashMap<String, Arc<TableLock>>>,
+    // the handles of the process hold the lock file, the locks only know of it
+    file_lock: Mutex<Weak<FileLock>>,
+}
+
+// units of work owning the tables of a directory, a unit is known by the key of its work in the log
+#[derive(Debug, Default)]
+struct TableOwners {
+    tables: HashMap<String, u64>,
+    // unit each waiting unit waits for
+    waiting: HashMap<u64, u64>,
+}
+
+// reader/writer lock of a table
+#[derive(Debug, Default)]
+pub struct TableLock {
+    state: Mutex<TableLockState>,
+    released: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct TableLockState {
+    readers: usize,
+    // thread holding the lock for writing
+    writer: Option<ThreadId>,
+}
+
+// a table lock held for reading or writing, released when dropped
+#[derive(Debug)]
+pub struct TableGuard {
+    lock: Arc<TableLock>,
+    write: bool,
+}
+
+impl DirectoryLocks {
+    // own tables for a unit of work, waiting for the units owning them to end. A wait that would never
+    // end fails, the tables owned by the call on the way are released
+    pub fn lock_tables(&self, work: u64, table_names: &[String]) -> Result<(), Box<dyn Error>> {
+        let mut table_names = table_names.to_vec();
+        table_names.sort();
+        table_names.dedup();
+        let mut owners = self.owners.lock().map_err(|_| "Table owners lock poisoned")?;
+        let mut taken = Vec::new();
+        for table_name in table_names {
+            loop {
+                let owner = match owners.tables.get(&table_name) {
+                    None => {
+                        owners.tables.insert(table_name.clone(), work);
+                        taken.push(table_name.clone());
+                        break;
+                    }
+                    Some(owner) if *owner == work => break,
+                    Some(owner) => *owner,
+                };
+                if owners.waits_for(owner, work) {
+                    for table_name in taken {
+                        owners.tables.remove(&table_name);
+                    }
+                    self.owners_released.notify_all();
+                    return Err(format!("Deadlock on table {}, it is owned by work waiting for this one", table_name).into());
+                }
+                owners.waiting.insert(work, owner);
+                owners = self.owners_released.wait(owners).map_err(|_| "Table owners lock poisoned")?;
+                owners.waiting.remove(&work);
+            }
+        }
+        Ok(())
+    }
+
+    // release the tables owned by a unit of work, once it ended
+    pub fn release_tables(&self, work: u64) {
+        let mut owners = self.owners.lock().unwrap_or_else(|error| error.into_inner());
+        owners.tables.retain(|_, owner| *owner != work);
+        self.owners_released.notify_all();
+    }
+
+    // take the lock file of the directory for a handle, the lock of the process is shared when it holds
+    // one already. A reader process is refused the exclusive lock until its last reader handle is closed
+    pub fn lock_file(&self, root_dir: &str, mode: LockMode) -> Result<Arc<FileLock>, Box<dyn Error>> {
+        let mut file_lock = self.file_lock.lock().unwrap_or_else(|error| error.into_inner());
+        if let Some(held) = file_lock.upgrade() {
+            // the last handle may drop the lock, which takes the file lock of the locks again
+            drop(file_lock);
+            if mode == LockMode::Exclusive && held.get_mode() == LockMode::Shared {
+                return Err(format!("{}: database is locked", root_dir).into());
+            }
+            return Ok(held);
+        }
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(Path::new(root_dir).join(LOCK_FILE_NAME))?;
+        let locked = match mode {
+            LockMode::Shared => file.try_lock_shared(),
+            LockMode::Exclusive => file.try_lock(),
+        };
+        match locked {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => return Err(format!("{}: database is locked", root_dir).into()),
+            Err(TryLockError::Error(error)) => return Err(error.into()),
+        }
+        let held = Arc::new(FileLock { root_dir: root_dir.to_string(), file: Some(file), mode });
+        *file_lock = Arc::downgrade(&held);
+        Ok(held)
+    }
+
+    // mode the process holds the lock file in, None while no handle of the process uses the directory
+    pub fn get_lock_mode(&self) -> Option<LockMode> {
+        let file_lock = self.file_lock.lock().unwrap_or_else(|error| error.into_inner());
+        file_lock.upgrade().map(|held| held.get_mode())
+    }
+
+    pub fn get_table_lock(&self, table_name: &str) -> Arc<TableLock> {
+        let mut tables = self.tables.lock().unwrap_or_else(|error| error.into_inner());
+        tables.entry(table_name.to_string()).or_default().clone()
+    }
+}
+
+impl FileLock {
+    pub fn get_mode(&self) -> LockMode {
+        self.mode
+    }
+}
+
+impl Drop for FileLock {
+    // the last handle of the process lets the directory go: the work left in it is rolled back and what
+    // the process knows of the directory is forgotten, other processes may change it from now on
+    fn drop(&mut self) {
+        let locks = shared_directory_locks(&self.root_dir);
+        let _file_lock = locks.file_lock.lock().unwrap_or_else(|error| error.into_inner());
+        files::release_directory(&self.root_dir);
+        *locks.owners.lock().unwrap_or_else(|error| error.into_inner()) = TableOwners::default();
+        self.file = None;
+    }
+}
+
+impl TableOwners {
+    // true when a unit of work waits for another one, directly or through the units it waits for
+    fn waits_for(&self, work: u64, other: u64) -> bool {
+        let mut next = work;
+        // a chain of waits is no longer than the number of waiting units
+        for _ in 0..=self.waiting.len() {
+            match self.waiting.get(&next) {
+                Some(waited) if *waited == other => return true,
+                Some(waited) => next = *waited,
+                None => return false,
+            }
+        }
+        false
+    }
+}
+
+impl TableLock {
+    // wait until no writer holds the table
+    pub fn read(self: &Arc<Self>) -> Result<TableGuard, Box<dyn Error>> {
+        let mut state = self.state.lock().map_err(|_| "Table lock poisoned")?;
+        if state.writer == Some(thread::current().id()) {
+            return Err("A transaction is open on this database, its statements run through it".into());
+        }
+        while state.writer.is_some() {
+            state = self.released.wait(state).map_err(|_| "Table lock poisoned")?;
+        }
+        state.readers += 1;
+        Ok(TableGuard { lock: self.clone(), write: false })
+    }
+
+    // wait until neither a reader nor a writer holds the table
+    pub fn write(self: &Arc<Self>) -> Result<TableGuard, Box<dyn Error>> {
+        let mut state = self.state.lock().map_err(|_| "Table lock poisoned")?;
+        if state.writer == Some(thread::current().id()) {
+            return Err("A transaction is open on this database, its statements run through it".into());
+        }
+        while state.writer.is_some() || state.readers > 0 {
+            state = self.released.wait(state).map_err(|_| "Table lock poisoned")?;
+        }
+        state.writer = Some(thread::current().id());
+        Ok(TableGuard { lock: self.clone(), write: true })
+    }
+}
+
+impl Drop for TableGuard {
+    fn drop(&mut self) {
+        let mut state = self.lock.state.lock().unwrap_or_else(|error| error.into_inner());
+        if self.write {
+            state.writer = None;
+        } else {
+            state.readers -= 1;
+        }
+        self.lock.released.notify_all();
+    }
+}
+
+// locks by database directory, every file storage of a directory shares its locks
+fn directory_locks() -> &'static Mutex<HashMap<PathBuf, Arc<DirectoryLocks>>> {
+    static DIRECTORY_LOCKS: OnceLock<Mutex<HashMap<PathBuf, Arc<DirectoryLocks>>>> = OnceLock::new();
+    DIRECTORY_LOCKS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+pub fn shared_directory_locks(root_dir: &str) -> Arc<DirectoryLocks> {
+    let mut locks = directory_locks().lock().unwrap_or_else(|error| error.into_inner());
+    locks.entry(directory_key(root_dir)).or_default().clone()
+}
+
+// forget the locks of a directory, the lock file is closed once no handle uses the locks anymore
+pub fn release_directory_locks(root_dir: &str) {
+    let mut locks = directory_locks().lock().unwrap_or_else(|error| error.into_inner());
+    locks.remove(&directory_key(root_dir));
+}
@@ -1,2 +1,15 @@
+use std::path::PathBuf;
+
+pub mod buffer;
 pub mod files; 
+pub mod heap;
+pub mod locks;
+pub mod mvcc;
 pub mod persistence;
+pub mod wal;
+
+// key of a database directory in the registries of the process, the same directory reached through
+// different paths shares its buffer pool, log and locks
+pub fn directory_key(root_dir: &str) -> PathBuf {
+    std::path::absolute(root_dir).unwrap_or_else(|_| PathBuf::from(root_dir))
+}
@@ -0,0 +1,67 @@
+// mvcc is a module that contains the visibility rules of row versions.
+// a snapshot sees the versions of work committed before it and of its own transaction
+
+use crate::storage::heap::RowVersion;
+use crate::storage::wal;
+
+// what a reader sees of the row versions of a directory, released from the directory when dropped
+#[derive(Debug)]
+pub struct Snapshot {
+    root_dir: String,
+    id: u64,
+    // work with a lower id is committed, unless it was in progress
+    next_id: u64,
+    active: Vec<u64>,
+    // transaction of the reader, its own work is seen
+    own: Option<u64>,
+}
+
+impl Snapshot {
+    pub fn new(root_dir: &str, id: u64, next_id: u64, active: Vec<u64>, own: Option<u64>) -> Snapshot {
+        Snapshot { root_dir: root_dir.to_string(), id, next_id, active, own }
+    }
+
+    pub fn get_next_id(&self) -> u64 {
+        self.next_id
+    }
+
+    // true when the work of a transaction is seen, 0 stands for work older than transaction ids
+    pub fn sees(&self, transaction: u64) -> bool {
+        transaction == 0 || Some(transaction) == self.own || (transaction < self.next_id && !self.active.contains(&transaction))
+    }
+
+    pub fn sees_version(&self, version: &RowVersion) -> bool {
+        self.sees(version.get_created_by()) && !(version.is_expired() && self.sees(version.get_expired_by()))
+    }
+}
+
+impl Drop for Snapshot {
+    fn drop(&mut self) {
+        let wal = wal::shared_write_ahead_log(&self.root_dir);
+        let mut wal = wal.lock().unwrap_or_else(|error| error.into_inner());
+        wal.release_snapshot(self.id);
+    }
+}
+
+// which expired versions no reader needs anymore
+#[derive(Debug)]
+pub struct VacuumHorizon {
+    // transaction ids of the work in progress
+    work_ids: Vec<u64>,
+    // next transaction id and work in progress of the registered snapshots
+    snapshots: Vec<(u64, Vec<u64>)>,
+}
+
+impl VacuumHorizon {
+    pub fn new(work_ids: Vec<u64>, snapshots: Vec<(u64, Vec<u64>)>) -> VacuumHorizon {
+        VacuumHorizon { work_ids, snapshots }
+    }
+
+    // true when a version was expired by committed work that every snapshot sees
+    pub fn is_dead(&self, version: &RowVersion) -> bool {
+        let expired_by = version.get_expired_by();
+        version.is_expired()
+            && !self.work_ids.contains(&expired_by)
+            && self.snapshots.iter().all(|(next_id, active)| expired_by < *next_id && !active.contains(&expired_by))
+    }
+}
@@ -1,34 +1,207 @@
 // persistence is a module that contains the persistence logic for the storage module.
+// rows are stored as versions, a statement owns the tables it writes until its work ends
 
-use crate::database::{self, abstraction::{DatabaseTrait, Table, DML}};
-use crate::database::abstraction::Record;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Mutex;
+
+use crate::database::abstraction::{AlterTableOperation, BPlusTree, Column, ColumnDefault, DataType, ForeignKey, Index, InsertResult, Query, Record, ReferentialAction, ResultSet, Table, Value, DML};
+use crate::database::btree;
+use crate::database::evaluator;
+use crate::database::parser;
+use crate::database::parser::{Expression, OrderByItem, SelectItem, Statement};
+use crate::database::planner::{self, PlanNode};
 
 use serde_json;
 
-use super::files::{FileStorage, TABLE_FILE_DATA_EXTENSION, TABLE_FILE_DESCRIPTOR_EXTENSION};
+use super::heap::{HeapFile, HeapRow, HeapVersion, RowId};
+use super::locks::TableGuard;
+use super::mvcc::Snapshot;
+use super::files::{FileExtension, FileStorage, TABLE_FILE_DATA_EXTENSION, TABLE_FILE_DESCRIPTOR_EXTENSION, TABLE_FILE_INDEX_EXTENSION, TABLE_FILE_SEQUENCE_EXTENSION, TEMP_FILE_EXTENSION};
+
+// encoded size of the largest row id or integer in an index key
+const INDEX_INTEGER_SIZE: usize = 20;
+
+// index entries map (indexed values..., row id) to the row id in the data file
+type IndexTree = BPlusTree<Vec<Value>, u64>;
 
 pub struct DataHandler{
-    storage: FileStorage
+    storage: FileStorage,
+    // cut text values longer than their column length instead of rejecting them
+    truncate_text: bool,
+    // transaction of the handle, its reads see its work before the commit
+    transaction: Option<u64>,
+    // the transaction was opened by a BEGIN statement of the handle, it is rolled back along with the handle
+    begun: bool,
+    // locks of the tables whose descriptor the transaction of the handle changed, held until its end
+    table_locks: Mutex<HashMap<String, TableGuard>>
 }
 
 impl DataHandler {
     pub fn new_from_path(database_path: String) -> DataHandler {
         DataHandler{
-            storage: FileStorage::new(&database_path)
+            storage: FileStorage::new(&database_path),
+            truncate_text: false,
+            transaction: None,
+            begun: false,
+            table_locks: Mutex::new(HashMap::new())
         }
     }
 
     pub fn new_from_storage(storage: FileStorage) -> DataHandler {
         DataHandler{
-            storage
+            storage,
+            truncate_text: false,
+            transaction: None,
+            begun: false,
+            table_locks: Mutex::new(HashMap::new())
+        }
+    }
+
+    pub fn is_truncating_text(&self) -> bool {
+        self.truncate_text
+    }
+
+    pub fn set_truncate_text(&mut self, truncate_text: bool) {
+        self.truncate_text = truncate_text;
+    }
+
+    pub fn get_transaction(&self) -> Option<u64> {
+        self.transaction
+    }
+
+    pub fn set_transaction(&mut self, transaction: Option<u64>) {
+        self.transaction = transaction;
+    }
+
+    // run a BEGIN, COMMIT or ROLLBACK statement, the transaction opened by BEGIN is the one of the handle
+    pub fn execute_transaction_control(&mut self, query: Query) -> Result<(), Box<dyn std::error::Error>> {
+        match query.parse()? {
+            Statement::Begin => {
+                if self.transaction.is_some() {
+                    return Err("A transaction is already open".into());
+                }
+                self.transaction = Some(self.storage.begin_transaction()?);
+                self.begun = true;
+                Ok(())
+            }
+            Statement::Commit => match self.transaction.take() {
+                Some(transaction) => {
+                    let result = self.storage.commit_transaction(transaction);
+                    self.release_locks();
+                    result
+                }
+                None => Err("No transaction is open".into()),
+            },
+            Statement::Rollback => match self.transaction.take() {
+                Some(transaction) => {
+                    let result = self.storage.rollback_transaction(transaction);
+                    self.release_locks();
+                    result
+                }
+                None => Err("No transaction is open".into()),
+            },
+            _ => Err("Not a transaction control statement".into()),
+        }
+    }
+
+    // own the tables a statement writes for its unit of work, the work of the transaction of the handle
+    // when it has one. The transaction is moved to the calling thread first
+    pub fn lock_tables(&self, table_names: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+        match (self.transaction, self.storage.get_transaction()?) {
+            // the work of the thread is the transaction of another handle, it would wait for itself
+            (None, Some(_)) => return Err("A transaction is open on this database, its statements run through it".into()),
+            (Some(transaction), open) if open != Some(transaction) => self.storage.attach_transaction(transaction)?,
+            _ => {}
+        }
+        match self.storage.lock_tables(table_names) {
+            Err(error) if self.transaction.is_none() => self.storage.finish_work(Err(error)),
+            result => result,
+        }
+    }
+
+    // a table and the tables related to it by foreign keys, a change of the table may check or change them
+    pub fn related_tables(&self, table: &Table) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut table_names = vec![table.get_name().to_string()];
+        for column in table.get_columns() {
+            if let Some(foreign_key) = column.get_references() {
+                table_names.push(foreign_key.get_table().to_string());
+            }
+        }
+        for (referencing_table, _) in self.load_referencing_columns(table.get_name())? {
+            table_names.push(referencing_table.get_name().to_string());
+        }
+        Ok(table_names)
+    }
+
+    // own the tables of a statement on a table, the table alone when it has no descriptor
+    fn lock_statement(&self, table_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let table_names = match self.load_table_descriptor(table_name) {
+            Ok(table) => self.related_tables(&table)?,
+            Err(_) => vec![table_name.to_string()],
+        };
+        self.lock_tables(&table_names)
+    }
+
+    // own the tables of the statement of a query, a query without table fails once it runs
+    fn lock_query(&self, query: &Query) -> Result<(), Box<dyn std::error::Error>> {
+        match query.parse()?.get_table_name() {
+            Some(table_name) => self.lock_statement(table_name),
+            None => Ok(()),
+        }
+    }
+
+    // release the locks held by the transaction of the handle, once it is committed or rolled back
+    fn release_locks(&mut self) {
+        self.table_locks.get_mut().unwrap_or_else(|error| error.into_inner()).clear();
+        self.begun = false;
+    }
+
+    // true when the transaction of the handle holds the lock of a table
+    fn holds_table(&self, table_name: &str) -> bool {
+        self.table_locks.lock().unwrap_or_else(|error| error.into_inner()).contains_key(table_name)
+    }
+
+    // run an action while a table is locked for reading
+    fn read_table<T>(&self, table_name: &str, action: impl FnOnce() -> Result<T, Box<dyn std::error::Error>>) -> Result<T, Box<dyn std::error::Error>> {
+        if self.holds_table(table_name) {
+            return action();
+        }
+        let _guard = self.storage.get_table_lock(table_name).read()?;
+        action()
+    }
+
+    // run an action while a table is locked for writing
+    fn write_table<T>(&self, table_name: &str, action: impl FnOnce() -> Result<T, Box<dyn std::error::Error>>) -> Result<T, Box<dyn std::error::Error>> {
+        if self.holds_table(table_name) {
+            return action();
+        }
+        let _guard = self.storage.get_table_lock(table_name).write()?;
+        action()
+    }
+
+    // run an action changing the descriptor of a table while the table is locked for writing,
+    // within a transaction the lock is held until the transaction ends
+    fn change_table<T>(&self, table_name: &str, action: impl FnOnce() -> Result<T, Box<dyn std::error::Error>>) -> Result<T, Box<dyn std::error::Error>> {
+        if self.transaction.is_none() || self.holds_table(table_name) {
+            return self.write_table(table_name, action);
         }
+        let guard = self.storage.get_table_lock(table_name).write()?;
+        let result = action();
+        self.table_locks.lock().unwrap_or_else(|error| error.into_inner()).insert(table_name.to_string(), guard);
+        result
     }
 
     pub fn persist_table_descriptor(&self, table: &Table) -> Result<(), Box<dyn std::error::Error>>{
         // check if table has declared columns
-        if table.get_columns().len() == 0 {
+        if table.get_columns().is_empty() {
             return Err("Table has no columns".into());
         }
+        let auto_increment_count = table.get_columns().iter().filter(|column| column.is_auto_increment()).count();
+        if auto_increment_count > 0 && (auto_increment_count > 1 || table.get_primary_key().len() > 1) {
+            return Err("Auto increment column must be the only primary key column".into());
+        }
+        self.check_foreign_key_definitions(table)?;
         // persist table descriptor
         let file_name = table.get_name().to_string()+"."+TABLE_FILE_DESCRIPTOR_EXTENSION;
         let content = serde_json::to_string_pretty(&table)?;
@@ -36,40 +209,590 @@ impl DataHandler {
         Ok(())
     }
 
+    // descriptor of a table, parsed once and kept in the buffer pool until the descriptor file changes
     pub fn load_table_descriptor(&self, table_name: &str) -> Result<Table, Box<dyn std::error::Error>> {
         let file_name = table_name.to_string() + "." + TABLE_FILE_DESCRIPTOR_EXTENSION;
-        let content = self.storage.read_file(&file_name)?;
-        let table: Table = serde_json::from_str(&content)?;
-        Ok(table)
+        self.storage.read_parsed_file(&file_name, |content| Ok(serde_json::from_str::<Table>(content)?))
     }
 
     pub fn persist_new_table(&self, table: &Table) -> Result<(), Box<dyn std::error::Error>>{
         // check if table has declared columns
-        if table.get_columns().len() == 0 {
+        if table.get_columns().is_empty() {
             return Err("Table has no columns".into());
         }
         // persist empty table data
         let file_name = table.get_name().to_string()+"."+TABLE_FILE_DATA_EXTENSION;
-        let content = serde_json::to_string(&Vec::<Record>::new())?;
-        // let database_path = table.get_database().get_root_dir();
+        if self.storage.file_exists(&file_name) {
+            self.storage.delete_file(&file_name)?;
+        }
+        HeapFile::create(&self.storage, &file_name)?;
+        // indexes declared along with the table start empty
+        for index in table.get_indexes() {
+            self.rebuild_index(table, index, &[])?;
+        }
+        Ok(())
+    }
+
+    // last value generated for the auto increment column of a table, 0 before the first insert
+    pub fn load_table_sequence(&self, table_name: &str) -> Result<i64, Box<dyn std::error::Error>> {
+        let file_name = table_name.to_string() + "." + TABLE_FILE_SEQUENCE_EXTENSION;
+        if !self.storage.file_exists(&file_name) {
+            return Ok(0);
+        }
+        let content = self.storage.read_file(&file_name)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn persist_table_sequence(&self, table_name: &str, sequence: i64) -> Result<(), Box<dyn std::error::Error>> {
+        let file_name = table_name.to_string() + "." + TABLE_FILE_SEQUENCE_EXTENSION;
+        let content = serde_json::to_string(&sequence)?;
         self.storage.write_file(&file_name, &content)?;
         Ok(())
-    }    
-}
+    }
 
-impl DML for DataHandler {
-    fn insert(&mut self, record: Record) -> Result<u32, Box<dyn std::error::Error>> {
-        // check if record compability with table constraints
-        let table_description = self.load_table_descriptor(record.get_table().get_name())?;
-        if table_description.get_name() != record.get_table().get_name() {
-            return Err("Table name mismatch".into());
+    // names of the tables of the database, sorted
+    fn load_table_names(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let extension = ".".to_string() + TABLE_FILE_DESCRIPTOR_EXTENSION;
+        let mut table_names: Vec<String> = self.storage.list_files_with_extension(FileExtension::Descriptor)?
+            .iter()
+            .map(|file_name| file_name.trim_end_matches(extension.as_str()).to_string())
+            .collect();
+        table_names.sort();
+        Ok(table_names)
+    }
+
+    // constraint indexes of a table: <table>_pkey over the primary key and <table>_<column>_key over each
+    // unique column. A key too wide for an index page, or whose name is taken by another index, gets none
+    // and is checked against every row instead
+    fn constraint_indexes(&self, table: &Table) -> Result<Vec<Index>, Box<dyn std::error::Error>> {
+        let mut indexes = Vec::new();
+        for (constraint, key_columns) in DataHandler::unique_keys(table) {
+            let names: Vec<&str> = key_columns.iter().map(|i| table.get_columns()[*i].get_name()).collect();
+            let name = match constraint {
+                "Primary key" => format!("{}_pkey", table.get_name()),
+                _ => format!("{}_{}_key", table.get_name(), names.join("_")),
+            };
+            let mut index = Index::new(&name, &names);
+            index.set_constraint(true);
+            if DataHandler::index_order(table, &index).is_err() {
+                continue;
+            }
+            let is_taken = table.get_indexes().iter().any(|other| other.get_name() == name && !other.is_constraint())
+                || self.load_index_table(&name)?.is_some_and(|other| other.get_name() != table.get_name());
+            if !is_taken {
+                indexes.push(index);
+            }
         }
-        if table_description.get_columns().len() != record.get_table().get_columns().len() {
-            return Err("Column count mismatch".into());
+        Ok(indexes)
+    }
+
+    // replace the constraint indexes of a table with the ones its keys need now,
+    // returns the constraint indexes the table lost
+    pub fn set_constraint_indexes(&self, table: &mut Table) -> Result<Vec<Index>, Box<dyn std::error::Error>> {
+        let mut indexes = self.constraint_indexes(table)?;
+        let (old_indexes, other_indexes): (Vec<Index>, Vec<Index>) = table.get_indexes().iter()
+            .cloned()
+            .partition(|index| index.is_constraint());
+        let removed = old_indexes.into_iter()
+            .filter(|old_index| indexes.iter().all(|index| index.get_name() != old_index.get_name()))
+            .collect();
+        indexes.extend(other_indexes);
+        table.set_indexes(indexes);
+        Ok(removed)
+    }
+
+    // build an index over the rows of a table and record it in the table descriptor,
+    // index names are unique within the database
+    pub fn create_index(&self, table_name: &str, index: Index) -> Result<Table, Box<dyn std::error::Error>> {
+        self.lock_tables(&[table_name.to_string()])?;
+        let result = self.change_table(table_name, || self.add_index(table_name, index));
+        self.storage.finish_work(result)
+    }
+
+    fn add_index(&self, table_name: &str, index: Index) -> Result<Table, Box<dyn std::error::Error>> {
+        let mut table_description = self.load_table_descriptor(table_name)?;
+        if let Some(table) = self.load_index_table(index.get_name())? {
+            return Err(format!("Index {} already exists on table {}", index.get_name(), table.get_name()).into());
+        }
+        if index.get_columns().is_empty() {
+            return Err(format!("Index {} has no columns", index.get_name()).into());
         }
+        for (i, column) in index.get_columns().iter().enumerate() {
+            if table_description.get_column(column).is_none() {
+                return Err(format!("Unknown column {} in table {}", column, table_name).into());
+            }
+            if index.get_columns()[..i].contains(column) {
+                return Err(format!("Column {} appears twice in index {}", column, index.get_name()).into());
+            }
+        }
+
+        let records = self.load_table_data(&table_description)?;
+        let mut indexes = table_description.get_indexes().clone();
+        indexes.push(index.clone());
+        table_description.set_indexes(indexes);
+        self.rebuild_index(&table_description, &index, &records)?;
+        self.persist_table_descriptor(&table_description)?;
+        Ok(table_description)
+    }
+
+    // remove an index from its table descriptor and delete its file
+    pub fn drop_index(&self, index_name: &str) -> Result<Table, Box<dyn std::error::Error>> {
+        let result = match self.load_index_table(index_name) {
+            Ok(Some(table)) => {
+                self.lock_tables(&[table.get_name().to_string()])?;
+                self.change_table(table.get_name(), || self.remove_index(index_name))
+            }
+            Ok(None) => Err(format!("Unknown index {}", index_name).into()),
+            Err(error) => Err(error),
+        };
+        self.storage.finish_work(result)
+    }
+
+    fn remove_index(&self, index_name: &str) -> Result<Table, Box<dyn std::error::Error>> {
+        let mut table_description = match self.load_index_table(index_name)? {
+            Some(table) => table,
+            None => return Err(format!("Unknown index {}", index_name).into()),
+        };
+        if table_description.get_index(index_name).is_some_and(|index| index.is_constraint()) {
+            return Err(format!("Index {} enforces a key of table {}", index_name, table_description.get_name()).into());
+        }
+        let indexes = table_description.get_indexes().iter()
+            .filter(|index| index.get_name() != index_name)
+            .cloned()
+            .collect();
+        table_description.set_indexes(indexes);
+        self.persist_table_descriptor(&table_description)?;
+        self.storage.delete_file(&(index_name.to_string() + "." + TABLE_FILE_INDEX_EXTENSION))?;
+        Ok(table_description)
+    }
+
+    // descriptor of the table an index belongs to
+    pub fn load_index_table(&self, index_name: &str) -> Result<Option<Table>, Box<dyn std::error::Error>> {
+        for name in self.load_table_names()? {
+            let table = self.load_table_descriptor(&name)?;
+            if table.get_index(index_name).is_some() {
+                return Ok(Some(table));
+            }
+        }
+        Ok(None)
+    }
+
+    // open the B+ tree of an index
+    pub fn load_index(&self, index: &Index) -> Result<IndexTree, Box<dyn std::error::Error>> {
+        BPlusTree::open(&self.storage, &(index.get_name().to_string() + "." + TABLE_FILE_INDEX_EXTENSION))
+    }
+
+    // index keys of stored rows of a table
+    fn index_keys(table_description: &Table, index: &Index, records: &[Record]) -> Result<BTreeSet<Vec<Value>>, Box<dyn std::error::Error>> {
+        let mut positions = Vec::new();
+        for column in index.get_columns() {
+            match table_description.get_column_index(column) {
+                Some(position) => positions.push(position),
+                None => return Err(format!("Unknown column {} in index {}", column, index.get_name()).into()),
+            }
+        }
+        let mut keys = BTreeSet::new();
+        for record in records {
+            let row_id = match record.get_row_id() {
+                Some(row_id) => row_id,
+                None => return Err(format!("Record of table {} is not stored", table_description.get_name()).into()),
+            };
+            let mut key: Vec<Value> = positions.iter().map(|position| record.get_values()[*position].1.clone()).collect();
+            key.push(Value::Integer(row_id.to_u64() as i64));
+            keys.insert(key);
+        }
+        Ok(keys)
+    }
+
+    // the row id ending an index key
+    fn index_key_row(key: &[Value]) -> u64 {
+        match key.last() {
+            Some(Value::Integer(row)) => *row as u64,
+            _ => unreachable!("Index keys end with the row id"),
+        }
+    }
+
+    // order of the B+ tree of an index, as high as a node of keys of the widest values of its columns allows
+    fn index_order(table_description: &Table, index: &Index) -> Result<usize, Box<dyn std::error::Error>> {
+        // a key is a JSON array of the indexed values followed by the row id
+        let mut key_size = 2 + INDEX_INTEGER_SIZE;
+        for column_name in index.get_columns() {
+            let column = match table_description.get_column(column_name) {
+                Some(column) => column,
+                None => return Err(format!("Unknown column {} in table {}", column_name, table_description.get_name()).into()),
+            };
+            key_size += 1 + match column.get_data_type() {
+                DataType::Integer => INDEX_INTEGER_SIZE,
+                DataType::Real => 24,
+                // quotes around the characters, a character takes up to 6 bytes once escaped
+                DataType::Text(length) => 2 + 6 * *length as usize,
+                DataType::Blob => return Err(format!("Column {} of type BLOB cannot be indexed", column_name).into()),
+            };
+        }
+        match btree::page_order(key_size, INDEX_INTEGER_SIZE) {
+            Some(order) => Ok(order),
+            None => Err(format!("Keys of index {} do not fit in an index page, its columns are too wide", index.get_name()).into()),
+        }
+    }
+
+    // write a new index file holding every row of a table
+    fn rebuild_index(&self, table_description: &Table, index: &Index, records: &[Record]) -> Result<(), Box<dyn std::error::Error>> {
+        let file_name = index.get_name().to_string() + "." + TABLE_FILE_INDEX_EXTENSION;
+        if self.storage.file_exists(&file_name) {
+            self.storage.delete_file(&file_name)?;
+        }
+        let order = DataHandler::index_order(table_description, index)?;
+        let mut tree: IndexTree = BPlusTree::create(&self.storage, &file_name, order)?;
+        for key in DataHandler::index_keys(table_description, index, records)? {
+            let row = DataHandler::index_key_row(&key);
+            tree.insert(key, row)?;
+        }
+        tree.flush()
+    }
+
+    // remove the entries of the removed rows from each index of a table and add the entries of the added rows,
+    // a changed row is both removed and added
+    fn update_indexes(&self, table_description: &Table, old_records: &[Record], new_records: &[Record]) -> Result<(), Box<dyn std::error::Error>> {
+        let mut trees = Vec::new();
+        for index in table_description.get_indexes() {
+            let old_keys = DataHandler::index_keys(table_description, index, old_records)?;
+            let new_keys = DataHandler::index_keys(table_description, index, new_records)?;
+            let mut tree = self.load_index(index)?;
+            for key in old_keys.difference(&new_keys) {
+                tree.remove(key)?;
+            }
+            for key in new_keys.difference(&old_keys) {
+                tree.insert(key.clone(), DataHandler::index_key_row(key))?;
+            }
+            trees.push(tree);
+        }
+        // files are written once every tree took its changes
+        for tree in trees.iter_mut() {
+            tree.flush()?;
+        }
+        Ok(())
+    }
+
+    // bring the data file of a table in line with the new content of the table:
+    // records are matched to the latest row versions by row id, records without one are new rows
+    // and rows missing from the records are deleted. A changed or deleted row keeps its old version
+    // and its index entries for the readers that still see it
+    fn write_table_data(&self, table: &Table, records: &[Record]) -> Result<(), Box<dyn std::error::Error>> {
+        self.write_table(table.get_name(), || self.write_table_versions(table, records))
+    }
+
+    fn write_table_versions(&self, table: &Table, records: &[Record]) -> Result<(), Box<dyn std::error::Error>> {
+        let work_id = self.storage.start_work()?;
+        let mut heap = self.open_table_heap(table)?;
+        // dead versions are removed first so that the new versions can take their room
+        let (dead, versions) = self.remove_dead_versions(table, &mut heap)?;
+        let mut old_rows: BTreeMap<RowId, Record> = DataHandler::latest_records(table, versions)?
+            .into_iter()
+            .map(|record| (record.get_row_id().unwrap(), record))
+            .collect();
+        let kept: BTreeSet<RowId> = records.iter().filter_map(|record| record.get_row_id()).collect();
+        for row_id in old_rows.keys() {
+            if !kept.contains(row_id) {
+                heap.expire(*row_id, work_id)?;
+            }
+        }
+        let mut added = Vec::new();
+        for record in records {
+            let values: Vec<Value> = record.get_values().iter().map(|(_, value)| value.clone()).collect();
+            let mut stored = record.clone();
+            match record.get_row_id().and_then(|row_id| old_rows.remove(&row_id)) {
+                Some(old_record) if old_record == *record => continue,
+                Some(old_record) => {
+                    heap.expire(old_record.get_row_id().unwrap(), work_id)?;
+                    stored.set_row_id(Some(heap.insert_version(&values, work_id)?));
+                }
+                None => stored.set_row_id(Some(heap.insert_version(&values, work_id)?)),
+            }
+            added.push(stored);
+        }
+        self.update_indexes(table, &dead, &added)
+    }
+
+    // delete the row versions of a table that no reader needs anymore, with their index entries.
+    // Returns the deleted versions and the versions left
+    fn remove_dead_versions(&self, table: &Table, heap: &mut HeapFile) -> Result<(Vec<Record>, Vec<HeapVersion>), Box<dyn std::error::Error>> {
+        let horizon = self.storage.get_vacuum_horizon()?;
+        let (dead, versions): (Vec<HeapVersion>, Vec<HeapVersion>) = heap.scan_versions()?
+            .into_iter()
+            .partition(|(_, version, _)| horizon.is_dead(version));
+        for (row_id, _, _) in dead.iter() {
+            heap.delete(*row_id)?;
+        }
+        let dead = DataHandler::records_from_rows(table, dead.into_iter().map(|(row_id, _, values)| (row_id, values)).collect())?;
+        Ok((dead, versions))
+    }
+
+    // remove the row versions of a table that no reader needs anymore, returns how many were removed
+    pub fn vacuum(&self, table_name: &str) -> Result<usize, Box<dyn std::error::Error>> {
+        self.lock_tables(&[table_name.to_string()])?;
+        let result = self.write_table(table_name, || self.vacuum_table(table_name));
+        self.storage.finish_work(result)
+    }
+
+    fn vacuum_table(&self, table_name: &str) -> Result<usize, Box<dyn std::error::Error>> {
+        let table_description = self.load_table_descriptor(table_name)?;
+        let mut heap = self.open_table_heap(&table_description)?;
+        let (dead, _) = self.remove_dead_versions(&table_description, &mut heap)?;
+        self.update_indexes(&table_description, &dead, &[])?;
+        Ok(dead.len())
+    }
+
+    // load the latest version of every row of a table, records are bound to the given table descriptor
+    pub fn load_table_data(&self, table: &Table) -> Result<Vec<Record>, Box<dyn std::error::Error>> {
+        let heap = self.open_table_heap(table)?;
+        DataHandler::latest_records(table, heap.scan_versions()?)
+    }
+
+    // load the rows of a table as a snapshot sees them
+    pub fn load_visible_data(&self, table: &Table, snapshot: &Snapshot) -> Result<Vec<Record>, Box<dyn std::error::Error>> {
+        let heap = self.open_table_heap(table)?;
+        let rows = heap.scan_versions()?
+            .into_iter()
+            .filter(|(_, version, _)| snapshot.sees_version(version))
+            .map(|(row_id, _, values)| (row_id, values))
+            .collect();
+        DataHandler::records_from_rows(table, rows)
+    }
+
+    fn latest_records(table: &Table, versions: Vec<HeapVersion>) -> Result<Vec<Record>, Box<dyn std::error::Error>> {
+        let rows = versions.into_iter()
+            .filter(|(_, version, _)| !version.is_expired())
+            .map(|(row_id, _, values)| (row_id, values))
+            .collect();
+        DataHandler::records_from_rows(table, rows)
+    }
+
+    // records of the rows of a heap file, values are paired with the columns of the table descriptor
+    fn records_from_rows(table: &Table, rows: Vec<HeapRow>) -> Result<Vec<Record>, Box<dyn std::error::Error>> {
+        let mut records = Vec::new();
+        for (row_id, values) in rows {
+            if values.len() != table.get_columns().len() {
+                return Err(format!("Row {:?} of table {} has {} values for {} columns",
+                    row_id, table.get_name(), values.len(), table.get_columns().len()).into());
+            }
+            let values = table.get_columns().iter().cloned().zip(values).collect();
+            let mut record = Record::new(table.clone(), values);
+            record.set_row_id(Some(row_id));
+            records.push(record);
+        }
+        Ok(records)
+    }
+
+    // migrate the data file of a table before it is read, the migration is a unit of work of its own,
+    // or a statement of the transaction of the handle
+    fn migrate_for_reading(&self, table_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let file_name = table_name.to_string() + "." + TABLE_FILE_DATA_EXTENSION;
+        if !self.storage.file_exists(&file_name) || HeapFile::is_heap_file(&self.storage, &file_name) {
+            return Ok(());
+        }
+        self.lock_tables(&[table_name.to_string()])?;
+        let result = self.write_table(table_name, || {
+            let table_description = self.load_table_descriptor(table_name)?;
+            self.migrate_table_data(&table_description).map(|_| ())
+        });
+        self.storage.finish_work(result)
+    }
+
+    // open the data file of a table, a data file of an older version is migrated first
+    fn open_table_heap(&self, table: &Table) -> Result<HeapFile, Box<dyn std::error::Error>> {
+        self.migrate_table_data(table)?;
+        HeapFile::open(&self.storage, &(table.get_name().to_string() + "." + TABLE_FILE_DATA_EXTENSION))
+    }
+
+    // convert a data file holding the rows of a table as JSON records, or a heap file without row
+    // versions, into a heap file, returns false when the data file already is a heap file.
+    // The heap is built beside the old file and replaces it once complete, the indexes are rebuilt
+    // since they refer to rows by position in the old file. The migration belongs to the unit of work
+    // of the statement running it
+    pub fn migrate_table_data(&self, table: &Table) -> Result<bool, Box<dyn std::error::Error>> {
+        let file_name = table.get_name().to_string() + "." + TABLE_FILE_DATA_EXTENSION;
+        if HeapFile::is_heap_file(&self.storage, &file_name) {
+            return Ok(false);
+        }
+        let mut rows = Vec::new();
+        if HeapFile::is_unversioned_heap_file(&self.storage, &file_name) {
+            rows.extend(HeapFile::open_unversioned(&self.storage, &file_name)?.scan()?.into_iter().map(|(_, values)| values));
+        } else {
+            let json_records: Vec<Record> = serde_json::from_str(&self.storage.read_file(&file_name)?)?;
+            for record in json_records {
+                // values are cast back to their column type, rows written before typed values hold text only
+                let mut values = Vec::new();
+                for (column, value) in record.get_values() {
+                    values.push(value.cast_to(column.get_data_type())?);
+                }
+                rows.push(values);
+            }
+        }
+        let migration_file_name = file_name.clone() + ".migration";
+        if self.storage.file_exists(&migration_file_name) {
+            self.storage.delete_file(&migration_file_name)?;
+        }
+        let mut heap = HeapFile::create(&self.storage, &migration_file_name)?;
+        let mut records = Vec::new();
+        for row in rows {
+            let mut record = Record::new(table.clone(), table.get_columns().iter().cloned().zip(row.iter().cloned()).collect());
+            record.set_row_id(Some(heap.insert(&row)?));
+            records.push(record);
+        }
+        self.storage.rename_file(&migration_file_name, &file_name)?;
+        for index in table.get_indexes() {
+            self.rebuild_index(table, index, &records)?;
+        }
+        Ok(true)
+    }
+
+    // apply schema changes to a table, the descriptor and every row are rewritten once all changes succeeded
+    pub fn alter_table(&self, table_name: &str, operations: &[AlterTableOperation]) -> Result<Table, Box<dyn std::error::Error>> {
+        self.lock_tables(&[table_name.to_string()])?;
+        let result = self.change_table(table_name, || self.alter_table_schema(table_name, operations));
+        self.storage.finish_work(result)
+    }
+
+    fn alter_table_schema(&self, table_name: &str, operations: &[AlterTableOperation]) -> Result<Table, Box<dyn std::error::Error>> {
+        let mut table_description = self.load_table_descriptor(table_name)?;
+        let mut rows: Vec<Vec<(Column, Value)>> = self.load_table_data(&table_description)?
+            .iter()
+            .map(|record| record.get_values().clone())
+            .collect();
+
+        for operation in operations {
+            let mut columns = table_description.get_columns().clone();
+            match operation {
+                AlterTableOperation::AddColumn { column, default } => {
+                    if table_description.get_column(column.get_name()).is_some() {
+                        return Err(format!("Column {} already exists in table {}", column.get_name(), table_name).into());
+                    }
+                    let default = match default {
+                        Value::Null => DataHandler::default_value(&table_description, column)?,
+                        _ => default.clone(),
+                    };
+                    let default = default.cast_to(column.get_data_type())?;
+                    if !column.is_nullable() && default.is_null() && !rows.is_empty() {
+                        return Err(format!("Column {} is not nullable and has no default value", column.get_name()).into());
+                    }
+                    columns.push(column.clone());
+                    for row in rows.iter_mut() {
+                        row.push((column.clone(), default.clone()));
+                    }
+                }
+                AlterTableOperation::DropColumn(name) => {
+                    let index = match table_description.get_column_index(name) {
+                        Some(index) => index,
+                        None => return Err(format!("Unknown column {} in table {}", name, table_name).into()),
+                    };
+                    self.check_column_not_referenced(&table_description, name)?;
+                    if let Some(index) = table_description.get_indexes().iter().find(|index| !index.is_constraint() && index.get_columns().contains(name)) {
+                        return Err(format!("Column {} is used by index {}", name, index.get_name()).into());
+                    }
+                    if columns.len() == 1 {
+                        return Err("Table has no columns".into());
+                    }
+                    columns.remove(index);
+                    for row in rows.iter_mut() {
+                        row.remove(index);
+                    }
+                }
+                AlterTableOperation::RenameColumn { old_name, new_name } => {
+                    let index = match table_description.get_column_index(old_name) {
+                        Some(index) => index,
+                        None => return Err(format!("Unknown column {} in table {}", old_name, table_name).into()),
+                    };
+                    self.check_column_not_referenced(&table_description, old_name)?;
+                    if table_description.get_column(new_name).is_some() {
+                        return Err(format!("Column {} already exists in table {}", new_name, table_name).into());
+                    }
+                    columns[index].set_name(new_name);
+                    for row in rows.iter_mut() {
+                        row[index].0 = columns[index].clone();
+                    }
+                    // index entries hold values only, the index files are rebuilt with the data file
+                    let indexes = table_description.get_indexes().iter()
+                        .map(|index| {
+                            let mut index = index.clone();
+                            let index_columns = index.get_columns().iter()
+                                .map(|column| if column == old_name { new_name.clone() } else { column.clone() })
+                                .collect();
+                            index.set_columns(index_columns);
+                            index
+                        })
+                        .collect();
+                    table_description.set_indexes(indexes);
+                }
+                AlterTableOperation::SetNullable { name, is_nullable } => {
+                    let index = match table_description.get_column_index(name) {
+                        Some(index) => index,
+                        None => return Err(format!("Unknown column {} in table {}", name, table_name).into()),
+                    };
+                    if *is_nullable && columns[index].is_primary_key() {
+                        return Err("Primary key column cannot be nullable".into());
+                    }
+                    if !*is_nullable && rows.iter().any(|row| row[index].1.is_null()) {
+                        return Err(format!("Column {} contains null values", name).into());
+                    }
+                    columns[index].set_nullable(*is_nullable);
+                    for row in rows.iter_mut() {
+                        row[index].0 = columns[index].clone();
+                    }
+                }
+            }
+            table_description.set_columns(columns);
+        }
+
+        // the keys follow the columns they are made of
+        let removed_indexes = self.set_constraint_indexes(&mut table_description)?;
+        let mut records = Vec::new();
+        for values in rows {
+            records.push(self.validate_record(&table_description, &Record::new(table_description.clone(), values))?);
+        }
+        DataHandler::check_unique_constraints(&table_description, &records)?;
+        self.check_foreign_key_definitions(&table_description)?;
+        self.check_foreign_keys(&table_description, &records, &table_description, &records)?;
+        self.persist_table_data(&table_description, &records)?;
+        self.persist_table_descriptor(&table_description)?;
+        for index in removed_indexes {
+            self.storage.delete_file(&(index.get_name().to_string() + "." + TABLE_FILE_INDEX_EXTENSION))?;
+        }
+        Ok(table_description)
+    }
+
+    // rewrite every record of a table in a new data file, the indexes of the table are rebuilt
+    // since every row gets a new row id.
+    // The new data file is built beside the old one and replaces it once complete, its rows are
+    // versions created by the unit of work in progress
+    pub fn persist_table_data(&self, table: &Table, records: &[Record]) -> Result<(), Box<dyn std::error::Error>> {
+        let work_id = self.storage.start_work()?;
+        let file_name = table.get_name().to_string() + "." + TABLE_FILE_DATA_EXTENSION;
+        let temp_file_name = file_name.clone() + "." + TEMP_FILE_EXTENSION;
+        if self.storage.file_exists(&temp_file_name) {
+            self.storage.delete_file(&temp_file_name)?;
+        }
+        let mut heap = HeapFile::create(&self.storage, &temp_file_name)?;
+        let mut stored = Vec::new();
+        for record in records {
+            let values: Vec<Value> = record.get_values().iter().map(|(_, value)| value.clone()).collect();
+            let mut record = record.clone();
+            record.set_row_id(Some(heap.insert_version(&values, work_id)?));
+            stored.push(record);
+        }
+        self.storage.rename_file(&temp_file_name, &file_name)?;
+        for index in table.get_indexes() {
+            self.rebuild_index(table, index, &stored)?;
+        }
+        Ok(())
+    }
+
+    // check a full record against the column definitions of the table descriptor,
+    // returns the record with every value cast to the type of its column
+    fn validate_record(&self, table_description: &Table, record: &Record) -> Result<Record, Box<dyn std::error::Error>> {
         if table_description.get_columns().len() != record.get_values().len() {
             return Err("Column count mismatch".into());
         }
+        let mut values = Vec::new();
         for (i, record_column) in record.get_values().iter().enumerate() {
             let table_column = &table_description.get_columns()[i];
             if record_column.0.get_name() != table_column.get_name() {
@@ -78,68 +801,766 @@ impl DML for DataHandler {
             if record_column.0.get_data_type() != table_column.get_data_type() {
                 return Err(format!("Column type mismatch for column {} {}", i, table_column.get_name()).into());
             }
-            if !table_column.is_nullable() && record_column.1.is_none() {
+            let value = match record_column.1.cast_to(table_column.get_data_type()) {
+                Ok(value) => value,
+                Err(e) => return Err(format!("{} in column {} {}", e, i, table_column.get_name()).into()),
+            };
+            // text length is counted in characters, not bytes
+            let value = match (table_column.get_data_type(), value) {
+                (DataType::Text(length), Value::Text(text)) if text.chars().count() > *length as usize => {
+                    if !self.truncate_text {
+                        return Err(format!("Value too long for column {}: maximum length is {} characters", table_column.get_name(), length).into());
+                    }
+                    Value::Text(text.chars().take(*length as usize).collect())
+                }
+                (_, value) => value,
+            };
+            if !table_column.is_nullable() && value.is_null() {
                 return Err(format!("Column value is null for not null column {} {}", i, table_column.get_name()).into());
             }
-        }   
+            values.push((table_column.clone(), value));
+        }
+        // the record keeps the row it is stored in
+        let row_id = record.get_row_id();
+        let mut record = Record::new(table_description.clone(), values);
+        record.set_row_id(row_id);
+        // a check constraint fails when its expression is false, not when it is unknown
+        for column in table_description.get_columns() {
+            if let Some(check) = column.get_check() {
+                let expression = parser::parse_expression(check)?;
+                if !evaluator::satisfies(&expression, &record)? {
+                    return Err(format!("Check constraint violation on column {}: {}", column.get_name(), check).into());
+                }
+            }
+        }
+        Ok(record)
+    }
 
-        // check if record column count matches table column count
-        let record_columns = record.get_values();
-        let table_columns = record.get_table().get_columns();
-        if record_columns.len() != table_columns.len() {
-            return Err("Column count mismatch".into());
+    // list the columns of every table of the database with a foreign key to the given table
+    pub fn load_referencing_columns(&self, table_name: &str) -> Result<Vec<(Table, usize)>, Box<dyn std::error::Error>> {
+        let mut referencing_columns = Vec::new();
+        for name in self.load_table_names()? {
+            let table = self.load_table_descriptor(&name)?;
+            for (i, column) in table.get_columns().iter().enumerate() {
+                if column.get_references().is_some_and(|foreign_key| foreign_key.get_table() == table_name) {
+                    referencing_columns.push((table.clone(), i));
+                }
+            }
         }
-        // check if record column types matches table column types
-        for (i, record_column) in record_columns.iter().enumerate() {
-            let table_column = &table_columns[i];
-            if record_column.0.get_data_type() != table_column.get_data_type() {
-                return Err(format!("Column type mismatch for column {} {}", i, table_column.get_name()).into());
+        Ok(referencing_columns)
+    }
+
+    // the referenced column of a foreign key, the single column primary key when none is named
+    fn referenced_column_index(referenced_table: &Table, foreign_key: &ForeignKey) -> Result<usize, Box<dyn std::error::Error>> {
+        match foreign_key.get_column() {
+            Some(name) => referenced_table.get_column_index(name)
+                .ok_or_else(|| format!("Unknown column {} in table {}", name, referenced_table.get_name()).into()),
+            None => {
+                let primary_key = referenced_table.get_primary_key();
+                if primary_key.len() != 1 {
+                    return Err(format!("Table {} has no single column primary key to reference", referenced_table.get_name()).into());
+                }
+                Ok(referenced_table.get_column_index(primary_key[0].get_name()).unwrap())
             }
         }
-        // check if record column values are not null for not null columns
-        for (i, record_column) in record_columns.iter().enumerate() {
-            let table_column = &table_columns[i];
-            if !table_column.is_nullable() && record_column.1.is_none() {
-                return Err(format!("Column value is null for not null column {} {}", i, table_column.get_name()).into());
+    }
+
+    // check that the foreign keys of a table reference a unique column of a table of the database
+    fn check_foreign_key_definitions(&self, table: &Table) -> Result<(), Box<dyn std::error::Error>> {
+        for column in table.get_columns() {
+            let foreign_key = match column.get_references() {
+                Some(foreign_key) => foreign_key,
+                None => continue,
+            };
+            let referenced_table = if foreign_key.get_table() == table.get_name() {
+                table.clone()
+            } else {
+                self.load_table_descriptor(foreign_key.get_table())
+                    .map_err(|_| format!("Unknown table {} referenced by column {}", foreign_key.get_table(), column.get_name()))?
+            };
+            let index = DataHandler::referenced_column_index(&referenced_table, foreign_key)?;
+            let referenced_column = &referenced_table.get_columns()[index];
+            let is_key = referenced_column.is_unique() || referenced_table.get_primary_key() == vec![referenced_column];
+            if !is_key {
+                return Err(format!("Column {}.{} referenced by column {} is not a primary key or unique",
+                    referenced_table.get_name(), referenced_column.get_name(), column.get_name()).into());
+            }
+            if foreign_key.get_on_delete() == ReferentialAction::SetNull && !column.is_nullable() {
+                return Err(format!("Column {} cannot be set to null on delete, it is not nullable", column.get_name()).into());
+            }
+        }
+        Ok(())
+    }
+
+    fn check_column_not_referenced(&self, table_description: &Table, column_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        for (referencing_table, i) in self.load_referencing_columns(table_description.get_name())? {
+            let foreign_key = referencing_table.get_columns()[i].get_references().unwrap();
+            let index = DataHandler::referenced_column_index(table_description, foreign_key)?;
+            if table_description.get_columns()[index].get_name() == column_name {
+                return Err(format!("Column {} is referenced by {}.{}",
+                    column_name, referencing_table.get_name(), referencing_table.get_columns()[i].get_name()).into());
+            }
+        }
+        Ok(())
+    }
+
+    // the distinct non NULL values of a column
+    fn referenced_values(records: &[Record], index: usize) -> BTreeSet<Value> {
+        records.iter()
+            .map(|record| record.get_values()[index].1.clone())
+            .filter(|value| !value.is_null())
+            .collect()
+    }
+
+    // check that every foreign key value of the checked records exists in the referenced table,
+    // changed_data holds every record of the changed table once the change is applied
+    fn check_foreign_keys(&self, table_description: &Table, checked: &[Record], changed_table: &Table, changed_data: &[Record]) -> Result<(), Box<dyn std::error::Error>> {
+        for (i, column) in table_description.get_columns().iter().enumerate() {
+            let foreign_key = match column.get_references() {
+                Some(foreign_key) => foreign_key,
+                None => continue,
+            };
+            let (referenced_table, referenced_data) = if foreign_key.get_table() == changed_table.get_name() {
+                (changed_table.clone(), changed_data.to_vec())
+            } else {
+                let referenced_table = self.load_table_descriptor(foreign_key.get_table())?;
+                let referenced_data = self.load_table_data(&referenced_table)?;
+                (referenced_table, referenced_data)
+            };
+            let index = DataHandler::referenced_column_index(&referenced_table, foreign_key)?;
+            let referenced_column = &referenced_table.get_columns()[index];
+            let referenced_values = DataHandler::referenced_values(&referenced_data, index);
+            for record in checked {
+                let value = &record.get_values()[i].1;
+                if value.is_null() {
+                    continue;
+                }
+                let found = match value.cast_to(referenced_column.get_data_type()) {
+                    Ok(value) => referenced_values.contains(&value),
+                    Err(_) => false,
+                };
+                if !found {
+                    return Err(format!("Foreign key constraint violation on column {}: {} not found in {}.{}",
+                        column.get_name(), value, referenced_table.get_name(), referenced_column.get_name()).into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // check that rows of other tables still find the values they reference after a change of the table
+    fn check_referencing_rows(&self, table_description: &Table, table_data: &[Record]) -> Result<(), Box<dyn std::error::Error>> {
+        for (referencing_table, _) in self.load_referencing_columns(table_description.get_name())? {
+            if referencing_table.get_name() == table_description.get_name() {
+                self.check_foreign_keys(table_description, table_data, table_description, table_data)?;
+            } else {
+                let referencing_data = self.load_table_data(&referencing_table)?;
+                self.check_foreign_keys(&referencing_table, &referencing_data, table_description, table_data)?;
+            }
+        }
+        Ok(())
+    }
+
+    // apply the ON DELETE action of every foreign key referencing deleted records,
+    // changes holds the new content of every table touched by the delete
+    fn apply_delete_actions(&self, table_description: &Table, deleted: &[Record], changes: &mut BTreeMap<String, (Table, Vec<Record>)>) -> Result<(), Box<dyn std::error::Error>> {
+        for (referencing_table, i) in self.load_referencing_columns(table_description.get_name())? {
+            let referencing_column = referencing_table.get_columns()[i].clone();
+            let foreign_key = referencing_column.get_references().unwrap();
+            let index = DataHandler::referenced_column_index(table_description, foreign_key)?;
+            let referenced_column = &table_description.get_columns()[index];
+
+            // a value is gone once no remaining record of the table holds it
+            let remaining_values = DataHandler::referenced_values(&changes[table_description.get_name()].1, index);
+            let deleted_values: BTreeSet<Value> = DataHandler::referenced_values(deleted, index)
+                .into_iter()
+                .filter(|value| !remaining_values.contains(value))
+                .collect();
+            if deleted_values.is_empty() {
+                continue;
+            }
+
+            if !changes.contains_key(referencing_table.get_name()) {
+                let referencing_data = self.load_table_data(&referencing_table)?;
+                changes.insert(referencing_table.get_name().to_string(), (referencing_table.clone(), referencing_data));
+            }
+            let (referencing_table, referencing_data) = changes.remove(referencing_table.get_name()).unwrap();
+            let (matching, mut kept): (Vec<Record>, Vec<Record>) = referencing_data.into_iter().partition(|record| {
+                match record.get_values()[i].1.cast_to(referenced_column.get_data_type()) {
+                    Ok(value) => deleted_values.contains(&value),
+                    Err(_) => false,
+                }
+            });
+            if matching.is_empty() {
+                changes.insert(referencing_table.get_name().to_string(), (referencing_table, kept));
+                continue;
+            }
+
+            match foreign_key.get_on_delete() {
+                ReferentialAction::Restrict => {
+                    return Err(format!("Foreign key constraint violation: {}.{} references {} rows being deleted",
+                        referencing_table.get_name(), referencing_column.get_name(), table_description.get_name()).into());
+                }
+                ReferentialAction::SetNull => {
+                    for mut record in matching {
+                        let mut values = record.get_values().clone();
+                        values[i].1 = Value::Null;
+                        record.set_values(values);
+                        kept.push(self.validate_record(&referencing_table, &record)?);
+                    }
+                    changes.insert(referencing_table.get_name().to_string(), (referencing_table, kept));
+                }
+                ReferentialAction::Cascade => {
+                    changes.insert(referencing_table.get_name().to_string(), (referencing_table.clone(), kept));
+                    self.apply_delete_actions(&referencing_table, &matching, changes)?;
+                }
             }
         }
-        // check if record column values are unique for unique columns
+        Ok(())
+    }
+
+    // the primary key and the unique columns of a table, as positions of their columns
+    fn unique_keys(table_description: &Table) -> Vec<(&'static str, Vec<usize>)> {
+        let mut keys = Vec::new();
+        let primary_key: Vec<usize> = table_description.get_primary_key()
+            .iter()
+            .map(|column| table_description.get_column_index(column.get_name()).unwrap())
+            .collect();
+        for (i, column) in table_description.get_columns().iter().enumerate() {
+            if column.is_unique() && primary_key != [i] {
+                keys.push(("Unique", vec![i]));
+            }
+        }
+        if !primary_key.is_empty() {
+            keys.insert(0, ("Primary key", primary_key));
+        }
+        keys
+    }
+
+    fn duplicate_key_error(table_description: &Table, constraint: &str, key_columns: &[usize], key: &[Value]) -> Box<dyn std::error::Error> {
+        let names: Vec<&str> = key_columns.iter().map(|i| table_description.get_columns()[*i].get_name()).collect();
+        let values: Vec<String> = key.iter().map(|value| value.to_string()).collect();
+        format!(
+            "{} constraint violation on table {}: duplicate key ({}) = ({})",
+            constraint, table_description.get_name(), names.join(", "), values.join(", ")
+        ).into()
+    }
+
+    // check that no two records share the same primary key or the same value in a unique column
+    fn check_unique_constraints(table_description: &Table, records: &[Record]) -> Result<(), Box<dyn std::error::Error>> {
+        for (constraint, key_columns) in DataHandler::unique_keys(table_description) {
+            let mut key_values = BTreeSet::new();
+            for record in records {
+                let key: Vec<Value> = key_columns.iter().map(|i| record.get_values()[*i].1.clone()).collect();
+                // NULL values are never equal to each other
+                if key.iter().any(|value| value.is_null()) {
+                    continue;
+                }
+                if key_values.contains(&key) {
+                    return Err(DataHandler::duplicate_key_error(table_description, constraint, &key_columns, &key));
+                }
+                key_values.insert(key);
+            }
+        }
+        Ok(())
+    }
+
+    // check that the primary key and unique values of a new record are not used by a row of its table yet
+    fn check_new_keys(&self, table_description: &Table, record: &Record) -> Result<(), Box<dyn std::error::Error>> {
+        for (constraint, key_columns) in DataHandler::unique_keys(table_description) {
+            let key: Vec<Value> = key_columns.iter().map(|i| record.get_values()[*i].1.clone()).collect();
+            if key.iter().any(|value| value.is_null()) {
+                continue;
+            }
+            if self.key_exists(table_description, &key_columns, &key)? {
+                return Err(DataHandler::duplicate_key_error(table_description, constraint, &key_columns, &key));
+            }
+        }
+        Ok(())
+    }
+
+    // check that the foreign key values of a new record exist in the referenced tables, a record may
+    // reference itself
+    fn check_new_references(&self, table_description: &Table, record: &Record) -> Result<(), Box<dyn std::error::Error>> {
+        for (i, column) in table_description.get_columns().iter().enumerate() {
+            let foreign_key = match column.get_references() {
+                Some(foreign_key) => foreign_key,
+                None => continue,
+            };
+            let value = &record.get_values()[i].1;
+            if value.is_null() {
+                continue;
+            }
+            let is_self_reference = foreign_key.get_table() == table_description.get_name();
+            let referenced_table = if is_self_reference {
+                table_description.clone()
+            } else {
+                self.load_table_descriptor(foreign_key.get_table())?
+            };
+            let index = DataHandler::referenced_column_index(&referenced_table, foreign_key)?;
+            let referenced_column = &referenced_table.get_columns()[index];
+            let found = match value.cast_to(referenced_column.get_data_type()) {
+                Ok(value) => (is_self_reference && record.get_values()[index].1 == value)
+                    || self.key_exists(&referenced_table, &[index], &[value])?,
+                Err(_) => false,
+            };
+            if !found {
+                return Err(format!("Foreign key constraint violation on column {}: {} not found in {}.{}",
+                    column.get_name(), value, referenced_table.get_name(), referenced_column.get_name()).into());
+            }
+        }
+        Ok(())
+    }
+
+    // whether the latest version of a row of a table holds the key in the given columns. The key is
+    // looked up in an index starting with the columns, the rows are scanned when the table has none
+    fn key_exists(&self, table_description: &Table, key_columns: &[usize], key: &[Value]) -> Result<bool, Box<dyn std::error::Error>> {
+        let names: Vec<&str> = key_columns.iter().map(|i| table_description.get_columns()[*i].get_name()).collect();
+        let index = table_description.get_indexes().iter()
+            .find(|index| index.get_columns().len() >= names.len() && index.get_columns().iter().zip(names.iter()).all(|(column, name)| column == name));
+        let index = match index {
+            Some(index) => index,
+            None => {
+                let records = self.load_table_data(table_description)?;
+                return Ok(records.iter().any(|record| key_columns.iter().zip(key).all(|(i, value)| record.get_values()[*i].1 == *value)));
+            }
+        };
+        let heap = self.open_table_heap(table_description)?;
+        for row_id in self.scan_index(index, key, None, None)? {
+            match heap.get_version(row_id)? {
+                Some((version, _)) if !version.is_expired() => return Ok(true),
+                Some(_) => {}
+                None => return Err(format!("Index {} refers to a missing row {:?}", index.get_name(), row_id).into()),
+            }
+        }
+        Ok(false)
+    }
+
+    // build a full record from the given values, missing columns take their default value or NULL
+    fn complete_record(table_description: &Table, record: &Record) -> Result<Record, Box<dyn std::error::Error>> {
+        for (i, (column, _)) in record.get_values().iter().enumerate() {
+            if table_description.get_column(column.get_name()).is_none() {
+                return Err(format!("Unknown column {} in table {}", column.get_name(), table_description.get_name()).into());
+            }
+            if record.get_values()[..i].iter().any(|(other, _)| other.get_name() == column.get_name()) {
+                return Err(format!("Column {} is given more than once", column.get_name()).into());
+            }
+        }
+        let mut values = Vec::new();
+        for column in table_description.get_columns() {
+            match record.get_value(column.get_name()) {
+                Some(column_value) => values.push(column_value.clone()),
+                None => values.push((column.clone(), DataHandler::default_value(table_description, column)?)),
+            }
+        }
+        Ok(Record::new(table_description.clone(), values))
+    }
+
+    fn default_value(table_description: &Table, column: &Column) -> Result<Value, Box<dyn std::error::Error>> {
+        match column.get_default() {
+            None => Ok(Value::Null),
+            Some(ColumnDefault::Literal(value)) => Ok(value.clone()),
+            Some(ColumnDefault::Expression(expression)) => {
+                let expression = parser::parse_expression(expression)?;
+                evaluator::evaluate(&expression, &Record::new(table_description.clone(), Vec::new()))
+            }
+        }
+    }
+
+    // how a SELECT statement reads the rows of its table
+    pub fn plan(&self, statement: &Statement) -> Result<PlanNode, Box<dyn std::error::Error>> {
+        let table_name = match statement {
+            Statement::Select { table, .. } => table,
+            _ => return Err("Only SELECT statements can be explained".into()),
+        };
+        self.migrate_for_reading(table_name)?;
+        // the descriptor is read under the lock, a transaction changing it holds the lock until its end
+        self.read_table(table_name, || {
+            let table_description = self.load_table_descriptor(table_name)?;
+            let row_count = self.open_table_heap(&table_description)?.get_tuple_count();
+            planner::plan(statement, &table_description, row_count)
+        })
+    }
+
+    // run a plan over the rows of its table as a snapshot sees them
+    fn execute_plan(&self, plan: &PlanNode, table_description: &Table, snapshot: &Snapshot) -> Result<Vec<Record>, Box<dyn std::error::Error>> {
+        match plan {
+            PlanNode::SeqScan { filter, .. } => {
+                let records = self.load_visible_data(table_description, snapshot)?;
+                DataHandler::filter_records(records, filter.as_ref())
+            }
+            PlanNode::IndexScan { index, prefix, lower, upper, condition, filter, .. } => {
+                // only the rows found in the index are read from the data file, the index also
+                // refers to the versions the snapshot does not see
+                let heap = self.open_table_heap(table_description)?;
+                let mut rows = Vec::new();
+                for row_id in self.scan_index(index, prefix, lower.as_ref(), upper.as_ref())? {
+                    match heap.get_version(row_id)? {
+                        Some((version, values)) if snapshot.sees_version(&version) => rows.push((row_id, values)),
+                        Some(_) => {}
+                        None => return Err(format!("Index {} refers to a missing row {:?}", index.get_name(), row_id).into()),
+                    }
+                }
+                let mut selected = DataHandler::records_from_rows(table_description, rows)?;
+                // the index narrows the rows, every condition is still checked
+                for condition in condition.iter() {
+                    selected = DataHandler::filter_records(selected, Some(condition))?;
+                }
+                DataHandler::filter_records(selected, filter.as_ref())
+            }
+            PlanNode::Sort { order_by, input, .. } => {
+                let mut records = self.execute_plan(input, table_description, snapshot)?;
+                DataHandler::sort_records(&mut records, order_by)?;
+                Ok(records)
+            }
+            PlanNode::Limit { limit, offset, input, .. } => {
+                let records = self.execute_plan(input, table_description, snapshot)?;
+                let offset = offset.unwrap_or(0) as usize;
+                let limit = limit.map(|limit| limit as usize).unwrap_or(usize::MAX);
+                Ok(records.into_iter().skip(offset).take(limit).collect())
+            }
+            PlanNode::Project { projection, input, .. } => {
+                let mut projected = Vec::new();
+                for record in self.execute_plan(input, table_description, snapshot)? {
+                    projected.push(DataHandler::project_record(table_description, &record, projection)?);
+                }
+                Ok(projected)
+            }
+        }
+    }
+
+    // ids of the rows whose index key starts with the prefix, followed by a value up to upper,
+    // in data file order
+    fn scan_index(&self, index: &Index, prefix: &[Value], lower: Option<&Value>, upper: Option<&Value>) -> Result<Vec<RowId>, Box<dyn std::error::Error>> {
+        let mut tree = self.load_index(index)?;
+        let mut start = prefix.to_vec();
+        if let Some(lower) = lower {
+            start.push(lower.clone());
+        }
+        let mut rows = Vec::new();
+        for entry in tree.range(start..) {
+            let (key, row) = entry?;
+            if key[..prefix.len()] != *prefix || upper.is_some_and(|upper| key[prefix.len()] > *upper) {
+                break;
+            }
+            rows.push(RowId::from_u64(row));
+        }
+        rows.sort();
+        Ok(rows)
+    }
+
+    // keep the records matching an optional WHERE predicate
+    fn filter_records(records: Vec<Record>, selection: Option<&Expression>) -> Result<Vec<Record>, Box<dyn std::error::Error>> {
+        let predicate = match selection {
+            None => return Ok(records),
+            Some(predicate) => predicate,
+        };
+        let mut filtered = Vec::new();
+        for record in records {
+            if evaluator::matches(predicate, &record)? {
+                filtered.push(record);
+            }
+        }
+        Ok(filtered)
+    }
+
+    fn sort_records(records: &mut Vec<Record>, order_by: &[OrderByItem]) -> Result<(), Box<dyn std::error::Error>> {
+        if order_by.is_empty() {
+            return Ok(());
+        }
+        // compute sort keys first so that evaluation errors are reported instead of panicking in the comparator
+        let mut keyed_records = Vec::new();
+        for record in records.drain(..) {
+            let mut keys = Vec::new();
+            for item in order_by {
+                keys.push(evaluator::evaluate(&item.expression, &record)?);
+            }
+            keyed_records.push((keys, record));
+        }
+        // NULL values sort first, then numbers, text and blobs
+        keyed_records.sort_by(|(left_keys, _), (right_keys, _)| {
+            for (i, item) in order_by.iter().enumerate() {
+                let ordering = left_keys[i].cmp(&right_keys[i]);
+                let ordering = if item.ascending { ordering } else { ordering.reverse() };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            Ordering::Equal
+        });
+        records.extend(keyed_records.into_iter().map(|(_, record)| record));
+        Ok(())
+    }
+
+    // build the projected record for a SELECT list
+    fn project_record(table: &Table, record: &Record, projection: &[SelectItem]) -> Result<Record, Box<dyn std::error::Error>> {
+        let mut values = Vec::new();
+        for item in projection {
+            match item {
+                SelectItem::Wildcard => values.extend(record.get_values().iter().cloned()),
+                SelectItem::Expression { expression: Expression::Column(name), alias } => {
+                    let (column, value) = match record.get_value(name) {
+                        Some(column_value) => column_value.clone(),
+                        None => return Err(format!("Unknown column {} in table {}", name, table.get_name()).into()),
+                    };
+                    let mut column = column;
+                    if let Some(alias) = alias {
+                        column.set_name(alias);
+                    }
+                    values.push((column, value));
+                }
+                SelectItem::Expression { expression, alias } => {
+                    let value = evaluator::evaluate(expression, record)?;
+                    let data_type = match &value {
+                        Value::Integer(_) => DataType::Integer,
+                        Value::Real(_) => DataType::Real,
+                        Value::Blob(_) => DataType::Blob,
+                        _ => DataType::Text(u16::MAX),
+                    };
+                    let name = alias.clone().unwrap_or_else(|| "?column?".to_string());
+                    let column = Column::new(&name, data_type, false, true)?;
+                    values.push((column, value));
+                }
+            }
+        }
+        Ok(Record::new(table.clone(), values))
+    }
+}
+
+// statements are committed once they succeeded and rolled back otherwise, one writer of a table at a time
+impl DML for DataHandler {
+    fn insert(&mut self, record: Record) -> Result<InsertResult, Box<dyn std::error::Error>> {
+        self.lock_statement(record.get_table().get_name())?;
+        let result = self.insert_record(record);
+        self.storage.finish_work(result)
+    }
+
+    fn select(&self, query: Query) -> Result<ResultSet, Box<dyn std::error::Error>> {
+        self.select_records(query)
+    }
+
+    fn update(&mut self, record: Record, query: Query) -> Result<u32, Box<dyn std::error::Error>> {
+        self.lock_query(&query)?;
+        let result = self.update_records(record, query);
+        self.storage.finish_work(result)
+    }
+
+    fn delete(&mut self, query: Query) -> Result<u32, Box<dyn std::error::Error>> {
+        self.lock_query(&query)?;
+        let result = self.delete_records(query);
+        self.storage.finish_work(result)
+    }
+}
+
+impl Drop for DataHandler {
+    // a transaction opened by BEGIN and left without a COMMIT is rolled back
+    fn drop(&mut self) {
+        if let (Some(transaction), true) = (self.transaction, self.begun) {
+            let _ = self.storage.rollback_transaction(transaction);
+        }
+    }
+}
+
+impl DataHandler {
+    fn insert_record(&mut self, record: Record) -> Result<InsertResult, Box<dyn std::error::Error>> {
+        // check if record compability with table constraints
+        let table_description = self.load_table_descriptor(record.get_table().get_name())?;
+        if table_description.get_name() != record.get_table().get_name() {
+            return Err("Table name mismatch".into());
+        }
+        // columns missing from the record take their default value
+        let mut record = DataHandler::complete_record(&table_description, &record)?;
+
+        // a NULL auto increment value is replaced by the next value of the table sequence,
+        // the sequence never goes back below an id given explicitly
+        let auto_increment = table_description.get_columns().iter().position(|column| column.is_auto_increment());
+        let mut sequence = None;
+        if let Some(index) = auto_increment {
+            let mut values = record.get_values().clone();
+            let last_id = self.load_table_sequence(table_description.get_name())?;
+            let id = match values[index].1.cast_to(&DataType::Integer)? {
+                Value::Integer(id) => id,
+                _ => match last_id.checked_add(1) {
+                    Some(id) => id,
+                    None => return Err(format!("Auto increment column {} is out of values", values[index].0.get_name()).into()),
+                },
+            };
+            values[index].1 = Value::Integer(id);
+            record.set_values(values);
+            sequence = Some(id.max(last_id));
+        }
+        let record = self.validate_record(&table_description, &record)?;
+
         // check if record column values are within the range for columns with range
-        // check if record column values are within the length for columns with length
         // check if record column values are within the pattern for columns with pattern
         // check if record column values are within the enum for columns with enum
 
-        // load table data
-        let file_name = record.get_table().get_name().to_string() + "." + TABLE_FILE_DATA_EXTENSION;
-        let content = self.storage.read_file(&file_name)?;
-        let mut table_data: Vec<Record> = serde_json::from_str(&content)?;
+        // check if record primary key and unique values are not used yet
+        self.check_new_keys(&table_description, &record)?;
+        // check if referenced rows exist
+        self.check_new_references(&table_description, &record)?;
 
-        //check if record already exists
-        for existing_record in table_data.iter() {
-            if record == *existing_record {
-                return Err("Record already exists".into());
-            }
+        // persist record, the new row goes to the end of the data file
+        let mut record = record;
+        let values: Vec<Value> = record.get_values().iter().map(|(_, value)| value.clone()).collect();
+        let work_id = self.storage.start_work()?;
+        self.write_table(table_description.get_name(), || {
+            record.set_row_id(Some(self.open_table_heap(&table_description)?.insert_version(&values, work_id)?));
+            self.update_indexes(&table_description, &[], std::slice::from_ref(&record))
+        })?;
+        if let Some(sequence) = sequence {
+            self.persist_table_sequence(table_description.get_name(), sequence)?;
         }
-        // add record to table data
-        table_data.push(record.clone());
-
-        // persist record
-        let content = serde_json::to_string_pretty(&table_data)?;
-        self.storage.write_file(&file_name, &content)?;
 
-        Ok(1)
-        
+        // the row id is the integer primary key of the record
+        let primary_key = table_description.get_primary_key();
+        let last_insert_id = match primary_key.as_slice() {
+            [column] => match record.get_value(column.get_name()) {
+                Some((_, Value::Integer(id))) => Some(*id),
+                _ => None,
+            },
+            _ => None,
+        };
+        Ok(InsertResult::new(1, last_insert_id))
     }
 
-    fn select(&self, query: crate::database::abstraction::Query ) -> Result<crate::database::abstraction::ResultSet, Box<dyn std::error::Error>> {
-        todo!()
+    fn select_records(&self, query: Query) -> Result<ResultSet, Box<dyn std::error::Error>> {
+        let statement = query.parse()?;
+        // EXPLAIN gives the plan of the query, one row per plan node line
+        if let Statement::Explain(statement) = &statement {
+            let plan = self.plan(statement)?;
+            let table_description = self.load_table_descriptor(statement.get_table_name().unwrap_or_default())?;
+            let column = Column::new("plan", DataType::Text(u16::MAX), false, false)?;
+            let mut result_set = ResultSet::default();
+            for line in plan.to_string().lines() {
+                result_set.add_record(Record::new(table_description.clone(), vec![(column.clone(), Value::Text(line.to_string()))]));
+            }
+            return Ok(result_set);
+        }
+        let table_name = match &statement {
+            Statement::Select { table, .. } => table,
+            _ => return Err("Query is not a SELECT statement".into()),
+        };
+        self.migrate_for_reading(table_name)?;
+        self.read_table(table_name, || {
+            let table_description = self.load_table_descriptor(table_name)?;
+            let row_count = self.open_table_heap(&table_description)?.get_tuple_count();
+            let plan = planner::plan(&statement, &table_description, row_count)?;
+            // the rows are read as they were committed when the statement started
+            let snapshot = self.storage.take_snapshot(self.transaction)?;
+            Ok(ResultSet::new(self.execute_plan(&plan, &table_description, &snapshot)?))
+        })
     }
 
-    fn update(&mut self, record: crate::database::abstraction::Record, query: crate::database::abstraction::Query) -> Result<u32, Box<dyn std::error::Error>> {
-        todo!()
+    fn update_records(&mut self, record: Record, query: Query) -> Result<u32, Box<dyn std::error::Error>> {
+        // the query gives the matching rows, an UPDATE query may also carry SET assignments
+        let statement = query.parse()?;
+        let assignments = match &statement {
+            Statement::Update { assignments, .. } => assignments.clone(),
+            Statement::Select { .. } | Statement::Delete { .. } => Vec::new(),
+            _ => return Err("Query must be a SELECT, UPDATE or DELETE statement".into()),
+        };
+        let table_name = statement.get_table_name().unwrap_or_default();
+        if table_name != record.get_table().get_name() {
+            return Err("Table name mismatch".into());
+        }
+        let table_description = self.load_table_descriptor(table_name)?;
+
+        // check if updated columns exist in the table with the same type
+        for (column, _) in record.get_values() {
+            match table_description.get_column(column.get_name()) {
+                None => return Err(format!("Unknown column {} in table {}", column.get_name(), table_name).into()),
+                Some(table_column) if table_column.get_data_type() != column.get_data_type() => {
+                    return Err(format!("Column type mismatch for column {}", column.get_name()).into());
+                }
+                Some(_) => {}
+            }
+        }
+        for (column_name, _) in assignments.iter() {
+            if table_description.get_column(column_name).is_none() {
+                return Err(format!("Unknown column {} in table {}", column_name, table_name).into());
+            }
+        }
+
+        let mut table_data = self.load_table_data(&table_description)?;
+        let mut updated_records = Vec::new();
+        for existing_record in table_data.iter_mut() {
+            if let Some(predicate) = statement.get_selection() {
+                if !evaluator::matches(predicate, existing_record)? {
+                    continue;
+                }
+            }
+            let mut values = existing_record.get_values().clone();
+            for (column, value) in record.get_values() {
+                let index = table_description.get_column_index(column.get_name()).unwrap();
+                values[index].1 = value.clone();
+            }
+            // assignments are computed from the row as it was before the update
+            for (column_name, expression) in assignments.iter() {
+                let index = table_description.get_column_index(column_name).unwrap();
+                values[index].1 = evaluator::evaluate(expression, existing_record)?;
+            }
+            let mut updated_record = Record::new(table_description.clone(), values);
+            updated_record.set_row_id(existing_record.get_row_id());
+            *existing_record = self.validate_record(&table_description, &updated_record)?;
+            updated_records.push(existing_record.clone());
+        }
+
+        if !updated_records.is_empty() {
+            DataHandler::check_unique_constraints(&table_description, &table_data)?;
+            self.check_foreign_keys(&table_description, &updated_records, &table_description, &table_data)?;
+            self.check_referencing_rows(&table_description, &table_data)?;
+            self.write_table_data(&table_description, &table_data)?;
+            // ids set by the update are not handed out by later inserts
+            if let Some(index) = table_description.get_columns().iter().position(|column| column.is_auto_increment()) {
+                let last_id = self.load_table_sequence(table_name)?;
+                let max_id = updated_records.iter()
+                    .filter_map(|record| match record.get_values()[index].1 { Value::Integer(id) => Some(id), _ => None })
+                    .fold(last_id, i64::max);
+                if max_id > last_id {
+                    self.persist_table_sequence(table_name, max_id)?;
+                }
+            }
+        }
+        Ok(updated_records.len() as u32)
     }
 
-    fn delete(&mut self, query: crate::database::abstraction::Query) -> Result<u32, Box<dyn std::error::Error>> {
-        todo!()
+    fn delete_records(&mut self, query: Query) -> Result<u32, Box<dyn std::error::Error>> {
+        // a query without WHERE clause removes every row of the table
+        let statement = query.parse()?;
+        if !matches!(statement, Statement::Delete { .. } | Statement::Select { .. }) {
+            return Err("Query must be a DELETE or SELECT statement".into());
+        }
+        let table_name = statement.get_table_name().unwrap_or_default();
+        let table_description = self.load_table_descriptor(table_name)?;
+        let table_data = self.load_table_data(&table_description)?;
+        let record_count = table_data.len();
+
+        // every row is evaluated before the data file is rewritten
+        let mut kept_records = Vec::new();
+        let mut deleted_records = Vec::new();
+        for existing_record in table_data {
+            let is_deleted = match statement.get_selection() {
+                Some(predicate) => evaluator::matches(predicate, &existing_record)?,
+                None => true,
+            };
+            if is_deleted {
+                deleted_records.push(existing_record);
+            } else {
+                kept_records.push(existing_record);
+            }
+        }
+
+        let deleted_count = record_count - kept_records.len();
+        if deleted_count > 0 {
+            // rows of other tables referencing the deleted rows are changed along with them
+            let mut changes = BTreeMap::new();
+            changes.insert(table_name.to_string(), (table_description.clone(), kept_records));
+            self.apply_delete_actions(&table_description, &deleted_records, &mut changes)?;
+            for (table, records) in changes.values() {
+                self.write_table_data(table, records)?;
+            }
+        }
+        Ok(deleted_count as u32)
     }
 }
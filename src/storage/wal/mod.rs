@@ -0,0 +1,695 @@
+// wal is a module that contains the write-ahead log of a database directory.
+// undo records are synced before a file changes, work left without a commit is rolled back on open
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::{self, ThreadId};
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::directory_key;
+use super::locks;
+
+// log of a database directory, next to the table files
+pub const WAL_FILE_NAME: &str = "wal.log";
+// extension of the files deleted by work not committed yet
+pub const TRASH_FILE_EXTENSION: &str = "trash";
+// upper bound of the transaction ids handed out in the directory
+pub const XID_FILE_NAME: &str = "wal.xid";
+// number of transaction ids reserved by each write of the id file
+const XID_BATCH: u64 = 1024;
+
+// whether a file of the directory belongs to the log rather than to the tables
+pub fn is_log_file(file_name: &str) -> bool {
+    file_name.starts_with(WAL_FILE_NAME) || file_name.starts_with(XID_FILE_NAME) || file_name.ends_with(TRASH_FILE_EXTENSION)
+}
+
+// how much is synced to disk, a sync makes a write survive a power loss, not only a crash of the process
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Durability {
+    // nothing is synced, for tests and throwaway databases
+    Off,
+    // the log and the files are synced, not the directories holding them
+    Normal,
+    // the log, the files and their directory are synced
+    Full,
+}
+
+// durability of the logs opened from now on
+static DEFAULT_DURABILITY: Mutex<Durability> = Mutex::new(Durability::Full);
+
+pub fn get_default_durability() -> Durability {
+    *DEFAULT_DURABILITY.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+pub fn set_default_durability(durability: Durability) {
+    *DEFAULT_DURABILITY.lock().unwrap_or_else(|error| error.into_inner()) = durability;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LogRecord {
+    // content of a page before it was written, and the length of its file at that time
+    PageWrite { file: String, offset: u64, file_length: u64, before: Vec<u8> },
+    // content of a whole file before it was written, None when the file did not exist
+    FileWrite { file: String, before: Option<Vec<u8>> },
+    CreateFile { file: String },
+    // the file was renamed to its trash name
+    DeleteFile { file: String, trash: String },
+    RenameFile { from: String, to: String },
+    Commit,
+    // the work was undone, its records are not undone again
+    Rollback,
+}
+
+// a record as written in the log, along with the key of its work
+#[derive(Debug, Serialize, Deserialize)]
+struct LogEntry<R> {
+    work: u64,
+    record: R,
+}
+
+// a unit of work in progress: a statement, or the statements of a transaction
+#[derive(Debug, Default)]
+struct Work {
+    // tags the records of the work in the log, and the tables it owns
+    key: u64,
+    // records of the work not committed yet
+    pending: Vec<LogRecord>,
+    // files written by the work not committed yet, synced on commit
+    touched_files: BTreeSet<String>,
+    // pages whose content before the work is already logged
+    logged_pages: HashSet<(String, u64)>,
+    created_files: HashSet<String>,
+    // id of the transaction of the work, its work is committed by its end only
+    transaction: Option<u64>,
+    // pending records of the statements of the transaction that succeeded
+    statement_start: usize,
+    // transaction id of the work, given on its first change
+    id: Option<u64>,
+    // the work appended records to the log, they are closed by a commit or rollback record
+    logged: bool,
+}
+
+impl Work {
+    fn is_pending(&self) -> bool {
+        !self.pending.is_empty() || !self.touched_files.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub struct WriteAheadLog {
+    root_dir: String,
+    recovered: bool,
+    // work in progress by thread, a statement belongs to the work of the thread running it
+    works: HashMap<ThreadId, Work>,
+    work_count: u64,
+    trash_count: u64,
+    durability: Durability,
+    // next transaction id, and the first one not reserved in the id file
+    next_id: u64,
+    reserved_id: u64,
+    // snapshots of the readers by id, with the next transaction id and the work in progress when taken
+    snapshots: HashMap<u64, (u64, Vec<u64>)>,
+    snapshot_count: u64,
+}
+
+impl WriteAheadLog {
+    pub fn new(root_dir: &str) -> WriteAheadLog {
+        WriteAheadLog {
+            root_dir: root_dir.to_string(),
+            recovered: false,
+            works: HashMap::new(),
+            work_count: 0,
+            trash_count: 0,
+            durability: get_default_durability(),
+            next_id: 1,
+            reserved_id: 1,
+            snapshots: HashMap::new(),
+            snapshot_count: 0,
+        }
+    }
+
+    fn path(&self, file_name: &str) -> PathBuf {
+        Path::new(&self.root_dir).join(file_name)
+    }
+
+    // work of a thread, started on first use
+    fn work_of(&mut self, thread: ThreadId) -> &mut Work {
+        let work_count = &mut self.work_count;
+        self.works.entry(thread).or_insert_with(|| {
+            *work_count += 1;
+            Work { key: *work_count, ..Work::default() }
+        })
+    }
+
+    fn current_work(&mut self) -> &mut Work {
+        self.work_of(thread::current().id())
+    }
+
+    pub fn get_durability(&self) -> Durability {
+        self.durability
+    }
+
+    pub fn set_durability(&mut self, durability: Durability) {
+        self.durability = durability;
+    }
+
+    // sync a file written by the work, unless the durability level skips it
+    pub fn sync_file(&self, file: &File) -> Result<(), Box<dyn Error>> {
+        if self.durability != Durability::Off {
+            file.sync_all()?;
+        }
+        Ok(())
+    }
+
+    // sync the directory after files were created, deleted or renamed in it
+    pub fn sync_root_dir(&self) -> Result<(), Box<dyn Error>> {
+        if self.durability == Durability::Full {
+            sync_directory(&self.root_dir)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_recovered(&self) -> bool {
+        self.recovered
+    }
+
+    // true when the log holds records, a crash left work to undo
+    pub fn has_log_records(&self) -> bool {
+        std::fs::metadata(self.path(WAL_FILE_NAME)).is_ok_and(|metadata| metadata.len() > 0)
+    }
+
+    // true while work of any thread is waiting for its commit, or a transaction is open
+    pub fn has_pending_work(&self) -> bool {
+        self.works.values().any(|work| work.is_pending() || work.transaction.is_some())
+    }
+
+    // records of the work of the calling thread not committed yet
+    pub fn get_pending_records(&self) -> Vec<LogRecord> {
+        self.works.get(&thread::current().id()).map(|work| work.pending.clone()).unwrap_or_default()
+    }
+
+    // threads with work in progress
+    pub fn get_work_threads(&self) -> Vec<ThreadId> {
+        self.works.keys().copied().collect()
+    }
+
+    // key of the work of the calling thread, the work is started when the thread has none
+    pub fn get_work_key(&mut self) -> u64 {
+        self.current_work().key
+    }
+
+    // files written by the work of a thread not committed yet
+    pub fn get_touched_files(&self, thread: ThreadId) -> BTreeSet<String> {
+        self.works.get(&thread).map(|work| work.touched_files.clone()).unwrap_or_default()
+    }
+
+    // a file is about to be written by the work of the calling thread
+    pub fn touch_file(&mut self, file_name: &str) {
+        let work = self.current_work();
+        if !work.touched_files.contains(file_name) {
+            work.touched_files.insert(file_name.to_string());
+        }
+    }
+
+    // transaction of the work of the calling thread
+    pub fn get_transaction(&self) -> Option<u64> {
+        self.works.get(&thread::current().id()).and_then(|work| work.transaction)
+    }
+
+    // thread whose work is an open transaction
+    pub fn get_transaction_thread(&self, transaction: u64) -> Option<ThreadId> {
+        self.works.iter().find(|(_, work)| work.transaction == Some(transaction)).map(|(thread, _)| *thread)
+    }
+
+    // open a transaction for the work of the calling thread, returns its id
+    pub fn begin_transaction(&mut self) -> Result<u64, Box<dyn Error>> {
+        if self.get_transaction().is_some() {
+            return Err("A transaction is already open".into());
+        }
+        let id = self.next_transaction_id()?;
+        let work = self.current_work();
+        work.transaction = Some(id);
+        work.id = Some(id);
+        work.statement_start = work.pending.len();
+        Ok(id)
+    }
+
+    // move the work of an open transaction to the calling thread, for the thread to run its statements
+    pub fn attach_transaction(&mut self, transaction: u64) -> Result<(), Box<dyn Error>> {
+        let current = thread::current().id();
+        let thread = match self.get_transaction_thread(transaction) {
+            Some(thread) => thread,
+            None => return Err(format!("Transaction {} is not open", transaction).into()),
+        };
+        if thread == current {
+            return Ok(());
+        }
+        if self.works.get(&current).is_some_and(|work| work.is_pending() || work.transaction.is_some()) {
+            return Err("Work of another transaction is in progress on this thread".into());
+        }
+        let work = self.works.remove(&thread).unwrap();
+        self.works.insert(current, work);
+        Ok(())
+    }
+
+    // forget the transaction of a thread, once its work is committed or rolled back
+    pub fn end_transaction(&mut self, thread: ThreadId) {
+        self.end_work(thread);
+    }
+
+    // transaction id of the work of the calling thread, the id is given on the first call of the work
+    pub fn start_work(&mut self) -> Result<u64, Box<dyn Error>> {
+        if let Some(id) = self.current_work().id {
+            return Ok(id);
+        }
+        let id = self.next_transaction_id()?;
+        self.current_work().id = Some(id);
+        Ok(id)
+    }
+
+    // transaction ids of the work in progress
+    pub fn get_work_ids(&self) -> Vec<u64> {
+        self.works.values().filter_map(|work| work.id).collect()
+    }
+
+    // register the snapshot of a reader, returns its id, the next transaction id and the work in progress.
+    // Work with a lower id than the next one, other than the work in progress, is committed
+    pub fn register_snapshot(&mut self) -> (u64, u64, Vec<u64>) {
+        self.snapshot_count += 1;
+        let active = self.get_work_ids();
+        self.snapshots.insert(self.snapshot_count, (self.next_id, active.clone()));
+        (self.snapshot_count, self.next_id, active)
+    }
+
+    pub fn release_snapshot(&mut self, snapshot: u64) {
+        self.snapshots.remove(&snapshot);
+    }
+
+    // next transaction id and work in progress of every registered snapshot
+    pub fn get_snapshots(&self) -> Vec<(u64, Vec<u64>)> {
+        self.snapshots.values().cloned().collect()
+    }
+
+    // hand out a transaction id, a new batch of ids is reserved on disk first when the batch is used up
+    fn next_transaction_id(&mut self) -> Result<u64, Box<dyn Error>> {
+        if self.next_id >= self.reserved_id {
+            let reserved_id = self.next_id + XID_BATCH;
+            let temp = self.path(&format!("{}.tmp", XID_FILE_NAME));
+            let mut file = File::create(&temp)?;
+            file.write_all(&reserved_id.to_le_bytes())?;
+            self.sync_file(&file)?;
+            std::fs::rename(&temp, self.path(XID_FILE_NAME))?;
+            self.sync_root_dir()?;
+            self.reserved_id = reserved_id;
+        }
+        self.next_id += 1;
+        Ok(self.next_id - 1)
+    }
+
+    // close a statement of the transaction of the calling thread that succeeded, its changes must already
+    // be written to their file. Pages and created files are logged again by the next statements, to be
+    // undone on their own
+    pub fn end_statement(&mut self) {
+        let work = self.current_work();
+        work.statement_start = work.pending.len();
+        work.logged_pages.clear();
+        work.created_files.clear();
+    }
+
+    // undo the work of the current statement of the transaction of the calling thread, returns the files
+    // changed on the way. Its records stay in the log, undoing them again after a crash is harmless
+    pub fn rollback_statement(&mut self) -> Result<Vec<String>, Box<dyn Error>> {
+        let work = self.current_work();
+        let records = work.pending.split_off(work.statement_start.min(work.pending.len()));
+        work.logged_pages.clear();
+        work.created_files.clear();
+        self.undo(&records)
+    }
+
+    // bring the directory back to its last committed state, returns the files changed on the way.
+    // Runs once, before the directory is used
+    pub fn recover(&mut self) -> Result<Vec<String>, Box<dyn Error>> {
+        // ids reserved before the crash may be in use, the next ones come after them
+        if let Ok(content) = std::fs::read(self.path(XID_FILE_NAME)) {
+            let reserved_id = u64::from_le_bytes(content.get(..8).ok_or("Truncated transaction id file")?.try_into()?);
+            self.next_id = self.next_id.max(reserved_id);
+            self.reserved_id = self.next_id;
+        }
+        let entries = self.read_entries()?;
+        let committed: HashSet<u64> = entries.iter().filter(|entry| entry.record == LogRecord::Commit).map(|entry| entry.work).collect();
+        let rolled_back: HashSet<u64> = entries.iter().filter(|entry| entry.record == LogRecord::Rollback).map(|entry| entry.work).collect();
+        // trash of committed work may have been left behind
+        for entry in entries.iter().filter(|entry| committed.contains(&entry.work)) {
+            if let LogRecord::DeleteFile { trash, .. } = &entry.record {
+                remove_if_exists(&self.path(trash))?;
+            }
+        }
+        let records: Vec<LogRecord> = entries.into_iter()
+            .filter(|entry| !committed.contains(&entry.work) && !rolled_back.contains(&entry.work))
+            .map(|entry| entry.record)
+            .collect();
+        let changed = self.undo(&records)?;
+        self.truncate()?;
+        self.recovered = true;
+        Ok(changed)
+    }
+
+    // undo the work of a thread not committed yet, returns the files changed on the way.
+    // The log keeps a rollback record of the work while it holds the work of other threads
+    pub fn rollback(&mut self, thread: ThreadId) -> Result<Vec<String>, Box<dyn Error>> {
+        let Some(work) = self.works.get_mut(&thread) else {
+            return Ok(Vec::new());
+        };
+        let pending = std::mem::take(&mut work.pending);
+        let touched_files = std::mem::take(&mut work.touched_files);
+        let logged = work.logged;
+        let mut changed = self.undo(&pending)?;
+        changed.extend(touched_files);
+        if !self.is_shared(thread) {
+            self.truncate()?;
+        } else if logged {
+            self.append_work(thread, vec![LogRecord::Rollback])?;
+        }
+        self.clear(thread);
+        Ok(changed)
+    }
+
+    // records of the content of pages about to be written, each page is logged for the work writing its
+    // file, unless undoing the work does not need it
+    pub fn log_pages(&mut self, pages: Vec<(&str, u64, usize)>) -> Result<(), Box<dyn Error>> {
+        let mut records: HashMap<ThreadId, Vec<LogRecord>> = HashMap::new();
+        for (file_name, offset, length) in pages {
+            let thread = self.get_file_thread(file_name);
+            if let Some(record) = self.page_record(thread, file_name, offset, length)? {
+                records.entry(thread).or_default().push(record);
+            }
+        }
+        for (thread, records) in records {
+            self.append_work(thread, records)?;
+        }
+        Ok(())
+    }
+
+    // thread of the work writing a file, the calling thread when no work writes it
+    fn get_file_thread(&self, file_name: &str) -> ThreadId {
+        self.works.iter()
+            .find(|(_, work)| work.touched_files.contains(file_name))
+            .map_or_else(|| thread::current().id(), |(thread, _)| *thread)
+    }
+
+    // record of the content of a page about to be written, None when undoing the work does not need it
+    fn page_record(&mut self, thread: ThreadId, file_name: &str, offset: u64, length: usize) -> Result<Option<LogRecord>, Box<dyn Error>> {
+        let path = self.path(file_name);
+        let work = self.work_of(thread);
+        work.touched_files.insert(file_name.to_string());
+        // a file created by the work is deleted on undo
+        if work.created_files.contains(file_name) || !work.logged_pages.insert((file_name.to_string(), offset)) {
+            return Ok(None);
+        }
+        let mut file = File::open(path)?;
+        let file_length = file.metadata()?.len();
+        let mut before = Vec::new();
+        if offset < file_length {
+            file.seek(SeekFrom::Start(offset))?;
+            file.take(length as u64).read_to_end(&mut before)?;
+        }
+        Ok(Some(LogRecord::PageWrite { file: file_name.to_string(), offset, file_length, before }))
+    }
+
+    // record of the content of a file about to be written by the work of the calling thread
+    pub fn file_record(&mut self, file_name: &str) -> Result<LogRecord, Box<dyn Error>> {
+        self.touch_file(file_name);
+        let path = self.path(file_name);
+        let before = if path.exists() { Some(std::fs::read(path)?) } else { None };
+        Ok(LogRecord::FileWrite { file: file_name.to_string(), before })
+    }
+
+    pub fn create_record(&mut self, file_name: &str) -> LogRecord {
+        let work = self.current_work();
+        work.touched_files.insert(file_name.to_string());
+        work.created_files.insert(file_name.to_string());
+        LogRecord::CreateFile { file: file_name.to_string() }
+    }
+
+    // record of a file about to be deleted, the file is to be renamed to the trash name of the record
+    pub fn delete_record(&mut self, file_name: &str) -> LogRecord {
+        let work = self.current_work();
+        work.touched_files.remove(file_name);
+        work.created_files.remove(file_name);
+        work.logged_pages.retain(|(file, _)| file != file_name);
+        self.trash_count += 1;
+        let trash = format!("{}.{}.{}", file_name, self.trash_count, TRASH_FILE_EXTENSION);
+        LogRecord::DeleteFile { file: file_name.to_string(), trash }
+    }
+
+    pub fn rename_record(&mut self, file_name: &str, new_file_name: &str) -> LogRecord {
+        let work = self.current_work();
+        work.touched_files.remove(file_name);
+        work.touched_files.insert(new_file_name.to_string());
+        LogRecord::RenameFile { from: file_name.to_string(), to: new_file_name.to_string() }
+    }
+
+    // append records of the work of the calling thread to the log, they are on disk once this returns
+    pub fn append(&mut self, records: Vec<LogRecord>) -> Result<(), Box<dyn Error>> {
+        self.append_work(thread::current().id(), records)
+    }
+
+    fn append_work(&mut self, thread: ThreadId, records: Vec<LogRecord>) -> Result<(), Box<dyn Error>> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let key = self.work_of(thread).key;
+        let is_new = !self.path(WAL_FILE_NAME).exists();
+        let mut log = OpenOptions::new().create(true).append(true).open(self.path(WAL_FILE_NAME))?;
+        if is_new {
+            self.sync_root_dir()?;
+        }
+        for record in &records {
+            let payload = serde_json::to_vec(&LogEntry { work: key, record })?;
+            let mut entry = Vec::with_capacity(payload.len() + 12);
+            entry.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            entry.extend_from_slice(&checksum(&payload).to_le_bytes());
+            entry.extend_from_slice(&payload);
+            log.write_all(&entry)?;
+        }
+        if self.durability != Durability::Off {
+            log.sync_data()?;
+        }
+        let work = self.work_of(thread);
+        work.pending.extend(records);
+        work.logged = true;
+        Ok(())
+    }
+
+    // commit the pending work of a thread, every change it made must already be written to its file
+    pub fn commit(&mut self, thread: ThreadId) -> Result<(), Box<dyn Error>> {
+        let Some(work) = self.works.get(&thread) else {
+            return Ok(());
+        };
+        if !work.is_pending() && !work.logged {
+            self.clear(thread);
+            return Ok(());
+        }
+        for file_name in &work.touched_files {
+            let path = self.path(file_name);
+            if path.exists() {
+                self.sync_file(&File::open(path)?)?;
+            }
+        }
+        let changes_directory = work.pending.iter().any(|record| matches!(record,
+            LogRecord::CreateFile { .. } | LogRecord::DeleteFile { .. } | LogRecord::RenameFile { .. }));
+        if changes_directory {
+            self.sync_root_dir()?;
+        }
+        let work = self.work_of(thread);
+        let pending = std::mem::take(&mut work.pending);
+        if work.logged {
+            self.append_work(thread, vec![LogRecord::Commit])?;
+        }
+        for record in &pending {
+            if let LogRecord::DeleteFile { trash, .. } = record {
+                remove_if_exists(&self.path(trash))?;
+            }
+        }
+        if !self.is_shared(thread) {
+            self.truncate()?;
+        }
+        self.clear(thread);
+        Ok(())
+    }
+
+    // true when the log holds records of work of other threads not committed yet, the log is emptied
+    // once no work has records in it
+    fn is_shared(&self, thread: ThreadId) -> bool {
+        self.works.iter().any(|(other, work)| *other != thread && !work.pending.is_empty())
+    }
+
+    // forget what the work of a thread changed once committed or rolled back, the work of a transaction
+    // goes on until the transaction ends
+    fn clear(&mut self, thread: ThreadId) {
+        let Some(work) = self.works.get_mut(&thread) else {
+            return;
+        };
+        if work.transaction.is_none() {
+            self.end_work(thread);
+            return;
+        }
+        work.pending.clear();
+        work.touched_files.clear();
+        work.logged_pages.clear();
+        work.created_files.clear();
+        work.statement_start = 0;
+        work.logged = false;
+        work.id = work.transaction;
+    }
+
+    // forget the work of a thread, the tables it owns are released
+    fn end_work(&mut self, thread: ThreadId) {
+        if let Some(work) = self.works.remove(&thread) {
+            locks::shared_directory_locks(&self.root_dir).release_tables(work.key);
+        }
+    }
+
+    // records of the log with the key of their work, up to the first torn record
+    fn read_entries(&self) -> Result<Vec<LogEntry<LogRecord>>, Box<dyn Error>> {
+        let path = self.path(WAL_FILE_NAME);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = std::fs::read(path)?;
+        let mut entries = Vec::new();
+        // records written before they were tagged with their work belong to the work of the last commit
+        let mut untagged_work = u64::MAX;
+        let mut position = 0;
+        while position + 12 <= content.len() {
+            let length = u32::from_le_bytes(content[position..position + 4].try_into()?) as usize;
+            let sum = u64::from_le_bytes(content[position + 4..position + 12].try_into()?);
+            let payload = match content.get(position + 12..position + 12 + length) {
+                Some(payload) if checksum(payload) == sum => payload,
+                _ => break,
+            };
+            if let Ok(entry) = serde_json::from_slice::<LogEntry<LogRecord>>(payload) {
+                entries.push(entry);
+            } else if let Ok(record) = serde_json::from_slice::<LogRecord>(payload) {
+                let is_commit = record == LogRecord::Commit;
+                entries.push(LogEntry { work: untagged_work, record });
+                if is_commit {
+                    untagged_work -= 1;
+                }
+            } else {
+                break;
+            }
+            position += 12 + length;
+        }
+        Ok(entries)
+    }
+
+    // apply the records backwards, each step can be replayed after a crash in the middle
+    fn undo(&self, records: &[LogRecord]) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut changed = BTreeSet::new();
+        for record in records.iter().rev() {
+            match record {
+                LogRecord::PageWrite { file, offset, file_length, before } => {
+                    let path = self.path(file);
+                    if path.exists() {
+                        let mut data_file = OpenOptions::new().write(true).open(&path)?;
+                        data_file.seek(SeekFrom::Start(*offset))?;
+                        data_file.write_all(before)?;
+                        if data_file.metadata()?.len() > *file_length {
+                            data_file.set_len(*file_length)?;
+                        }
+                    }
+                    changed.insert(file.clone());
+                }
+                LogRecord::FileWrite { file, before } => {
+                    match before {
+                        Some(content) => std::fs::write(self.path(file), content)?,
+                        None => remove_if_exists(&self.path(file))?,
+                    }
+                    changed.insert(file.clone());
+                }
+                LogRecord::CreateFile { file } => {
+                    remove_if_exists(&self.path(file))?;
+                    changed.insert(file.clone());
+                }
+                LogRecord::DeleteFile { file, trash } => {
+                    if self.path(trash).exists() {
+                        std::fs::rename(self.path(trash), self.path(file))?;
+                    }
+                    changed.insert(file.clone());
+                }
+                LogRecord::RenameFile { from, to } => {
+                    if self.path(to).exists() && !self.path(from).exists() {
+                        std::fs::rename(self.path(to), self.path(from))?;
+                    }
+                    changed.insert(from.clone());
+                    changed.insert(to.clone());
+                }
+                LogRecord::Commit | LogRecord::Rollback => {}
+            }
+        }
+        // the undone state is on disk before the log forgets it
+        for file_name in &changed {
+            let path = self.path(file_name);
+            if path.exists() {
+                self.sync_file(&File::open(path)?)?;
+            }
+        }
+        if !records.is_empty() {
+            self.sync_root_dir()?;
+        }
+        Ok(changed.into_iter().collect())
+    }
+
+    fn truncate(&self) -> Result<(), Box<dyn Error>> {
+        let path = self.path(WAL_FILE_NAME);
+        if path.exists() {
+            let log = OpenOptions::new().write(true).open(path)?;
+            log.set_len(0)?;
+            self.sync_file(&log)?;
+        }
+        Ok(())
+    }
+}
+
+fn remove_if_exists(path: &Path) -> Result<(), Box<dyn Error>> {
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+fn sync_directory(root_dir: &str) -> Result<(), Box<dyn Error>> {
+    File::open(root_dir)?.sync_all()?;
+    Ok(())
+}
+
+// FNV-1a, enough to tell a torn record from a complete one
+fn checksum(payload: &[u8]) -> u64 {
+    payload.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+
+// logs by database directory, every file storage of a directory shares its log
+fn write_ahead_logs() -> &'static Mutex<HashMap<PathBuf, Arc<Mutex<WriteAheadLog>>>> {
+    static WRITE_AHEAD_LOGS: OnceLock<Mutex<HashMap<PathBuf, Arc<Mutex<WriteAheadLog>>>>> = OnceLock::new();
+    WRITE_AHEAD_LOGS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// the log of a directory, recovery runs on its first use
+pub fn shared_write_ahead_log(root_dir: &str) -> Arc<Mutex<WriteAheadLog>> {
+    let mut logs = write_ahead_logs().lock().unwrap_or_else(|error| error.into_inner());
+    logs.entry(directory_key(root_dir))
+        .or_insert_with(|| Arc::new(Mutex::new(WriteAheadLog::new(root_dir))))
+        .clone()
+}
+
+// forget the log of a directory, the next use of the directory recovers it again
+pub fn release_write_ahead_log(root_dir: &str) {
+    let mut logs = write_ahead_logs().lock().unwrap_or_else(|error| error.into_inner());
+    logs.remove(&directory_key(root_dir));
+}
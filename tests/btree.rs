@@ -1,8 +1,15 @@
-use red::database::abstraction::{BPlusTree, NodeType};
+use std::ops::Bound::{Excluded, Included, Unbounded};
+
+use red::database::abstraction::{BPlusTree, NodeType, Value};
+use red::database::btree::{page_order, INDEX_PAGE_SIZE};
+use red::storage::files::FileStorage;
+
+mod common;
+use crate::common::{setup, ROOT_DIR};
 
 #[test]
 fn test_btree_creation() {
-    let mut btree = BPlusTree::new(3);
+    let btree: BPlusTree<i32, u64> = BPlusTree::new(3);
     assert_eq!(btree.get_order(), 3);
     assert!(btree.get_root_node().is_none());
     assert_eq!(0, btree.get_tree_height());
@@ -15,22 +22,267 @@ fn test_btree_add_leaf() {
     assert!(btree.get_root_node().is_none());
     assert_eq!(0, btree.get_tree_height());
 
-    btree.insert(7);
+    btree.insert(7, 0).unwrap();
     assert_eq!(1, btree.get_tree_height());
     assert_eq!("LeafNode", type_of_node(btree.get_root_node().unwrap()));
 
-    btree.insert(10);
+    btree.insert(10, 1).unwrap();
     assert_eq!(1, btree.get_tree_height());
     assert_eq!("LeafNode", type_of_node(btree.get_root_node().unwrap()));
 
-    btree.insert(15);
+    btree.insert(15, 2).unwrap();
     assert_eq!(2, btree.get_tree_height());
     assert_eq!("InternalNode", type_of_node(btree.get_root_node().unwrap()));
 }
 
-fn type_of_node(node: &NodeType) -> String {
+fn type_of_node<K, V>(node: &NodeType<K, V>) -> String {
     match node {
         NodeType::Leaf(_) => "LeafNode".to_string(),
         NodeType::Internal(_) => "InternalNode".to_string(),
     }
 }
+
+fn keys<K, V>(entries: impl Iterator<Item = Result<(K, V), Box<dyn std::error::Error>>>) -> Vec<K> {
+    entries.map(|entry| entry.unwrap().0).collect()
+}
+
+#[test]
+fn test_btree_search() {
+    let mut btree = BPlusTree::new(3);
+    assert_eq!(btree.search(&1).unwrap(), None);
+    for key in [50, 20, 80, 10, 30, 70, 90, 60, 40] {
+        assert_eq!(btree.insert(key, key as u64 * 10).unwrap(), None);
+    }
+    // an existing key gets its new value
+    assert_eq!(btree.insert(30, 31).unwrap(), Some(300));
+    assert!(btree.get_tree_height() > 1);
+    for key in [10, 20, 40, 50, 60, 70, 80, 90] {
+        assert_eq!(btree.search(&key).unwrap(), Some(key as u64 * 10));
+    }
+    assert_eq!(btree.search(&30).unwrap(), Some(31));
+    for key in [0, 15, 55, 100] {
+        assert!(!btree.contains_key(&key).unwrap());
+    }
+}
+
+#[test]
+fn test_btree_range() {
+    let mut btree = BPlusTree::new(2);
+    // insert in a scattered order so that splits happen all over the tree
+    for i in 0..100 {
+        btree.insert((i * 37) % 100, ()).unwrap();
+    }
+    assert_eq!(keys(btree.iter()), (0..100).collect::<Vec<i32>>());
+    assert_eq!(keys(btree.range(10..15)), vec![10, 11, 12, 13, 14]);
+    assert_eq!(keys(btree.range(10..=15)), vec![10, 11, 12, 13, 14, 15]);
+    assert_eq!(keys(btree.range((Excluded(10), Included(12)))), vec![11, 12]);
+    assert_eq!(keys(btree.range((Excluded(97), Unbounded))), vec![98, 99]);
+    assert_eq!(keys(btree.range(..3)), vec![0, 1, 2]);
+    assert_eq!(btree.range(200..).count(), 0);
+    assert_eq!(btree.range(-5..-1).count(), 0);
+    assert_eq!(BPlusTree::<i32, ()>::new(3).iter().count(), 0);
+}
+
+#[test]
+fn test_btree_linked_leaves() {
+    let mut btree = BPlusTree::new(2);
+    for key in 1..=20 {
+        btree.insert(key, key * 2).unwrap();
+    }
+    // walk down to the leftmost leaf, then along the sibling links
+    let mut node = btree.get_root_node().unwrap();
+    while let NodeType::Internal(internal_node) = node {
+        node = btree.get_node(internal_node.get_children()[0]).unwrap();
+    }
+    let mut keys: Vec<i32> = Vec::new();
+    loop {
+        let leaf_node = match node {
+            NodeType::Leaf(leaf_node) => leaf_node,
+            NodeType::Internal(_) => panic!("Expected a leaf node")
+        };
+        assert!(leaf_node.get_keys().len() <= btree.get_order());
+        for (key, value) in leaf_node.get_keys().iter().zip(leaf_node.get_values()) {
+            assert_eq!(*value, key * 2);
+        }
+        keys.extend(leaf_node.get_keys());
+        match leaf_node.get_next() {
+            None => break,
+            Some(next) => node = btree.get_node(next).unwrap(),
+        }
+    }
+    assert_eq!(keys, (1..=20).collect::<Vec<i32>>());
+}
+
+#[test]
+fn test_btree_remove() {
+    let mut btree = BPlusTree::new(3);
+    assert_eq!(btree.remove(&1).unwrap(), None);
+    for key in 1..=30 {
+        btree.insert(key, key + 100).unwrap();
+        btree.check_invariants().unwrap();
+    }
+    let height = btree.get_tree_height();
+    assert_eq!(btree.remove(&31).unwrap(), None);
+
+    // remove every other key, then the rest from the right
+    for key in (1..=30).step_by(2) {
+        assert_eq!(btree.remove(&key).unwrap(), Some(key + 100));
+        btree.check_invariants().unwrap();
+        assert!(!btree.contains_key(&key).unwrap());
+    }
+    assert_eq!(keys(btree.iter()), (2..=30).step_by(2).collect::<Vec<i32>>());
+    for key in (2..=30).rev().step_by(2) {
+        assert_eq!(btree.remove(&key).unwrap(), Some(key + 100));
+        btree.check_invariants().unwrap();
+        assert!(btree.get_tree_height() <= height);
+    }
+    assert!(btree.is_empty());
+    assert_eq!(0, btree.get_tree_height());
+}
+
+#[test]
+fn test_btree_insert_remove_mixed() {
+    // a small linear congruential generator keeps the sequence reproducible
+    let mut seed: u64 = 42;
+    let mut next = || {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((seed >> 33) % 200) as i32
+    };
+    for order in 2..6 {
+        let mut btree = BPlusTree::new(order);
+        let mut expected = std::collections::BTreeMap::new();
+        for step in 0..2000 {
+            let key = next();
+            if next() % 3 == 0 {
+                assert_eq!(btree.remove(&key).unwrap(), expected.remove(&key));
+            } else {
+                assert_eq!(btree.insert(key, step).unwrap(), expected.insert(key, step));
+            }
+            btree.check_invariants().unwrap();
+        }
+        assert_eq!(btree.iter().map(Result::unwrap).collect::<Vec<(i32, i32)>>(), expected.clone().into_iter().collect::<Vec<(i32, i32)>>());
+        assert_eq!(btree.range(50..150).count(), expected.range(50..150).count());
+    }
+}
+
+#[test]
+fn test_btree_composite_keys() {
+    // a non unique index on a text column keys its entries by (value, row id)
+    let mut btree: BPlusTree<Vec<Value>, u64> = BPlusTree::new(4);
+    let names = ["carol", "alice", "bob", "alice", "dave", "bob", "alice"];
+    for (row_id, name) in names.iter().enumerate() {
+        let key = vec![Value::Text(name.to_string()), Value::Integer(row_id as i64)];
+        assert_eq!(btree.insert(key, row_id as u64).unwrap(), None);
+    }
+    btree.check_invariants().unwrap();
+
+    // every row of a value is found with a range on the key prefix
+    let alice = Value::Text("alice".to_string());
+    let rows: Vec<u64> = btree
+        .range(vec![alice.clone()]..vec![Value::Text("alicf".to_string())])
+        .map(|entry| entry.unwrap().1)
+        .collect();
+    assert_eq!(rows, vec![1, 3, 6]);
+    assert_eq!(btree.search(&vec![alice, Value::Integer(3)]).unwrap(), Some(3));
+    let first = btree.iter().next().unwrap().unwrap();
+    assert_eq!(first.0[0], Value::Text("alice".to_string()));
+}
+
+fn create_index_file(file_name: &str, order: usize) -> BPlusTree<i64, u64> {
+    setup();
+    if std::fs::metadata(format!("{}/{}", ROOT_DIR, file_name)).is_ok() {
+        std::fs::remove_file(format!("{}/{}", ROOT_DIR, file_name)).unwrap();
+    }
+    BPlusTree::create(&FileStorage::new(ROOT_DIR), file_name, order).unwrap()
+}
+
+#[test]
+fn test_btree_persistence() {
+    let file_name = "test_btree_persistence.idx";
+    let storage = FileStorage::new(ROOT_DIR);
+    let mut btree = create_index_file(file_name, 8);
+    for key in 0..500 {
+        btree.insert((key * 37) % 500, key as u64).unwrap();
+    }
+    btree.flush().unwrap();
+    storage.checkpoint().unwrap();
+    let height = btree.get_tree_height();
+    drop(btree);
+
+    // the file holds a header page and one page per node
+    let file_size = std::fs::metadata(format!("{}/{}", ROOT_DIR, file_name)).unwrap().len();
+    assert_eq!(file_size % INDEX_PAGE_SIZE as u64, 0);
+
+    let mut btree: BPlusTree<i64, u64> = BPlusTree::open(&storage, file_name).unwrap();
+    assert_eq!(btree.get_order(), 8);
+    assert_eq!(btree.get_tree_height(), height);
+    assert_eq!(btree.search(&37).unwrap(), Some(1));
+    assert_eq!(keys(btree.range(100..105)), vec![100, 101, 102, 103, 104]);
+    btree.check_invariants().unwrap();
+
+    // removed keys and freed nodes survive another reopen
+    for key in 0..400 {
+        assert!(btree.remove(&key).unwrap().is_some());
+    }
+    btree.flush().unwrap();
+    let mut btree: BPlusTree<i64, u64> = BPlusTree::open(&storage, file_name).unwrap();
+    btree.check_invariants().unwrap();
+    assert_eq!(keys(btree.iter()), (400..500).collect::<Vec<i64>>());
+    for key in 0..400 {
+        btree.insert(key, 0).unwrap();
+    }
+    btree.flush().unwrap();
+    let mut btree: BPlusTree<i64, u64> = BPlusTree::open(&storage, file_name).unwrap();
+    assert_eq!(btree.iter().count(), 500);
+    btree.check_invariants().unwrap();
+}
+
+#[test]
+fn test_btree_loads_touched_pages() {
+    let file_name = "test_btree_loads_touched_pages.idx";
+    let storage = FileStorage::new(ROOT_DIR);
+    let mut btree = create_index_file(file_name, 4);
+    for key in 0..200 {
+        btree.insert(key, key as u64 * 2).unwrap();
+    }
+    btree.flush().unwrap();
+
+    let mut btree: BPlusTree<i64, u64> = BPlusTree::open(&storage, file_name).unwrap();
+    let loaded_nodes = |btree: &BPlusTree<i64, u64>| (0..1000).filter(|id| btree.get_node(*id).is_some()).count();
+    // only the root is read on open, a lookup reads one node per level
+    assert_eq!(loaded_nodes(&btree), 1);
+    assert_eq!(btree.search(&150).unwrap(), Some(300));
+    assert_eq!(loaded_nodes(&btree), btree.get_tree_height());
+    assert!(btree.get_tree_height() > 2);
+}
+
+#[test]
+fn test_btree_open_invalid_file() {
+    setup();
+    let storage = FileStorage::new(ROOT_DIR);
+    let file_name = "test_btree_open_invalid_file.idx";
+    storage.write_file(file_name, "not an index").unwrap();
+    assert!(BPlusTree::<i64, u64>::open(&storage, file_name).is_err());
+}
+
+#[test]
+fn test_btree_page_order() {
+    // nodes full of keys of the largest size fit in their pages
+    let key_size = 900;
+    let order = page_order(key_size, 20).unwrap();
+    assert!(order >= 2);
+    let file_name = "test_btree_page_order.idx";
+    setup();
+    if std::fs::metadata(format!("{}/{}", ROOT_DIR, file_name)).is_ok() {
+        std::fs::remove_file(format!("{}/{}", ROOT_DIR, file_name)).unwrap();
+    }
+    let mut btree: BPlusTree<String, u64> = BPlusTree::create(&FileStorage::new(ROOT_DIR), file_name, order).unwrap();
+    for key in 0..100 {
+        btree.insert(format!("{:03}{}", key, "x".repeat(key_size - 5)), u64::MAX).unwrap();
+    }
+    btree.flush().unwrap();
+    btree.check_invariants().unwrap();
+
+    // a page does not hold two keys of half its size
+    assert_eq!(page_order(INDEX_PAGE_SIZE / 2, 20), None);
+}
@@ -0,0 +1,159 @@
+use red::database::abstraction::{Column, DataType, Database, Query, Record, Table, Value, DDL, DML};
+use red::storage::buffer::BufferPool;
+use red::storage::files::FileStorage;
+use red::storage::persistence::DataHandler;
+
+mod common;
+use crate::common::{setup, ROOT_DIR};
+
+fn key(file_name: &str, offset: u64) -> (String, u64) {
+    (file_name.to_string(), offset)
+}
+
+// a storage in a directory of its own, so that its buffer pool is not shared with other tests
+fn create_storage(dir_name: &str) -> FileStorage {
+    setup();
+    let path = format!("{}/{}", ROOT_DIR, dir_name);
+    if std::fs::metadata(&path).is_ok() {
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+    std::fs::create_dir(&path).unwrap();
+    FileStorage::new(&path)
+}
+
+#[test]
+fn test_buffer_pool_lru_eviction() {
+    let mut pool = BufferPool::new(3 * 4);
+    for page in 0..3 {
+        assert!(pool.put(key("a", page * 4), vec![page as u8; 4], false).is_empty());
+    }
+    assert_eq!(pool.get_memory_used(), 12);
+
+    // reading page 0 makes page 4 the least recently used one
+    assert_eq!(pool.get(&key("a", 0)), Some(vec![0; 4]));
+    assert!(pool.put(key("a", 12), vec![3; 4], false).is_empty());
+    assert!(!pool.contains(&key("a", 4)));
+    assert!(pool.contains(&key("a", 0)));
+    assert_eq!(pool.get(&key("a", 4)), None);
+    assert_eq!((pool.get_hits(), pool.get_misses(), pool.get_evictions()), (1, 1, 1));
+
+    // a page larger than the budget is never cached
+    assert!(pool.put(key("b", 0), vec![0; 16], false).is_empty());
+    assert!(!pool.contains(&key("b", 0)));
+    assert_eq!(pool.put(key("b", 0), vec![0; 16], true), vec![(key("b", 0), vec![0; 16])]);
+}
+
+#[test]
+fn test_buffer_pool_dirty_pages() {
+    let mut pool = BufferPool::new(2 * 4);
+    pool.put(key("a", 0), vec![1; 4], true);
+    pool.put(key("a", 4), vec![2; 4], false);
+    // a clean copy keeps the page dirty
+    pool.put(key("a", 0), vec![1; 4], false);
+    assert!(pool.is_dirty(&key("a", 0)));
+    assert_eq!(pool.get_dirty_count(), 1);
+
+    // only dirty pages are handed back on eviction
+    assert_eq!(pool.put(key("a", 8), vec![3; 4], false), Vec::new());
+    assert_eq!(pool.put(key("a", 12), vec![4; 4], true), vec![(key("a", 0), vec![1; 4])]);
+
+    // a checkpoint takes the dirty pages in file order and leaves them clean
+    pool.put(key("a", 8), vec![5; 4], true);
+    assert_eq!(pool.take_dirty(), vec![(key("a", 8), vec![5; 4]), (key("a", 12), vec![4; 4])]);
+    assert_eq!(pool.get_dirty_count(), 0);
+
+    // shrinking the budget evicts pages
+    pool.put(key("a", 12), vec![6; 4], true);
+    assert_eq!(pool.set_memory_budget(4), Vec::new());
+    assert_eq!(pool.set_memory_budget(0), vec![(key("a", 12), vec![6; 4])]);
+    assert_eq!(pool.get_page_count(), 0);
+}
+
+#[test]
+fn test_storage_write_back() {
+    let storage = create_storage("test_storage_write_back");
+    storage.create_file("pages").unwrap();
+    storage.write_page("pages", 0, &[1; 8]).unwrap();
+    storage.write_page("pages", 8, &[2; 8]).unwrap();
+
+    // pages reach the file on checkpoint
+    assert!(storage.read_block("pages", 0, 16).is_err());
+    assert_eq!(storage.read_page("pages", 8, 8).unwrap(), vec![2; 8]);
+    storage.checkpoint().unwrap();
+    assert_eq!(storage.read_block("pages", 0, 16).unwrap(), [[1; 8], [2; 8]].concat());
+
+    // or when they are evicted, storages of the same directory share the pool
+    let other = FileStorage::new(storage.get_root_dir());
+    other.set_buffer_pool_budget(8).unwrap();
+    storage.write_page("pages", 0, &[3; 8]).unwrap();
+    assert_eq!(storage.read_block("pages", 0, 8).unwrap(), vec![1; 8]);
+    other.write_page("pages", 8, &[4; 8]).unwrap();
+    assert_eq!(storage.read_block("pages", 0, 8).unwrap(), vec![3; 8]);
+
+    // deleted files leave the pool
+    storage.delete_file("pages").unwrap();
+    assert_eq!(storage.get_buffer_pool().lock().unwrap().get_page_count(), 0);
+}
+
+#[test]
+fn test_hot_table_reads() {
+    let storage = create_storage("test_hot_table_reads");
+    let mut database = Database::new("customer", storage.clone());
+    let mut table = Table::new("users", Box::new(database.clone()));
+    table.add_column(Column::new("id", DataType::Integer, true, false).unwrap());
+    table.add_column(Column::new("name", DataType::Text(255), false, false).unwrap());
+    database.create_table(table.clone()).unwrap();
+    let mut data_handler = DataHandler::new_from_storage(storage.clone());
+    for id in 0..100 {
+        let values = vec![(table.get_columns()[0].clone(), Value::Integer(id)), (table.get_columns()[1].clone(), Value::Text(format!("user {}", id)))];
+        data_handler.insert(Record::new(table.clone(), values)).unwrap();
+    }
+
+    // every page written is clean once the statement returned
+    let buffer_pool = storage.get_buffer_pool();
+    assert_eq!(buffer_pool.lock().unwrap().get_dirty_count(), 0);
+
+    // a table read again comes from the pool
+    data_handler.select(Query::new("SELECT * FROM users")).unwrap();
+    buffer_pool.lock().unwrap().reset_counters();
+    let result = data_handler.select(Query::new("SELECT * FROM users WHERE id >= 50")).unwrap();
+    assert_eq!(result.get_records().len(), 50);
+    let pool = buffer_pool.lock().unwrap();
+    assert_eq!(pool.get_misses(), 0);
+    assert!(pool.get_hits() > 0);
+}
+
+#[test]
+fn test_cached_descriptors() {
+    let storage = create_storage("test_cached_descriptors");
+    let mut database = Database::new("customer", storage.clone());
+    let mut table = Table::new("users", Box::new(database.clone()));
+    table.add_column(Column::new("id", DataType::Integer, true, false).unwrap());
+    table.add_column(Column::new("name", DataType::Text(255), false, false).unwrap());
+    database.create_table(table.clone()).unwrap();
+    let data_handler = DataHandler::new_from_storage(storage.clone());
+
+    // a descriptor is parsed once, the pool keeps it along with the names of the files
+    data_handler.load_table_descriptor("users").unwrap();
+    let buffer_pool = storage.get_buffer_pool();
+    assert!(buffer_pool.lock().unwrap().get_parsed_file::<Table>("users.desc").is_some());
+    assert!(storage.list_files().unwrap().contains(&"users.desc".to_string()));
+    assert!(buffer_pool.lock().unwrap().get_file_names().is_some());
+
+    // a new content of the descriptor replaces the parsed one, until it is rolled back
+    let mut changed = table.clone();
+    changed.add_column(Column::new("email", DataType::Text(255), true, false).unwrap());
+    storage.write_file("users.desc", &serde_json::to_string(&changed).unwrap()).unwrap();
+    assert_eq!(data_handler.load_table_descriptor("users").unwrap().get_columns().len(), 3);
+    storage.rollback().unwrap();
+    assert_eq!(data_handler.load_table_descriptor("users").unwrap().get_columns().len(), 2);
+
+    // tables created and dropped are listed right away
+    let mut orders = Table::new("orders", Box::new(database.clone()));
+    orders.add_column(Column::new("id", DataType::Integer, true, false).unwrap());
+    database.create_table(orders.clone()).unwrap();
+    assert!(storage.list_files().unwrap().contains(&"orders.desc".to_string()));
+    database.drop_table(orders).unwrap();
+    assert!(!storage.list_files().unwrap().contains(&"orders.desc".to_string()));
+    assert!(data_handler.load_table_descriptor("orders").is_err());
+}
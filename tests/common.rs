@@ -1,6 +1,10 @@
+use red::storage::wal::{self, Durability};
+
 pub const ROOT_DIR: &str = "tests/workdir";
 
 pub fn setup() {
+    // test databases are thrown away, their writes are not synced
+    wal::set_default_durability(Durability::Off);
     if std::fs::metadata(ROOT_DIR).is_err() {
         let _ = std::fs::create_dir(ROOT_DIR);
     }
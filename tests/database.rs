@@ -1,8 +1,11 @@
 mod common;
 
-use red::database::abstraction::{Column, DataType, Database, DatabaseTrait, Record, Table, DDL, DML};
+use red::database::abstraction::{AlterTableOperation, Column, DataType, Database, DatabaseTrait, Query, Record, Table, Value, DDL, DML};
+use red::database::abstraction::{ColumnDefault, ForeignKey, Index, InsertResult, ReferentialAction};
+use red::database::parser::{self, Statement};
 use red::database::abstraction::RootDatabase;
-use red::storage::files::{FileStorage, TABLE_FILE_DATA_EXTENSION, TABLE_FILE_DESCRIPTOR_EXTENSION};
+use red::storage::files::{FileStorage, TABLE_FILE_DATA_EXTENSION, TABLE_FILE_DESCRIPTOR_EXTENSION, TABLE_FILE_INDEX_EXTENSION, TABLE_FILE_SEQUENCE_EXTENSION};
+use red::storage::heap::RowId;
 use red::storage::persistence::DataHandler;
 
 use crate::common::{setup, ROOT_DIR};
@@ -205,7 +208,7 @@ fn test_load_databases() {
     let db_name = "customer_04";
     let create_database = db_root.create_database(&db_name);
     assert!(create_database.is_ok());
-    assert!(std::fs::metadata(format!("{}/{}", ROOT_DIR, db_name)).is_ok());
+    assert!(std::fs::metadata(format!("{}/{}/{}", ROOT_DIR, root_path, db_name)).is_ok());
 
     let result = db_root.load_databases();
     assert!(result.is_ok());
@@ -227,7 +230,7 @@ fn test_insert_table_data() {
     let db_name = "customer_01";
     let create_database = db_root.create_database(&db_name);
     assert!(create_database.is_ok());
-    assert!(std::fs::metadata(format!("{}/{}", ROOT_DIR, db_name)).is_ok());
+    assert!(std::fs::metadata(format!("{}/{}/{}", ROOT_DIR, root_path, db_name)).is_ok());
 
     let mut database = create_database.unwrap();
     let mut new_table = Table::new("users", Box::new(database.clone()));
@@ -245,17 +248,935 @@ fn test_insert_table_data() {
     let columns_value = vec![
         (
             Column::new("id", DataType::Integer, true, false).unwrap(),
-            Some("7".to_string())
+            Value::Integer(7)
         ), 
         (
             Column::new("name", DataType::Text(255), false, false).unwrap(), 
-            Some("John Doe".to_string())
+            Value::Text("John Doe".to_string())
         )
     ];
     let new_record = Record::new(new_table.clone(), columns_value);
     let result = data_handler.insert(new_record);
     dbg!(&result);
     assert!(result.is_ok());
-    assert!(result.unwrap() == 1);
+    assert!(result.unwrap().get_affected_rows() == 1);
     
-}
\ No newline at end of file
+}
+
+// create a fresh database holding a users table (id, name, email)
+fn create_users_table(root_path: &str) -> (Table, DataHandler) {
+    setup();
+    if std::fs::metadata(format!("{}/{}", ROOT_DIR, root_path)).is_ok() {
+        std::fs::remove_dir_all(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    }
+    std::fs::create_dir(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+
+    let mut db_root = RootDatabase::new(&format!("{}/{}", ROOT_DIR, root_path));
+    let mut database = db_root.create_database("customer").unwrap();
+    let mut table = Table::new("users", Box::new(database.clone()));
+    table.add_column(Column::new("id", DataType::Integer, true, false).unwrap());
+    table.add_column(Column::new("name", DataType::Text(255), false, false).unwrap());
+    table.add_column(Column::new("email", DataType::Text(255), false, true).unwrap());
+    database.create_table(table.clone()).unwrap();
+
+    let data_handler = DataHandler::new_from_path(format!("{}/{}/customer", ROOT_DIR, root_path));
+    (table, data_handler)
+}
+
+fn open_customer_database(root_path: &str) -> Database {
+    let mut db_root = RootDatabase::new(&format!("{}/{}", ROOT_DIR, root_path));
+    db_root.load_databases().unwrap();
+    db_root.get_database("customer").unwrap().clone()
+}
+
+fn user_record(table: &Table, id: i64, name: &str, email: Option<&str>) -> Record {
+    Record::new(table.clone(), vec![
+        (table.get_column("id").unwrap().clone(), Value::Integer(id)),
+        (table.get_column("name").unwrap().clone(), Value::Text(name.to_string())),
+        (table.get_column("email").unwrap().clone(), email.map(|email| Value::Text(email.to_string())).unwrap_or(Value::Null)),
+    ])
+}
+
+#[test]
+fn test_select_table_data() {
+    let (table, mut data_handler) = create_users_table("db_root_select");
+    data_handler.insert(user_record(&table, 7, "John Doe", Some("john@doe.com"))).unwrap();
+    data_handler.insert(user_record(&table, 10, "Jane Doe", None)).unwrap();
+    data_handler.insert(user_record(&table, 12, "Jack Smith", Some("jack@smith.com"))).unwrap();
+
+    // no predicate returns every record with every column
+    let result = data_handler.select(Query::new("SELECT * FROM users")).unwrap();
+    assert_eq!(result.get_records().len(), 3);
+    assert_eq!(result.get_records()[0].get_values().len(), 3);
+
+    // integer comparisons use numeric ordering
+    let result = data_handler.select(Query::new("SELECT * FROM users WHERE id >= 8")).unwrap();
+    assert_eq!(result.get_records().len(), 2);
+
+    let result = data_handler.select(Query::new("SELECT * FROM users WHERE email IS NULL OR (id = 12 AND NOT name = 'John Doe')")).unwrap();
+    let ids: Vec<Value> = result.get_records().iter().map(|record| record.get_value("id").unwrap().1.clone()).collect();
+    assert_eq!(ids, vec![Value::Integer(10), Value::Integer(12)]);
+
+    // comparisons with NULL are unknown and never match
+    let result = data_handler.select(Query::new("SELECT * FROM users WHERE email <> 'john@doe.com'")).unwrap();
+    assert_eq!(result.get_records().len(), 1);
+
+    let result = data_handler.select(Query::new("SELECT * FROM users WHERE name LIKE '%doe' AND id IN (7, 12)")).unwrap();
+    assert_eq!(result.get_records().len(), 1);
+    let result = data_handler.select(Query::new("SELECT * FROM users WHERE name LIKE 'j_%_ %' AND name NOT LIKE '%h%'")).unwrap();
+    assert_eq!(result.get_records().len(), 1);
+
+    // many wildcards on a long value do not backtrack through every split of it
+    data_handler.insert(user_record(&table, 20, &"a".repeat(250), None)).unwrap();
+    let result = data_handler.select(Query::new("SELECT * FROM users WHERE name LIKE '%a%a%a%a%a%a%a%a%b'")).unwrap();
+    assert_eq!(result.get_records().len(), 0);
+    let result = data_handler.select(Query::new("SELECT * FROM users WHERE name LIKE '%a%a%a%a%a%a%a%a%'")).unwrap();
+    assert_eq!(result.get_records().len(), 1);
+}
+
+#[test]
+fn test_select_projection() {
+    let (table, mut data_handler) = create_users_table("db_root_select_projection");
+    data_handler.insert(user_record(&table, 7, "John Doe", Some("john@doe.com"))).unwrap();
+    data_handler.insert(user_record(&table, 10, "Jane Doe", None)).unwrap();
+
+    let result = data_handler.select(Query::new("SELECT name AS user_name, id FROM users ORDER BY id DESC LIMIT 1")).unwrap();
+    assert_eq!(result.get_records().len(), 1);
+    let values = result.get_records()[0].get_values();
+    assert_eq!(values.len(), 2);
+    assert_eq!(values[0].0.get_name(), "user_name");
+    assert_eq!(values[0].1, Value::Text("Jane Doe".to_string()));
+    assert_eq!(values[1].0.get_name(), "id");
+
+    // unknown columns and non SELECT queries are rejected
+    assert!(data_handler.select(Query::new("SELECT age FROM users")).is_err());
+    assert!(data_handler.select(Query::new("SELECT * FROM users WHERE age = 1")).is_err());
+    assert!(data_handler.select(Query::new("DELETE FROM users")).is_err());
+    assert!(data_handler.select(Query::new("SELECT * FROM unknown_table")).is_err());
+}
+
+#[test]
+fn test_update_table_data() {
+    let (table, mut data_handler) = create_users_table("db_root_update");
+    data_handler.insert(user_record(&table, 7, "John Doe", Some("john@doe.com"))).unwrap();
+    data_handler.insert(user_record(&table, 10, "Jane Doe", None)).unwrap();
+    data_handler.insert(user_record(&table, 12, "Jack Smith", None)).unwrap();
+
+    // record values are written on every matching row
+    let new_values = Record::new(table.clone(), vec![
+        (table.get_column("email").unwrap().clone(), Value::Text("unknown@doe.com".to_string())),
+    ]);
+    let result = data_handler.update(new_values, Query::new("SELECT * FROM users WHERE email IS NULL"));
+    assert_eq!(result.unwrap(), 2);
+    let result = data_handler.select(Query::new("SELECT * FROM users WHERE email = 'unknown@doe.com'")).unwrap();
+    assert_eq!(result.get_records().len(), 2);
+
+    // SET assignments of an UPDATE query are applied too
+    let no_values = Record::new(table.clone(), vec![]);
+    let result = data_handler.update(no_values.clone(), Query::new("UPDATE users SET name = name || ' Jr', id = id + 100 WHERE id = 7"));
+    assert_eq!(result.unwrap(), 1);
+    let result = data_handler.select(Query::new("SELECT name FROM users WHERE id = 107")).unwrap();
+    assert_eq!(result.get_records()[0].get_values()[0].1, Value::Text("John Doe Jr".to_string()));
+
+    // no matching row
+    let result = data_handler.update(no_values, Query::new("UPDATE users SET name = 'Nobody' WHERE id = 1"));
+    assert_eq!(result.unwrap(), 0);
+}
+
+#[test]
+fn test_update_table_data_constraints() {
+    let (table, mut data_handler) = create_users_table("db_root_update_constraints");
+    data_handler.insert(user_record(&table, 7, "John Doe", Some("john@doe.com"))).unwrap();
+
+    // not null column
+    let new_values = Record::new(table.clone(), vec![(table.get_column("name").unwrap().clone(), Value::Null)]);
+    assert!(data_handler.update(new_values, Query::new("SELECT * FROM users")).is_err());
+    let no_values = Record::new(table.clone(), vec![]);
+    assert!(data_handler.update(no_values.clone(), Query::new("UPDATE users SET name = NULL")).is_err());
+
+    // column type and unknown columns
+    let new_values = Record::new(table.clone(), vec![(Column::new("name", DataType::Integer, false, false).unwrap(), Value::Integer(1))]);
+    assert!(data_handler.update(new_values, Query::new("SELECT * FROM users")).is_err());
+    assert!(data_handler.update(no_values.clone(), Query::new("UPDATE users SET age = 1")).is_err());
+
+    // the failed updates left the row untouched
+    let result = data_handler.select(Query::new("SELECT * FROM users WHERE name = 'John Doe'")).unwrap();
+    assert_eq!(result.get_records().len(), 1);
+}
+
+#[test]
+fn test_delete_table_data() {
+    let (table, mut data_handler) = create_users_table("db_root_delete");
+    data_handler.insert(user_record(&table, 7, "John Doe", Some("john@doe.com"))).unwrap();
+    data_handler.insert(user_record(&table, 10, "Jane Doe", None)).unwrap();
+    data_handler.insert(user_record(&table, 12, "Jack Smith", None)).unwrap();
+
+    let result = data_handler.delete(Query::new("DELETE FROM users WHERE id = 7"));
+    assert_eq!(result.unwrap(), 1);
+    let result = data_handler.delete(Query::new("DELETE FROM users WHERE id = 7"));
+    assert_eq!(result.unwrap(), 0);
+    let result = data_handler.select(Query::new("SELECT * FROM users")).unwrap();
+    assert_eq!(result.get_records().len(), 2);
+
+    // a failing predicate leaves the table untouched
+    assert!(data_handler.delete(Query::new("DELETE FROM users WHERE age = 1")).is_err());
+    assert!(data_handler.delete(Query::new("UPDATE users SET id = 1")).is_err());
+
+    // no predicate removes every row
+    let result = data_handler.delete(Query::new("DELETE FROM users"));
+    assert_eq!(result.unwrap(), 2);
+    let result = data_handler.select(Query::new("SELECT * FROM users")).unwrap();
+    assert!(result.get_records().is_empty());
+}
+
+#[test]
+fn test_alter_table() {
+    let root_path = "db_root_alter";
+    let (table, mut data_handler) = create_users_table(root_path);
+    data_handler.insert(user_record(&table, 7, "John Doe", Some("john@doe.com"))).unwrap();
+    data_handler.insert(user_record(&table, 10, "Jane Doe", None)).unwrap();
+    let mut database = open_customer_database(root_path);
+
+    // add columns with and without default value
+    let result = database.alter_table(table.clone(), vec![
+        AlterTableOperation::AddColumn { column: Column::new("country", DataType::Text(2), false, false).unwrap(), default: Value::Text("FR".to_string()) },
+        AlterTableOperation::AddColumn { column: Column::new("age", DataType::Integer, false, true).unwrap(), default: Value::Null },
+    ]);
+    assert!(result.is_ok());
+    let table_description = data_handler.load_table_descriptor("users").unwrap();
+    assert_eq!(table_description.get_columns().len(), 5);
+    let result = data_handler.select(Query::new("SELECT * FROM users WHERE country = 'FR' AND age IS NULL")).unwrap();
+    assert_eq!(result.get_records().len(), 2);
+    assert_eq!(result.get_records()[0].get_values().len(), 5);
+
+    // drop and rename columns
+    let result = database.alter_table(table.clone(), vec![
+        AlterTableOperation::DropColumn("age".to_string()),
+        AlterTableOperation::RenameColumn { old_name: "name".to_string(), new_name: "full_name".to_string() },
+    ]);
+    assert!(result.is_ok());
+    let result = data_handler.select(Query::new("SELECT full_name FROM users WHERE id = 7")).unwrap();
+    assert_eq!(result.get_records()[0].get_values()[0].1, Value::Text("John Doe".to_string()));
+    assert!(data_handler.select(Query::new("SELECT age FROM users")).is_err());
+
+    // nullability can only be removed when no row holds a null value
+    let set_not_null = vec![AlterTableOperation::SetNullable { name: "email".to_string(), is_nullable: false }];
+    assert!(database.alter_table(table.clone(), set_not_null.clone()).is_err());
+    data_handler.update(Record::new(table.clone(), vec![]), Query::new("UPDATE users SET email = 'jane@doe.com' WHERE id = 10")).unwrap();
+    assert!(database.alter_table(table.clone(), set_not_null).is_ok());
+    assert!(!data_handler.load_table_descriptor("users").unwrap().get_column("email").unwrap().is_nullable());
+}
+
+#[test]
+fn test_alter_table_errors() {
+    let root_path = "db_root_alter_errors";
+    let (table, mut data_handler) = create_users_table(root_path);
+    data_handler.insert(user_record(&table, 7, "John Doe", None)).unwrap();
+    let mut root_database = RootDatabase::new(&format!("{}/{}/customer", ROOT_DIR, root_path));
+
+    // not null column without default on a table holding rows
+    let result = root_database.alter_table(table.clone(), vec![
+        AlterTableOperation::AddColumn { column: Column::new("age", DataType::Integer, false, false).unwrap(), default: Value::Null },
+    ]);
+    assert!(result.is_err());
+
+    // a failing operation discards the previous ones
+    let result = root_database.alter_table(table.clone(), vec![
+        AlterTableOperation::DropColumn("email".to_string()),
+        AlterTableOperation::RenameColumn { old_name: "unknown".to_string(), new_name: "other".to_string() },
+    ]);
+    assert!(result.is_err());
+    assert!(data_handler.load_table_descriptor("users").unwrap().get_column("email").is_some());
+
+    let result = root_database.alter_table(table.clone(), vec![
+        AlterTableOperation::RenameColumn { old_name: "name".to_string(), new_name: "id".to_string() },
+    ]);
+    assert!(result.is_err());
+    let result = root_database.alter_table(table.clone(), vec![
+        AlterTableOperation::SetNullable { name: "id".to_string(), is_nullable: true },
+    ]);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_typed_values() {
+    let root_path = "db_root_typed_values";
+    let (table, mut data_handler) = create_users_table(root_path);
+    let mut database = open_customer_database(root_path);
+    database.alter_table(table.clone(), vec![
+        AlterTableOperation::AddColumn { column: Column::new("score", DataType::Real, false, true).unwrap(), default: Value::Null },
+    ]).unwrap();
+    let table = data_handler.load_table_descriptor("users").unwrap();
+    let record = |id: Value, score: Value| Record::new(table.clone(), vec![
+        (table.get_column("id").unwrap().clone(), id),
+        (table.get_column("name").unwrap().clone(), Value::Text("John Doe".to_string())),
+        (table.get_column("email").unwrap().clone(), Value::Null),
+        (table.get_column("score").unwrap().clone(), score),
+    ]);
+
+    // values are parsed into the column type at insert time
+    assert!(data_handler.insert(record(Value::Text("abc".to_string()), Value::Null)).is_err());
+    assert!(data_handler.insert(record(Value::Integer(1), Value::Blob(vec![1, 2]))).is_err());
+    assert!(data_handler.insert(record(Value::Text("9".to_string()), Value::Integer(10))).is_ok());
+    assert!(data_handler.insert(record(Value::Integer(10), Value::Real(9.5))).is_ok());
+    assert!(data_handler.insert(record(Value::Integer(100), Value::Text("-1e3".to_string()))).is_ok());
+
+    let result = data_handler.select(Query::new("SELECT id, score FROM users WHERE id = 9")).unwrap();
+    assert_eq!(result.get_records()[0].get_values()[0].1, Value::Integer(9));
+    assert!(matches!(result.get_records()[0].get_values()[1].1, Value::Real(score) if score == 10.0));
+
+    // numeric ordering instead of text ordering
+    let result = data_handler.select(Query::new("SELECT id FROM users ORDER BY score")).unwrap();
+    let ids: Vec<Value> = result.get_records().iter().map(|record| record.get_values()[0].1.clone()).collect();
+    assert_eq!(ids, vec![Value::Integer(100), Value::Integer(10), Value::Integer(9)]);
+    let result = data_handler.select(Query::new("SELECT id FROM users WHERE id > 10 OR score < 9.6 ORDER BY id DESC")).unwrap();
+    let ids: Vec<Value> = result.get_records().iter().map(|record| record.get_values()[0].1.clone()).collect();
+    assert_eq!(ids, vec![Value::Integer(100), Value::Integer(10)]);
+
+    // update values are checked too
+    let no_values = Record::new(table.clone(), vec![]);
+    assert!(data_handler.update(no_values, Query::new("UPDATE users SET score = 'high'")).is_err());
+
+    // integers and reals compare exactly, beyond the integers a real holds
+    let large = 1_i64 << 53;
+    assert_eq!(Value::Integer(large), Value::Real(large as f64));
+    assert!(Value::Integer(large + 1) > Value::Real(large as f64));
+    assert!(Value::Real(large as f64) < Value::Integer(large + 1));
+    assert!(Value::Integer(i64::MAX) < Value::Real(i64::MAX as f64));
+    assert!(Value::Integer(i64::MIN) == Value::Real(i64::MIN as f64));
+    assert!(Value::Integer(-3) < Value::Real(-2.5));
+    assert!(Value::Integer(-2) > Value::Real(-2.5));
+    assert!(Value::Integer(0) > Value::Real(-0.0));
+    assert!(Value::Integer(i64::MAX) < Value::Real(f64::INFINITY));
+}
+
+#[test]
+fn test_load_untyped_table_data() {
+    // rows written before typed values stored every value as text
+    let (table, data_handler) = create_users_table("db_root_untyped_values");
+    let column = |name: &str| serde_json::to_string(table.get_column(name).unwrap()).unwrap();
+    let content = format!(
+        "[{{\"values\": [[{}, \"10\"], [{}, \"Jane Doe\"], [{}, null]]}}, {{\"values\": [[{}, \"9\"], [{}, \"John Doe\"], [{}, null]]}}]",
+        column("id"), column("name"), column("email"), column("id"), column("name"), column("email")
+    );
+    // the file is replaced through the storage, which drops the pages it cached
+    FileStorage::new(&format!("{}/db_root_untyped_values/customer", ROOT_DIR)).write_file("users.data", &content).unwrap();
+
+    let result = data_handler.select(Query::new("SELECT id FROM users WHERE id < 10")).unwrap();
+    assert_eq!(result.get_records().len(), 1);
+    assert_eq!(result.get_records()[0].get_values()[0].1, Value::Integer(9));
+
+    // the JSON data file was migrated to a heap file on first read
+    let data = std::fs::read(format!("{}/db_root_untyped_values/customer/users.data", ROOT_DIR)).unwrap();
+    assert!(data.starts_with(b"REDHEAP2"));
+    assert!(!data_handler.migrate_table_data(&table).unwrap());
+    let result = data_handler.select(Query::new("SELECT * FROM users ORDER BY id")).unwrap();
+    assert_eq!(result.get_records().len(), 2);
+    assert_eq!(result.get_records()[1].get_values()[1].1, Value::Text("Jane Doe".to_string()));
+}
+
+#[test]
+fn test_primary_key_uniqueness() {
+    let (table, mut data_handler) = create_users_table("db_root_primary_key");
+    data_handler.insert(user_record(&table, 7, "John Doe", None)).unwrap();
+    data_handler.insert(user_record(&table, 10, "Jane Doe", None)).unwrap();
+
+    // same id with a different name
+    let result = data_handler.insert(user_record(&table, 7, "Jack Smith", None));
+    let error = result.unwrap_err().to_string();
+    assert!(error.contains("Primary key constraint violation"));
+    assert!(error.contains("(id) = (7)"));
+
+    // updates cannot create duplicate keys either
+    let no_values = Record::new(table.clone(), vec![]);
+    assert!(data_handler.update(no_values.clone(), Query::new("UPDATE users SET id = 7 WHERE id = 10")).is_err());
+    assert!(data_handler.update(no_values.clone(), Query::new("UPDATE users SET id = 1")).is_err());
+    assert_eq!(data_handler.update(no_values, Query::new("UPDATE users SET id = id + 1")).unwrap(), 2);
+    let result = data_handler.select(Query::new("SELECT * FROM users")).unwrap();
+    assert_eq!(result.get_records().len(), 2);
+}
+
+#[test]
+fn test_composite_primary_key() {
+    setup();
+    let root_path = "db_root_composite_key";
+    if std::fs::metadata(format!("{}/{}", ROOT_DIR, root_path)).is_ok() {
+        std::fs::remove_dir_all(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    }
+    std::fs::create_dir(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    let mut db_root = RootDatabase::new(&format!("{}/{}", ROOT_DIR, root_path));
+    let mut database = db_root.create_database("customer").unwrap();
+    let mut table = Table::new("order_lines", Box::new(database.clone()));
+    table.add_column(Column::new("order_id", DataType::Integer, true, false).unwrap());
+    table.add_column(Column::new("line", DataType::Integer, true, false).unwrap());
+    table.add_column(Column::new("product", DataType::Text(64), false, false).unwrap());
+    database.create_table(table.clone()).unwrap();
+    assert_eq!(table.get_primary_key().len(), 2);
+
+    let mut data_handler = DataHandler::new_from_path(format!("{}/{}/customer", ROOT_DIR, root_path));
+    let line = |order_id: i64, line: i64, product: &str| Record::new(table.clone(), vec![
+        (table.get_columns()[0].clone(), Value::Integer(order_id)),
+        (table.get_columns()[1].clone(), Value::Integer(line)),
+        (table.get_columns()[2].clone(), Value::Text(product.to_string())),
+    ]);
+    assert!(data_handler.insert(line(1, 1, "apple")).is_ok());
+    assert!(data_handler.insert(line(1, 2, "apple")).is_ok());
+    assert!(data_handler.insert(line(2, 1, "pear")).is_ok());
+    let error = data_handler.insert(line(1, 2, "pear")).unwrap_err().to_string();
+    assert!(error.contains("(order_id, line) = (1, 2)"));
+}
+
+#[test]
+fn test_column_constraints() {
+    setup();
+    let root_path = "db_root_column_constraints";
+    if std::fs::metadata(format!("{}/{}", ROOT_DIR, root_path)).is_ok() {
+        std::fs::remove_dir_all(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    }
+    std::fs::create_dir(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    let mut db_root = RootDatabase::new(&format!("{}/{}", ROOT_DIR, root_path));
+    let mut database = db_root.create_database("customer").unwrap();
+    let statement = parser::parse_statement(
+        "CREATE TABLE accounts (
+            id INTEGER PRIMARY KEY,
+            email TEXT(255) UNIQUE,
+            status TEXT(16) NOT NULL DEFAULT 'active',
+            balance INTEGER DEFAULT (10 * 10) CHECK (balance >= 0)
+        )").unwrap();
+    let columns = match statement {
+        Statement::CreateTable { columns, .. } => columns,
+        _ => panic!("Expected a create table statement")
+    };
+    let mut table = Table::new("accounts", Box::new(database.clone()));
+    for definition in &columns {
+        table.add_column(Column::from_definition(definition).unwrap());
+    }
+    database.create_table(table.clone()).unwrap();
+
+    // constraints survive the descriptor round trip
+    let mut data_handler = DataHandler::new_from_path(format!("{}/{}/customer", ROOT_DIR, root_path));
+    let loaded = data_handler.load_table_descriptor("accounts").unwrap();
+    assert!(loaded.get_column("email").unwrap().is_unique());
+    assert_eq!(loaded.get_column("status").unwrap().get_default(), Some(&ColumnDefault::Literal(Value::Text("active".to_string()))));
+    assert_eq!(loaded.get_column("balance").unwrap().get_check(), Some("balance >= 0"));
+
+    // missing columns take their default value
+    let account = |id: i64, email: Option<&str>| {
+        let mut values = vec![(table.get_columns()[0].clone(), Value::Integer(id))];
+        if let Some(email) = email {
+            values.push((table.get_columns()[1].clone(), Value::Text(email.to_string())));
+        }
+        Record::new(table.clone(), values)
+    };
+    data_handler.insert(account(1, Some("john@doe.com"))).unwrap();
+    let result = data_handler.select(Query::new("SELECT status, balance FROM accounts WHERE id = 1")).unwrap();
+    let record = &result.get_records()[0];
+    assert_eq!(record.get_value("status").unwrap().1, Value::Text("active".to_string()));
+    assert_eq!(record.get_value("balance").unwrap().1, Value::Integer(100));
+
+    // unique values may repeat only when NULL
+    let error = data_handler.insert(account(2, Some("john@doe.com"))).unwrap_err().to_string();
+    assert!(error.contains("Unique constraint violation"));
+    assert!(error.contains("(email) = ('john@doe.com')"));
+    data_handler.insert(account(2, None)).unwrap();
+    data_handler.insert(account(3, None)).unwrap();
+
+    // check constraints apply to inserts and updates
+    let mut negative = account(4, None);
+    negative.set_values(vec![
+        (table.get_columns()[0].clone(), Value::Integer(4)),
+        (table.get_columns()[3].clone(), Value::Integer(-5)),
+    ]);
+    let error = data_handler.insert(negative).unwrap_err().to_string();
+    assert_eq!(error, "Check constraint violation on column balance: balance >= 0");
+    let no_values = Record::new(table.clone(), vec![]);
+    assert!(data_handler.update(no_values.clone(), Query::new("UPDATE accounts SET balance = balance - 150 WHERE id = 1")).is_err());
+    assert_eq!(data_handler.update(no_values.clone(), Query::new("UPDATE accounts SET balance = NULL WHERE id = 1")).unwrap(), 1);
+    assert!(data_handler.update(no_values, Query::new("UPDATE accounts SET email = 'jane@doe.com'")).is_err());
+}
+
+#[test]
+fn test_text_length() {
+    let (table, mut data_handler) = create_users_table("db_root_text_length");
+    assert!(!data_handler.is_truncating_text());
+    // the limit counts characters, 255 multi-byte characters fit
+    let name = "é".repeat(255);
+    data_handler.insert(user_record(&table, 1, &name, None)).unwrap();
+
+    let long_name = "é".repeat(256);
+    let error = data_handler.insert(user_record(&table, 2, &long_name, None)).unwrap_err().to_string();
+    assert_eq!(error, "Value too long for column name: maximum length is 255 characters");
+    let no_values = Record::new(table.clone(), vec![]);
+    let query = format!("UPDATE users SET email = '{}' WHERE id = 1", "a".repeat(300));
+    assert!(data_handler.update(no_values.clone(), Query::new(&query)).is_err());
+
+    data_handler.set_truncate_text(true);
+    data_handler.insert(user_record(&table, 2, &long_name, None)).unwrap();
+    assert_eq!(data_handler.update(no_values, Query::new(&query)).unwrap(), 1);
+    let result = data_handler.select(Query::new("SELECT * FROM users ORDER BY id")).unwrap();
+    assert_eq!(result.get_records()[0].get_value("email").unwrap().1, Value::Text("a".repeat(255)));
+    assert_eq!(result.get_records()[1].get_value("name").unwrap().1, Value::Text(name));
+}
+
+fn create_table_from_sql(database: &mut Database, sql: &str) -> Table {
+    let (table_name, columns) = match parser::parse_statement(sql).unwrap() {
+        Statement::CreateTable { table, columns, .. } => (table, columns),
+        _ => panic!("Expected a create table statement")
+    };
+    let mut table = Table::new(&table_name, Box::new(database.clone()));
+    for definition in &columns {
+        table.add_column(Column::from_definition(definition).unwrap());
+    }
+    database.create_table(table.clone()).unwrap();
+    table
+}
+
+fn insert_values(data_handler: &mut DataHandler, table: &Table, values: Vec<Value>) -> Result<InsertResult, Box<dyn std::error::Error>> {
+    let values = table.get_columns().iter().cloned().zip(values).collect();
+    data_handler.insert(Record::new(table.clone(), values))
+}
+
+#[test]
+fn test_foreign_keys() {
+    setup();
+    let root_path = "db_root_foreign_keys";
+    if std::fs::metadata(format!("{}/{}", ROOT_DIR, root_path)).is_ok() {
+        std::fs::remove_dir_all(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    }
+    std::fs::create_dir(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    let mut db_root = RootDatabase::new(&format!("{}/{}", ROOT_DIR, root_path));
+    let mut database = db_root.create_database("customer").unwrap();
+    let customers = create_table_from_sql(&mut database, "CREATE TABLE customers (id INTEGER PRIMARY KEY, name TEXT(64))");
+    let orders = create_table_from_sql(&mut database,
+        "CREATE TABLE orders (id INTEGER PRIMARY KEY, customer_id INTEGER NOT NULL REFERENCES customers ON DELETE CASCADE)");
+    let order_lines = create_table_from_sql(&mut database,
+        "CREATE TABLE order_lines (id INTEGER PRIMARY KEY, order_id INTEGER REFERENCES orders (id))");
+    let reviews = create_table_from_sql(&mut database,
+        "CREATE TABLE reviews (id INTEGER PRIMARY KEY, customer_id INTEGER REFERENCES customers (id) ON DELETE SET NULL)");
+
+    // references must target a key of an existing table
+    let mut invalid = Table::new("invalid", Box::new(database.clone()));
+    let mut column = Column::new("name", DataType::Text(64), false, true).unwrap();
+    column.set_references(Some(ForeignKey::new("customers", Some("name"), ReferentialAction::Restrict)));
+    invalid.add_column(column);
+    assert!(database.create_table(invalid.clone()).is_err());
+    let mut columns = invalid.get_columns().clone();
+    columns[0].set_references(Some(ForeignKey::new("suppliers", None, ReferentialAction::Restrict)));
+    invalid.set_columns(columns);
+    assert!(database.create_table(invalid).is_err());
+
+    let mut data_handler = DataHandler::new_from_path(format!("{}/{}/customer", ROOT_DIR, root_path));
+    let loaded = data_handler.load_table_descriptor("orders").unwrap();
+    assert_eq!(loaded.get_column("customer_id").unwrap().get_references(), Some(&ForeignKey::new("customers", None, ReferentialAction::Cascade)));
+
+    for id in 1..=3 {
+        insert_values(&mut data_handler, &customers, vec![Value::Integer(id), Value::Text(format!("customer {}", id))]).unwrap();
+    }
+    insert_values(&mut data_handler, &orders, vec![Value::Integer(10), Value::Integer(1)]).unwrap();
+    insert_values(&mut data_handler, &orders, vec![Value::Integer(20), Value::Integer(2)]).unwrap();
+    insert_values(&mut data_handler, &order_lines, vec![Value::Integer(100), Value::Integer(20)]).unwrap();
+    insert_values(&mut data_handler, &reviews, vec![Value::Integer(1000), Value::Integer(3)]).unwrap();
+    insert_values(&mut data_handler, &reviews, vec![Value::Integer(1001), Value::Null]).unwrap();
+
+    // insert and update must reference an existing row
+    let error = insert_values(&mut data_handler, &orders, vec![Value::Integer(30), Value::Integer(9)]).unwrap_err().to_string();
+    assert_eq!(error, "Foreign key constraint violation on column customer_id: 9 not found in customers.id");
+    let no_values = Record::new(orders.clone(), vec![]);
+    assert!(data_handler.update(no_values.clone(), Query::new("UPDATE orders SET customer_id = 9 WHERE id = 10")).is_err());
+    assert_eq!(data_handler.update(no_values, Query::new("UPDATE orders SET customer_id = 3 WHERE id = 10")).unwrap(), 1);
+    // referenced keys cannot change under the rows referencing them
+    let no_values = Record::new(customers.clone(), vec![]);
+    assert!(data_handler.update(no_values, Query::new("UPDATE customers SET id = 4 WHERE id = 2")).is_err());
+
+    // ON DELETE RESTRICT through the cascade from customers to orders to order lines
+    let error = data_handler.delete(Query::new("DELETE FROM customers WHERE id = 2")).unwrap_err().to_string();
+    assert!(error.contains("order_lines.order_id"));
+    assert_eq!(data_handler.select(Query::new("SELECT * FROM orders")).unwrap().get_records().len(), 2);
+    assert_eq!(data_handler.delete(Query::new("DELETE FROM order_lines")).unwrap(), 1);
+
+    // ON DELETE CASCADE and SET NULL
+    assert_eq!(data_handler.delete(Query::new("DELETE FROM customers WHERE id >= 2")).unwrap(), 2);
+    let result = data_handler.select(Query::new("SELECT * FROM orders")).unwrap();
+    assert_eq!(result.get_records().len(), 0);
+    let result = data_handler.select(Query::new("SELECT * FROM reviews WHERE customer_id IS NULL")).unwrap();
+    assert_eq!(result.get_records().len(), 2);
+
+    // referenced tables and columns cannot be dropped
+    assert!(database.drop_table(customers.clone()).is_err());
+    let error = data_handler.alter_table("customers", &[AlterTableOperation::DropColumn("id".to_string())]).unwrap_err();
+    assert_eq!(error.to_string(), "Column id is referenced by orders.customer_id");
+    database.drop_table(order_lines).unwrap();
+    database.drop_table(orders).unwrap();
+    database.drop_table(reviews).unwrap();
+    database.drop_table(customers).unwrap();
+}
+
+#[test]
+fn test_self_referencing_foreign_key() {
+    setup();
+    let root_path = "db_root_self_reference";
+    if std::fs::metadata(format!("{}/{}", ROOT_DIR, root_path)).is_ok() {
+        std::fs::remove_dir_all(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    }
+    std::fs::create_dir(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    let mut db_root = RootDatabase::new(&format!("{}/{}", ROOT_DIR, root_path));
+    let mut database = db_root.create_database("customer").unwrap();
+    let employees = create_table_from_sql(&mut database,
+        "CREATE TABLE employees (id INTEGER PRIMARY KEY, manager_id INTEGER REFERENCES employees ON DELETE CASCADE)");
+
+    let mut data_handler = DataHandler::new_from_path(format!("{}/{}/customer", ROOT_DIR, root_path));
+    insert_values(&mut data_handler, &employees, vec![Value::Integer(1), Value::Null]).unwrap();
+    insert_values(&mut data_handler, &employees, vec![Value::Integer(2), Value::Integer(1)]).unwrap();
+    insert_values(&mut data_handler, &employees, vec![Value::Integer(3), Value::Integer(2)]).unwrap();
+    insert_values(&mut data_handler, &employees, vec![Value::Integer(4), Value::Integer(4)]).unwrap();
+    assert!(insert_values(&mut data_handler, &employees, vec![Value::Integer(5), Value::Integer(6)]).is_err());
+
+    // deleting the top manager removes the whole chain
+    assert_eq!(data_handler.delete(Query::new("DELETE FROM employees WHERE id = 1")).unwrap(), 1);
+    let result = data_handler.select(Query::new("SELECT id FROM employees")).unwrap();
+    assert_eq!(result.get_records().len(), 1);
+}
+
+#[test]
+fn test_auto_increment() {
+    setup();
+    let root_path = "db_root_auto_increment";
+    if std::fs::metadata(format!("{}/{}", ROOT_DIR, root_path)).is_ok() {
+        std::fs::remove_dir_all(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    }
+    std::fs::create_dir(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    let mut db_root = RootDatabase::new(&format!("{}/{}", ROOT_DIR, root_path));
+    let mut database = db_root.create_database("customer").unwrap();
+    let customers = create_table_from_sql(&mut database, "CREATE TABLE customers (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT(64))");
+    assert!(customers.get_columns()[0].is_auto_increment());
+    let mut column = Column::new("name", DataType::Text(64), false, true).unwrap();
+    assert!(column.set_auto_increment(true).is_err());
+
+    let mut data_handler = DataHandler::new_from_path(format!("{}/{}/customer", ROOT_DIR, root_path));
+    let customer = |name: &str| Record::new(customers.clone(), vec![
+        (customers.get_columns()[1].clone(), Value::Text(name.to_string()))
+    ]);
+    assert_eq!(data_handler.insert(customer("John")).unwrap(), InsertResult::new(1, Some(1)));
+    assert_eq!(data_handler.insert(customer("Jane")).unwrap().get_last_insert_id(), Some(2));
+    // explicit ids are kept and move the sequence forward
+    assert_eq!(insert_values(&mut data_handler, &customers, vec![Value::Integer(10), Value::Text("Jack".to_string())]).unwrap().get_last_insert_id(), Some(10));
+    assert_eq!(insert_values(&mut data_handler, &customers, vec![Value::Null, Value::Text("Jill".to_string())]).unwrap().get_last_insert_id(), Some(11));
+    assert!(insert_values(&mut data_handler, &customers, vec![Value::Integer(2), Value::Text("Jim".to_string())]).is_err());
+
+    // deleted ids are not reused, the counter lives next to the descriptor
+    data_handler.delete(Query::new("DELETE FROM customers WHERE id = 11")).unwrap();
+    let sequence_file = format!("{}/{}/customer/customers.{}", ROOT_DIR, root_path, TABLE_FILE_SEQUENCE_EXTENSION);
+    assert_eq!(std::fs::read_to_string(&sequence_file).unwrap(), "11");
+    let mut data_handler = DataHandler::new_from_path(format!("{}/{}/customer", ROOT_DIR, root_path));
+    assert_eq!(data_handler.insert(customer("Joe")).unwrap().get_last_insert_id(), Some(12));
+
+    database.drop_table(customers).unwrap();
+    assert!(std::fs::metadata(&sequence_file).is_err());
+}
+
+fn create_index_from_sql(database: &mut Database, table: &Table, sql: &str) {
+    let index = match parser::parse_statement(sql).unwrap() {
+        Statement::CreateIndex { name, columns, .. } => Index::new(&name, &columns.iter().map(|column| column.as_str()).collect::<Vec<&str>>()),
+        _ => panic!("Expected a create index statement")
+    };
+    database.create_index(table.clone(), index).unwrap();
+}
+
+// (indexed values, row position) entries of an index in key order
+fn index_entries(data_handler: &DataHandler, index_name: &str) -> Vec<(Vec<Value>, RowId)> {
+    let table = data_handler.load_index_table(index_name).unwrap().unwrap();
+    let mut tree = data_handler.load_index(table.get_index(index_name).unwrap()).unwrap();
+    tree.iter()
+        .map(|entry| entry.unwrap())
+        .map(|(mut key, row)| {
+            key.pop();
+            (key, RowId::from_u64(row))
+        })
+        .collect()
+}
+
+#[test]
+fn test_indexes() {
+    setup();
+    let root_path = "db_root_indexes";
+    if std::fs::metadata(format!("{}/{}", ROOT_DIR, root_path)).is_ok() {
+        std::fs::remove_dir_all(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    }
+    std::fs::create_dir(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    let mut db_root = RootDatabase::new(&format!("{}/{}", ROOT_DIR, root_path));
+    let mut database = db_root.create_database("customer").unwrap();
+    let customers = create_table_from_sql(&mut database, "CREATE TABLE customers (id INTEGER PRIMARY KEY, name TEXT(64), city TEXT(64))");
+    let mut data_handler = DataHandler::new_from_path(format!("{}/{}/customer", ROOT_DIR, root_path));
+    let text = |value: &str| Value::Text(value.to_string());
+    for (id, name, city) in [(1, "John", "Paris"), (2, "Jane", "Lyon"), (3, "Jack", "Paris")] {
+        insert_values(&mut data_handler, &customers, vec![Value::Integer(id), text(name), text(city)]).unwrap();
+    }
+
+    // existing rows are indexed when the index is created, the file sits next to the table files
+    create_index_from_sql(&mut database, &customers, "CREATE INDEX customers_city ON customers (city, name)");
+    let index_file = format!("{}/{}/customer/customers_city.{}", ROOT_DIR, root_path, TABLE_FILE_INDEX_EXTENSION);
+    assert!(std::fs::metadata(&index_file).is_ok());
+    // the primary key got its index along with the table
+    let mut primary_key = Index::new("customers_pkey", &["id"]);
+    primary_key.set_constraint(true);
+    assert_eq!(data_handler.load_table_descriptor("customers").unwrap().get_indexes(), &vec![primary_key.clone(), Index::new("customers_city", &["city", "name"])]);
+    // rows of a table are stored in the first page of its data file
+    let row = |slot| RowId::new(1, slot);
+    assert_eq!(index_entries(&data_handler, "customers_city"), vec![
+        (vec![text("Lyon"), text("Jane")], row(1)),
+        (vec![text("Paris"), text("Jack")], row(2)),
+        (vec![text("Paris"), text("John")], row(0)),
+    ]);
+    assert!(database.create_index(customers.clone(), Index::new("customers_city", &["name"])).is_err());
+    assert!(database.create_index(customers.clone(), Index::new("customers_age", &["age"])).is_err());
+
+    // every change to the rows is applied to the index, a changed row gets a new version with a new id.
+    // The entries of the old versions are removed along with the versions
+    insert_values(&mut data_handler, &customers, vec![Value::Integer(4), text("Jill"), text("Lyon")]).unwrap();
+    data_handler.update(Record::new(customers.clone(), Vec::new()), Query::new("UPDATE customers SET city = 'Nice' WHERE id = 1")).unwrap();
+    data_handler.delete(Query::new("DELETE FROM customers WHERE id = 2")).unwrap();
+    assert_eq!(index_entries(&data_handler, "customers_city").len(), 4);
+    assert_eq!(data_handler.vacuum("customers").unwrap(), 1);
+    assert_eq!(index_entries(&data_handler, "customers_city"), vec![
+        (vec![text("Lyon"), text("Jill")], row(3)),
+        (vec![text("Nice"), text("John")], row(4)),
+        (vec![text("Paris"), text("Jack")], row(2)),
+    ]);
+
+    // an indexed column can be renamed but not dropped
+    let customers = data_handler.alter_table("customers", &[AlterTableOperation::RenameColumn { old_name: "city".to_string(), new_name: "town".to_string() }]).unwrap();
+    assert_eq!(customers.get_index("customers_city").unwrap().get_columns(), &vec!["town".to_string(), "name".to_string()]);
+    assert!(data_handler.alter_table("customers", &[AlterTableOperation::DropColumn("town".to_string())]).is_err());
+    assert_eq!(index_entries(&data_handler, "customers_city").len(), 3);
+
+    database.drop_index("customers_city").unwrap();
+    assert!(std::fs::metadata(&index_file).is_err());
+    assert_eq!(data_handler.load_table_descriptor("customers").unwrap().get_indexes(), &vec![primary_key]);
+    assert!(database.drop_index("customers_city").is_err());
+    assert!(database.drop_index("customers_pkey").is_err());
+
+    create_index_from_sql(&mut database, &customers, "CREATE INDEX customers_name ON customers (name)");
+    database.drop_table(customers).unwrap();
+    assert!(std::fs::metadata(format!("{}/{}/customer/customers_name.{}", ROOT_DIR, root_path, TABLE_FILE_INDEX_EXTENSION)).is_err());
+}
+
+#[test]
+fn test_select_with_index() {
+    setup();
+    let root_path = "db_root_select_with_index";
+    if std::fs::metadata(format!("{}/{}", ROOT_DIR, root_path)).is_ok() {
+        std::fs::remove_dir_all(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    }
+    std::fs::create_dir(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    let mut db_root = RootDatabase::new(&format!("{}/{}", ROOT_DIR, root_path));
+    let mut database = db_root.create_database("customer").unwrap();
+    let orders = create_table_from_sql(&mut database, "CREATE TABLE orders (id INTEGER PRIMARY KEY, customer TEXT(16), amount REAL)");
+    let mut data_handler = DataHandler::new_from_path(format!("{}/{}/customer", ROOT_DIR, root_path));
+    for id in 0..60 {
+        let customer = Value::Text(format!("c{}", id % 7));
+        insert_values(&mut data_handler, &orders, vec![Value::Integer(id), customer, Value::Real((id * 13 % 50) as f64)]).unwrap();
+    }
+    let queries = [
+        "SELECT id FROM orders WHERE customer = 'c3'",
+        "SELECT id FROM orders WHERE customer = 'c3' AND amount >= 20",
+        "SELECT id, amount FROM orders WHERE amount BETWEEN 10 AND 20.5 ORDER BY amount DESC, id LIMIT 4",
+        "SELECT id FROM orders WHERE 40 < amount AND customer > 'c4'",
+        "SELECT id FROM orders WHERE customer = 'c9'",
+    ];
+    let select_all = |data_handler: &DataHandler| -> Vec<Vec<Vec<Value>>> {
+        queries.iter()
+            .map(|sql| data_handler.select(Query::new(sql)).unwrap().get_records().iter()
+                .map(|record| record.get_values().iter().map(|(_, value)| value.clone()).collect())
+                .collect())
+            .collect()
+    };
+
+    // index scans return the rows a sequential scan returns
+    let expected = select_all(&data_handler);
+    let explain = |data_handler: &DataHandler, sql: &str| -> Vec<String> {
+        data_handler.select(Query::new(&format!("EXPLAIN {}", sql))).unwrap().get_records().iter()
+            .map(|record| match &record.get_value("plan").unwrap().1 {
+                Value::Text(line) => line.clone(),
+                value => panic!("Expected a text plan line, found {}", value),
+            })
+            .collect()
+    };
+    assert_eq!(explain(&data_handler, queries[0]), vec![
+        "Project id (rows=6)",
+        "  SeqScan on orders (rows=6)",
+        "    Filter: customer = 'c3'",
+    ]);
+    create_index_from_sql(&mut database, &orders, "CREATE INDEX orders_customer ON orders (customer, amount)");
+    create_index_from_sql(&mut database, &orders, "CREATE INDEX orders_amount ON orders (amount)");
+    assert_eq!(select_all(&data_handler), expected);
+    assert_eq!(explain(&data_handler, queries[1]), vec![
+        "Project id (rows=2)",
+        "  IndexScan using orders_customer on orders (rows=2)",
+        "    Index Cond: customer = 'c3' AND amount >= 20",
+    ]);
+    assert_eq!(explain(&data_handler, queries[2])[3], "      IndexScan using orders_amount on orders (rows=7)");
+    assert!(data_handler.select(Query::new("EXPLAIN DELETE FROM orders")).is_err());
+
+    // the rows found through the index follow the changes to the table, the new version of the updated
+    // row takes the room of the deleted rows
+    data_handler.delete(Query::new("DELETE FROM orders WHERE id < 10")).unwrap();
+    data_handler.update(Record::new(orders.clone(), Vec::new()), Query::new("UPDATE orders SET customer = 'c3' WHERE id = 20")).unwrap();
+    let records = data_handler.select(Query::new(queries[0])).unwrap();
+    let ids: Vec<String> = records.get_records().iter().map(|record| record.get_values()[0].1.to_string()).collect();
+    assert_eq!(ids, vec!["20", "10", "17", "24", "31", "38", "45", "52", "59"]);
+}
+
+#[test]
+fn test_index_on_wide_text() {
+    setup();
+    let root_path = "db_root_index_on_wide_text";
+    if std::fs::metadata(format!("{}/{}", ROOT_DIR, root_path)).is_ok() {
+        std::fs::remove_dir_all(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    }
+    std::fs::create_dir(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    let mut db_root = RootDatabase::new(&format!("{}/{}", ROOT_DIR, root_path));
+    let mut database = db_root.create_database("customer").unwrap();
+    let notes = create_table_from_sql(&mut database, "CREATE TABLE notes (id INTEGER PRIMARY KEY, title TEXT(255), summary TEXT(1000), body BLOB)");
+    let mut data_handler = DataHandler::new_from_path(format!("{}/{}/customer", ROOT_DIR, root_path));
+    create_index_from_sql(&mut database, &notes, "CREATE INDEX notes_title ON notes (title)");
+
+    // the nodes of the index hold as many keys as fit in a page, control characters take the most room once encoded
+    let title = |id: i64, filler: &str| Value::Text(format!("{:05}{}", id, filler.repeat(250)));
+    for id in 0..40 {
+        let filler = if id % 2 == 0 { "x" } else { "\u{1}" };
+        insert_values(&mut data_handler, &notes, vec![Value::Integer(id), title(id, filler), Value::Null, Value::Null]).unwrap();
+    }
+    let entries = index_entries(&data_handler, "notes_title");
+    assert_eq!(entries.len(), 40);
+    assert_eq!(entries[1].0, vec![title(1, "\u{1}")]);
+    let table = data_handler.load_index_table("notes_title").unwrap().unwrap();
+    let mut tree = data_handler.load_index(table.get_index("notes_title").unwrap()).unwrap();
+    assert!(tree.get_order() >= 2);
+    tree.check_invariants().unwrap();
+
+    // values without a bound or wider than a page allows cannot be indexed
+    assert!(database.create_index(notes.clone(), Index::new("notes_body", &["body"])).is_err());
+    assert!(database.create_index(notes.clone(), Index::new("notes_summary", &["summary"])).is_err());
+}
+
+#[test]
+fn test_migrate_large_rows() {
+    setup();
+    let root_path = "db_root_migrate_large_rows";
+    if std::fs::metadata(format!("{}/{}", ROOT_DIR, root_path)).is_ok() {
+        std::fs::remove_dir_all(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    }
+    std::fs::create_dir(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    let mut db_root = RootDatabase::new(&format!("{}/{}", ROOT_DIR, root_path));
+    let mut database = db_root.create_database("customer").unwrap();
+    let documents = create_table_from_sql(&mut database, "CREATE TABLE documents (id INTEGER PRIMARY KEY, body TEXT(65535), data BLOB)");
+    let mut data_handler = DataHandler::new_from_path(format!("{}/{}/customer", ROOT_DIR, root_path));
+
+    // a JSON data file holding rows larger than a page
+    let body = "lorem ipsum ".repeat(1000);
+    let column = |name: &str| serde_json::to_string(documents.get_column(name).unwrap()).unwrap();
+    let content = format!(
+        "[{{\"values\": [[{}, 1], [{}, {}], [{}, {}]]}}, {{\"values\": [[{}, 2], [{}, \"short\"], [{}, null]]}}]",
+        column("id"), column("body"), serde_json::to_string(&body).unwrap(), column("data"), serde_json::to_string(&vec![9u8; 5000]).unwrap(),
+        column("id"), column("body"), column("data")
+    );
+    database.get_storage().write_file("documents.data", &content).unwrap();
+
+    // the rows are kept whole once migrated, large rows can still be added and changed
+    let result = data_handler.select(Query::new("SELECT * FROM documents ORDER BY id")).unwrap();
+    assert_eq!(result.get_records().len(), 2);
+    assert_eq!(result.get_records()[0].get_values()[1].1, Value::Text(body.clone()));
+    assert_eq!(result.get_records()[0].get_values()[2].1, Value::Blob(vec![9; 5000]));
+    insert_values(&mut data_handler, &documents, vec![Value::Integer(3), Value::Text(body.repeat(3)), Value::Blob(vec![1; 20000])]).unwrap();
+    data_handler.update(Record::new(documents.clone(), Vec::new()), Query::new("UPDATE documents SET body = 'short' WHERE id = 1")).unwrap();
+    let result = data_handler.select(Query::new("SELECT id FROM documents WHERE body = 'short'")).unwrap();
+    assert_eq!(result.get_records().len(), 2);
+    let result = data_handler.select(Query::new("SELECT data FROM documents WHERE id = 3")).unwrap();
+    assert_eq!(result.get_records()[0].get_values()[0].1, Value::Blob(vec![1; 20000]));
+}
+
+#[test]
+fn test_constraint_indexes() {
+    setup();
+    let root_path = "db_root_constraint_indexes";
+    if std::fs::metadata(format!("{}/{}", ROOT_DIR, root_path)).is_ok() {
+        std::fs::remove_dir_all(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    }
+    std::fs::create_dir(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    let mut db_root = RootDatabase::new(&format!("{}/{}", ROOT_DIR, root_path));
+    let mut database = db_root.create_database("customer").unwrap();
+    let customers = create_table_from_sql(&mut database, "CREATE TABLE customers (id INTEGER PRIMARY KEY AUTOINCREMENT, email TEXT(64) UNIQUE)");
+    let orders = create_table_from_sql(&mut database, "CREATE TABLE orders (id INTEGER PRIMARY KEY, customer_id INTEGER REFERENCES customers)");
+    let notes = create_table_from_sql(&mut database, "CREATE TABLE notes (text TEXT(64))");
+    let mut data_handler = DataHandler::new_from_path(format!("{}/{}/customer", ROOT_DIR, root_path));
+    let text = |value: &str| Value::Text(value.to_string());
+
+    // the keys of a table are indexed as rows come in
+    let index_file = |name: &str| format!("{}/{}/customer/{}.{}", ROOT_DIR, root_path, name, TABLE_FILE_INDEX_EXTENSION);
+    assert!(std::fs::metadata(index_file("customers_pkey")).is_ok());
+    assert!(std::fs::metadata(index_file("customers_email_key")).is_ok());
+    insert_values(&mut data_handler, &customers, vec![Value::Null, text("john@example.com")]).unwrap();
+    insert_values(&mut data_handler, &customers, vec![Value::Null, text("jane@example.com")]).unwrap();
+    assert_eq!(index_entries(&data_handler, "customers_email_key"), vec![
+        (vec![text("jane@example.com")], RowId::new(1, 1)),
+        (vec![text("john@example.com")], RowId::new(1, 0)),
+    ]);
+
+    // new keys and referenced values are looked up in the indexes
+    let result = insert_values(&mut data_handler, &customers, vec![Value::Integer(2), text("jack@example.com")]);
+    assert_eq!(result.err().unwrap().to_string(), "Primary key constraint violation on table customers: duplicate key (id) = (2)");
+    let result = insert_values(&mut data_handler, &customers, vec![Value::Null, text("jane@example.com")]);
+    assert_eq!(result.err().unwrap().to_string(), "Unique constraint violation on table customers: duplicate key (email) = ('jane@example.com')");
+    insert_values(&mut data_handler, &orders, vec![Value::Integer(1), Value::Integer(2)]).unwrap();
+    let result = insert_values(&mut data_handler, &orders, vec![Value::Integer(2), Value::Integer(3)]);
+    assert_eq!(result.err().unwrap().to_string(), "Foreign key constraint violation on column customer_id: 3 not found in customers.id");
+    // a deleted row frees its key
+    data_handler.delete(Query::new("DELETE FROM customers WHERE id = 1")).unwrap();
+    insert_values(&mut data_handler, &customers, vec![Value::Null, text("john@example.com")]).unwrap();
+
+    // ids set by an update move the sequence forward
+    data_handler.update(Record::new(customers.clone(), Vec::new()), Query::new("UPDATE customers SET id = 20 WHERE id = 3")).unwrap();
+    assert_eq!(insert_values(&mut data_handler, &customers, vec![Value::Null, text("jill@example.com")]).unwrap().get_last_insert_id(), Some(21));
+
+    // a table without a key takes the same row twice
+    insert_values(&mut data_handler, &notes, vec![text("call back")]).unwrap();
+    insert_values(&mut data_handler, &notes, vec![text("call back")]).unwrap();
+    assert_eq!(data_handler.select(Query::new("SELECT * FROM notes")).unwrap().get_records().len(), 2);
+
+    // the index of a unique column goes along with the column
+    data_handler.alter_table("customers", &[AlterTableOperation::DropColumn("email".to_string())]).unwrap();
+    assert!(std::fs::metadata(index_file("customers_email_key")).is_err());
+    assert_eq!(index_entries(&data_handler, "customers_pkey").len(), 3);
+}
+
+#[test]
+fn test_drop_database_after_table_ddl() {
+    setup();
+    let root_path = "db_root_drop_database_after_table_ddl";
+    if std::fs::metadata(format!("{}/{}", ROOT_DIR, root_path)).is_ok() {
+        std::fs::remove_dir_all(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    }
+    std::fs::create_dir(format!("{}/{}", ROOT_DIR, root_path)).unwrap();
+    let mut db_root = RootDatabase::new(&format!("{}/{}", ROOT_DIR, root_path));
+    let mut database = db_root.create_database("customer").unwrap();
+    let customers = create_table_from_sql(&mut database, "CREATE TABLE customers (id INTEGER PRIMARY KEY, name TEXT(64))");
+    let mut data_handler = DataHandler::new_from_path(format!("{}/{}/customer", ROOT_DIR, root_path));
+    insert_values(&mut data_handler, &customers, vec![Value::Integer(1), Value::Text("John".to_string())]).unwrap();
+
+    // a database holding a table is kept whole, and still works
+    let database_dir = format!("{}/{}/customer", ROOT_DIR, root_path);
+    let result = db_root.drop_database("customer");
+    assert_eq!(result.err().unwrap().to_string(), format!("{}: database is not empty", database_dir));
+    assert_eq!(data_handler.select(Query::new("SELECT * FROM customers")).unwrap().get_records().len(), 1);
+
+    // once its tables are dropped, the database goes along with the files of its log
+    database.drop_table(customers).unwrap();
+    db_root.drop_database("customer").unwrap();
+    assert!(std::fs::metadata(&database_dir).is_err());
+    assert!(db_root.get_database("customer").is_none());
+
+    // a database of the same name starts over
+    let mut database = db_root.create_database("customer").unwrap();
+    create_table_from_sql(&mut database, "CREATE TABLE customers (id INTEGER PRIMARY KEY, name TEXT(64))");
+    assert!(data_handler.select(Query::new("SELECT * FROM customers")).unwrap().get_records().is_empty());
+}
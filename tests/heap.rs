@@ -0,0 +1,214 @@
+use red::database::abstraction::Value;
+use red::storage::files::FileStorage;
+use red::storage::heap::{decode_tuple, decode_version, encode_tuple, encode_versioned_tuple, HeapFile, RowId, RowVersion, HEAP_PAGE_SIZE, MAX_INLINE_TUPLE_SIZE};
+
+mod common;
+use crate::common::{setup, ROOT_DIR};
+
+fn create_heap(file_name: &str) -> (FileStorage, HeapFile) {
+    setup();
+    let storage = FileStorage::new(ROOT_DIR);
+    if storage.file_exists(file_name) {
+        storage.delete_file(file_name).unwrap();
+    }
+    let heap = HeapFile::create(&storage, file_name).unwrap();
+    (storage, heap)
+}
+
+fn row(id: i64, name: &str) -> Vec<Value> {
+    vec![Value::Integer(id), Value::Text(name.to_string()), Value::Null]
+}
+
+#[test]
+fn test_tuple_round_trip() {
+    let values = vec![
+        Value::Null,
+        Value::Integer(-42),
+        Value::Real(2.5),
+        Value::Text("héllo".to_string()),
+        Value::Blob(vec![0, 1, 255]),
+    ];
+    let tuple = encode_tuple(&values).unwrap();
+    assert_eq!(decode_tuple(&tuple).unwrap(), values);
+    assert!(decode_tuple(&tuple[..tuple.len() - 1]).is_err());
+}
+
+#[test]
+fn test_versioned_tuple_round_trip() {
+    let values = vec![Value::Integer(7), Value::Text("John".to_string())];
+    let tuple = encode_versioned_tuple(&values, RowVersion::new(3, 5)).unwrap();
+    assert_eq!(decode_tuple(&tuple).unwrap(), values);
+    assert_eq!(decode_version(&tuple).unwrap(), RowVersion::new(3, 5));
+    // a tuple without version was written before transaction ids, every transaction sees it
+    assert_eq!(decode_version(&encode_tuple(&values).unwrap()).unwrap(), RowVersion::default());
+}
+
+#[test]
+fn test_row_id() {
+    let row_id = RowId::new(3, 7);
+    assert_eq!(RowId::from_u64(row_id.to_u64()), row_id);
+    assert!(RowId::new(1, 500).to_u64() < RowId::new(2, 0).to_u64());
+}
+
+#[test]
+fn test_heap_insert_get_scan() {
+    let (_, mut heap) = create_heap("test_heap_insert_get_scan");
+    assert_eq!(heap.scan().unwrap(), vec![]);
+    let first = heap.insert(&row(1, "John")).unwrap();
+    let second = heap.insert(&row(2, "Jane")).unwrap();
+    assert_eq!(first, RowId::new(1, 0));
+    assert_eq!(second, RowId::new(1, 1));
+    assert_eq!(heap.get(second).unwrap(), Some(row(2, "Jane")));
+    assert_eq!(heap.get(RowId::new(1, 2)).unwrap(), None);
+    assert_eq!(heap.get(RowId::new(2, 0)).unwrap(), None);
+    assert_eq!(heap.scan().unwrap(), vec![(first, row(1, "John")), (second, row(2, "Jane"))]);
+    assert_eq!(heap.get_tuple_count(), 2);
+
+    // rows go to a new page once the last one is full
+    let name = "x".repeat(1000);
+    for id in 3..10 {
+        heap.insert(&row(id, &name)).unwrap();
+    }
+    assert!(heap.get_page_count() > 1);
+    assert_eq!(heap.scan().unwrap().len(), 9);
+}
+
+#[test]
+fn test_heap_update_delete() {
+    let (_, mut heap) = create_heap("test_heap_update_delete");
+    let ids: Vec<RowId> = (0..3).map(|id| heap.insert(&row(id, "name")).unwrap()).collect();
+
+    // a row keeps its id while it fits in its page, shorter or longer
+    assert_eq!(heap.update(ids[1], &row(1, "n")).unwrap(), ids[1]);
+    assert_eq!(heap.update(ids[1], &row(1, "a much longer name")).unwrap(), ids[1]);
+    assert_eq!(heap.get(ids[1]).unwrap(), Some(row(1, "a much longer name")));
+
+    assert!(heap.delete(ids[0]).unwrap());
+    assert!(!heap.delete(ids[0]).unwrap());
+    assert!(heap.update(ids[0], &row(0, "name")).is_err());
+    assert_eq!(heap.get_tuple_count(), 2);
+
+    // the slot of a deleted row is reused
+    assert_eq!(heap.insert(&row(3, "name")).unwrap(), ids[0]);
+    assert_eq!(heap.get_tuple_count(), 3);
+}
+
+#[test]
+fn test_heap_page_compaction() {
+    let (_, mut heap) = create_heap("test_heap_page_compaction");
+    let name = "x".repeat(HEAP_PAGE_SIZE / 5);
+    let ids: Vec<RowId> = (0..4).map(|id| heap.insert(&row(id, &name)).unwrap()).collect();
+    assert_eq!(heap.get_page_count(), 1);
+
+    // the room of deleted rows is reclaimed for a row that only fits once the page is compacted
+    heap.delete(ids[0]).unwrap();
+    heap.delete(ids[2]).unwrap();
+    let longer = "y".repeat(HEAP_PAGE_SIZE / 3);
+    assert_eq!(heap.update(ids[1], &row(1, &longer)).unwrap(), ids[1]);
+    assert_eq!(heap.get_page_count(), 1);
+    assert_eq!(heap.get(ids[1]).unwrap(), Some(row(1, &longer)));
+    assert_eq!(heap.get(ids[3]).unwrap(), Some(row(3, &name)));
+
+    // a row growing past the room left in its page moves to another page
+    let moved = heap.update(ids[3], &row(3, &"z".repeat(HEAP_PAGE_SIZE * 3 / 4))).unwrap();
+    assert_eq!(moved.get_page(), 2);
+    assert_eq!(heap.get(ids[3]).unwrap(), None);
+    assert_eq!(heap.get_tuple_count(), 2);
+}
+
+#[test]
+fn test_heap_free_space_reuse() {
+    let (storage, mut heap) = create_heap("test_heap_free_space_reuse");
+    let name = "x".repeat(HEAP_PAGE_SIZE / 3);
+    let ids: Vec<RowId> = (0..6).map(|id| heap.insert(&row(id, &name)).unwrap()).collect();
+    assert_eq!(heap.get_page_count(), 3);
+    assert_eq!(ids[5].get_page(), 3);
+
+    // a new row takes the room of a deleted row on an earlier page, after a reopen as well
+    heap.delete(ids[0]).unwrap();
+    let mut heap = HeapFile::open(&storage, "test_heap_free_space_reuse").unwrap();
+    assert_eq!(heap.insert(&row(6, &name)).unwrap().get_page(), 1);
+    assert_eq!(heap.insert(&row(7, &name)).unwrap().get_page(), 4);
+    assert_eq!(heap.get_page_count(), 4);
+
+    // the freed overflow pages of a large row take new rows once the data pages are full
+    let large = vec![Value::Integer(8), Value::Null, Value::Blob(vec![8; HEAP_PAGE_SIZE * 2])];
+    let large_id = heap.insert(&large).unwrap();
+    assert_eq!(large_id.get_page(), 4);
+    let page_count = heap.get_page_count();
+    assert!(page_count > 4);
+    heap.delete(large_id).unwrap();
+    assert_eq!(heap.insert(&row(9, &name)).unwrap().get_page(), 4);
+    assert_eq!(heap.insert(&row(10, &name)).unwrap().get_page(), 5);
+    assert_eq!(heap.get_page_count(), page_count);
+}
+
+#[test]
+fn test_heap_reopen() {
+    let (storage, mut heap) = create_heap("test_heap_reopen");
+    let first = heap.insert(&row(1, "John")).unwrap();
+    heap.insert(&row(2, "Jane")).unwrap();
+    heap.delete(first).unwrap();
+
+    let heap = HeapFile::open(&storage, "test_heap_reopen").unwrap();
+    assert_eq!(heap.get_tuple_count(), 1);
+    assert_eq!(heap.scan().unwrap(), vec![(RowId::new(1, 1), row(2, "Jane"))]);
+
+    // files without the heap header are rejected
+    storage.write_file("test_heap_reopen_json", "[]").unwrap();
+    assert!(!HeapFile::is_heap_file(&storage, "test_heap_reopen_json"));
+    assert!(HeapFile::open(&storage, "test_heap_reopen_json").is_err());
+}
+
+#[test]
+fn test_heap_row_versions() {
+    let (_, mut heap) = create_heap("test_heap_row_versions");
+    let first = heap.insert_version(&row(1, "John"), 4).unwrap();
+    let second = heap.insert(&row(2, "Jane")).unwrap();
+    assert_eq!(heap.get_version(first).unwrap(), Some((RowVersion::new(4, 0), row(1, "John"))));
+    assert_eq!(heap.get_version(second).unwrap(), Some((RowVersion::new(0, 0), row(2, "Jane"))));
+
+    // an expired version stays in place until it is deleted, an update keeps the version
+    assert!(heap.expire(first, 6).unwrap());
+    assert!(!heap.expire(RowId::new(1, 5), 6).unwrap());
+    assert_eq!(heap.update(first, &row(1, "Jack")).unwrap(), first);
+    assert_eq!(heap.scan_versions().unwrap(), vec![
+        (first, RowVersion::new(4, 6), row(1, "Jack")),
+        (second, RowVersion::new(0, 0), row(2, "Jane")),
+    ]);
+    assert!(heap.get_version(first).unwrap().unwrap().0.is_expired());
+}
+
+#[test]
+fn test_heap_overflow_pages() {
+    let (storage, mut heap) = create_heap("test_heap_overflow_pages");
+    let first = heap.insert(&row(1, "John")).unwrap();
+    // a row too large for a page keeps its values in overflow pages, the rows around it stay in data pages
+    let large = vec![Value::Integer(2), Value::Text("é".repeat(HEAP_PAGE_SIZE)), Value::Blob(vec![7; MAX_INLINE_TUPLE_SIZE])];
+    let second = heap.insert_version(&large, 3).unwrap();
+    let third = heap.insert(&row(3, "Jane")).unwrap();
+    assert_eq!(second, RowId::new(1, 1));
+    assert_eq!(third, RowId::new(1, 2));
+    assert_eq!(heap.get_page_count(), 5);
+    assert_eq!(heap.get_version(second).unwrap(), Some((RowVersion::new(3, 0), large.clone())));
+    assert!(heap.expire(second, 4).unwrap());
+    let heap_reopened = HeapFile::open(&storage, "test_heap_overflow_pages").unwrap();
+    assert_eq!(heap_reopened.scan_versions().unwrap(), vec![
+        (first, RowVersion::new(0, 0), row(1, "John")),
+        (second, RowVersion::new(3, 4), large),
+        (third, RowVersion::new(0, 0), row(3, "Jane")),
+    ]);
+    assert_eq!(heap.get(RowId::new(2, 0)).unwrap(), None);
+
+    // the overflow pages of an updated or deleted row are freed
+    let larger = vec![Value::Integer(2), Value::Null, Value::Blob(vec![8; HEAP_PAGE_SIZE * 3])];
+    assert_eq!(heap.update(second, &larger).unwrap(), second);
+    assert_eq!(heap.get(second).unwrap(), Some(larger.clone()));
+    assert_eq!(heap.get_page_count(), 9);
+    // the pages added by the update are in the header of the file
+    let heap_reopened = HeapFile::open(&storage, "test_heap_overflow_pages").unwrap();
+    assert_eq!(heap_reopened.get_page_count(), 9);
+    assert_eq!(heap_reopened.get(second).unwrap(), Some(larger));
+    assert!(heap.delete(second).unwrap());
+    assert_eq!(heap.scan().unwrap(), vec![(first, row(1, "John")), (third, row(3, "Jane"))]);
+}
@@ -0,0 +1,177 @@
+use std::fs::{File, OpenOptions, TryLockError};
+use std::process::Command;
+
+use red::database::abstraction::{Column, DataType, Database, Query, Record, Table, Value, DDL, DML};
+use red::storage::files::FileStorage;
+use red::storage::locks::{LockMode, LOCK_FILE_NAME};
+use red::storage::persistence::DataHandler;
+
+mod common;
+use crate::common::{setup, ROOT_DIR};
+
+// directory the child process opens
+const LOCK_DIR_VARIABLE: &str = "RED_LOCK_DIR";
+
+// a database with a table of items holding one row, the returned handle holds the directory as its writer
+fn create_database(dir_name: &str) -> (FileStorage, Table) {
+    setup();
+    let path = format!("{}/{}", ROOT_DIR, dir_name);
+    if std::fs::metadata(&path).is_ok() {
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+    std::fs::create_dir(&path).unwrap();
+    let storage = FileStorage::new(&path);
+    let mut database = Database::new("shop", storage.clone());
+    let mut items = Table::new("items", Box::new(Database::new("shop", FileStorage::new(&path))));
+    items.add_column(Column::new("id", DataType::Integer, true, false).unwrap());
+    items.add_column(Column::new("name", DataType::Text(255), false, false).unwrap());
+    database.create_table(items.clone()).unwrap();
+    DataHandler::new_from_storage(storage.clone()).insert(record(&items, 1, "book")).unwrap();
+    (storage, items)
+}
+
+fn record(table: &Table, id: i64, name: &str) -> Record {
+    let values = vec![Value::Integer(id), Value::Text(name.to_string())];
+    Record::new(table.clone(), table.get_columns().iter().cloned().zip(values).collect())
+}
+
+// the lock file opened apart from the storage, its lock conflicts with the one of the storage like the lock of another process
+fn open_lock_file(root_dir: &str) -> File {
+    let path = format!("{}/{}", root_dir, LOCK_FILE_NAME);
+    OpenOptions::new().read(true).write(true).open(path).unwrap()
+}
+
+fn error_message<T>(result: Result<T, Box<dyn std::error::Error>>) -> String {
+    result.err().expect("Expected an error").to_string()
+}
+
+#[test]
+#[ignore]
+fn lock_child() {
+    // opens the directory of the parent test, reports how it went
+    let Ok(path) = std::env::var(LOCK_DIR_VARIABLE) else {
+        return;
+    };
+    match FileStorage::open(&path, LockMode::Exclusive) {
+        Ok(_) => println!("opened"),
+        Err(error) => println!("error {}", error),
+    }
+}
+
+#[test]
+fn test_second_writer_is_locked_out() {
+    let (storage, _) = create_database("test_second_writer_is_locked_out");
+    let root_dir = storage.get_root_dir().to_string();
+
+    // another process cannot open the directory while this one writes to it
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args(["lock_child", "--exact", "--ignored", "--nocapture", "--test-threads=1"])
+        .env(LOCK_DIR_VARIABLE, &root_dir)
+        .output()
+        .unwrap();
+    let expected = format!("error {}: database is locked\n", root_dir);
+    assert!(String::from_utf8(output.stdout).unwrap().contains(&expected));
+    assert!(matches!(open_lock_file(&root_dir).try_lock_shared(), Err(TryLockError::WouldBlock)));
+
+    // once this process closes the directory, the other one opens it
+    storage.close();
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args(["lock_child", "--exact", "--ignored", "--nocapture", "--test-threads=1"])
+        .env(LOCK_DIR_VARIABLE, &root_dir)
+        .output()
+        .unwrap();
+    assert!(String::from_utf8(output.stdout).unwrap().contains("opened\n"));
+}
+
+#[test]
+fn test_lock_released_with_last_handle() {
+    let (storage, items) = create_database("test_lock_released_with_last_handle");
+    let root_dir = storage.get_root_dir().to_string();
+    storage.close();
+    assert!(open_lock_file(&root_dir).try_lock().is_ok());
+
+    // the handles of the process share the lock, the last one dropped releases it
+    let database = Database::open("shop", &root_dir, LockMode::Exclusive).unwrap();
+    let mut data_handler = DataHandler::new_from_storage(database.get_storage().clone());
+    data_handler.insert(record(&items, 2, "pen")).unwrap();
+    database.close();
+    assert!(matches!(open_lock_file(&root_dir).try_lock_shared(), Err(TryLockError::WouldBlock)));
+    drop(data_handler);
+    assert!(open_lock_file(&root_dir).try_lock().is_ok());
+
+    // what was written before the close is found by the next handle
+    let database = Database::open("shop", &root_dir, LockMode::Shared).unwrap();
+    let data_handler = DataHandler::new_from_storage(database.get_storage().clone());
+    assert_eq!(data_handler.select(Query::new("SELECT * FROM items")).unwrap().get_records().len(), 2);
+}
+
+#[test]
+fn test_work_rolled_back_with_last_handle() {
+    let (storage, _) = create_database("test_work_rolled_back_with_last_handle");
+    let root_dir = storage.get_root_dir().to_string();
+    storage.write_file("kept", "committed").unwrap();
+    storage.checkpoint().unwrap();
+
+    // work left unfinished by the last handle is undone, not made durable
+    storage.write_file("kept", "changed").unwrap();
+    storage.create_file("new").unwrap();
+    storage.close();
+    let storage = FileStorage::open(&root_dir, LockMode::Shared).unwrap();
+    assert_eq!(storage.read_file("kept").unwrap(), "committed");
+    assert!(!storage.file_exists("new"));
+}
+
+#[test]
+fn test_directory_locked_by_another_process() {
+    let (storage, items) = create_database("test_directory_locked_by_another_process");
+    let root_dir = storage.get_root_dir().to_string();
+    storage.close();
+    let other = open_lock_file(&root_dir);
+    other.try_lock().unwrap();
+
+    // every way into the directory reports the lock
+    let expected = format!("{}: database is locked", root_dir);
+    assert_eq!(error_message(FileStorage::open(&root_dir, LockMode::Exclusive)), expected);
+    assert_eq!(error_message(FileStorage::open(&root_dir, LockMode::Shared)), expected);
+    assert_eq!(error_message(Database::open("shop", &root_dir, LockMode::Shared)), expected);
+    let mut data_handler = DataHandler::new_from_storage(FileStorage::new(&root_dir));
+    assert_eq!(error_message(data_handler.select(Query::new("SELECT * FROM items"))), expected);
+    assert_eq!(error_message(data_handler.insert(record(&items, 2, "pen"))), expected);
+    assert_eq!(error_message(FileStorage::new(ROOT_DIR).delete_dir("test_directory_locked_by_another_process")), expected);
+
+    other.unlock().unwrap();
+    assert_eq!(data_handler.select(Query::new("SELECT * FROM items")).unwrap().get_records().len(), 1);
+}
+
+#[test]
+fn test_readers_share_directory() {
+    let (storage, items) = create_database("test_readers_share_directory");
+    let root_dir = storage.get_root_dir().to_string();
+    storage.close();
+    let other = open_lock_file(&root_dir);
+    other.try_lock_shared().unwrap();
+
+    // a reader opens the directory next to another reader, and does not write to it
+    let storage = FileStorage::open(&root_dir, LockMode::Shared).unwrap();
+    assert!(matches!(open_lock_file(&root_dir).try_lock(), Err(TryLockError::WouldBlock)));
+    let mut data_handler = DataHandler::new_from_storage(storage.clone());
+    assert_eq!(data_handler.select(Query::new("SELECT * FROM items")).unwrap().get_records().len(), 1);
+    let expected = format!("{}: database is opened for reading", root_dir);
+    assert_eq!(error_message(data_handler.insert(record(&items, 2, "pen"))), expected);
+    assert_eq!(error_message(storage.lock_tables(&["items".to_string()])), expected);
+    // the process does not become the writer while another process reads
+    assert_eq!(error_message(FileStorage::open(&root_dir, LockMode::Exclusive)), format!("{}: database is locked", root_dir));
+    assert_eq!(data_handler.select(Query::new("SELECT * FROM items")).unwrap().get_records().len(), 1);
+
+    // the readers of the process do not become writers, the writer waits for the last of them to close
+    other.unlock().unwrap();
+    assert_eq!(error_message(FileStorage::open(&root_dir, LockMode::Exclusive)), format!("{}: database is locked", root_dir));
+    assert!(matches!(open_lock_file(&root_dir).try_lock(), Err(TryLockError::WouldBlock)));
+    assert_eq!(error_message(data_handler.insert(record(&items, 2, "pen"))), expected);
+    drop(data_handler);
+    storage.close();
+    let mut writer = DataHandler::new_from_storage(FileStorage::open(&root_dir, LockMode::Exclusive).unwrap());
+    writer.insert(record(&items, 2, "pen")).unwrap();
+    assert!(matches!(open_lock_file(&root_dir).try_lock_shared(), Err(TryLockError::WouldBlock)));
+    assert_eq!(writer.select(Query::new("SELECT * FROM items")).unwrap().get_records().len(), 2);
+}
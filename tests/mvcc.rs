@@ -0,0 +1,186 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use red::database::abstraction::{Column, DataType, Database, Index, Query, Record, Table, Value, DDL, DML};
+use red::database::transaction::Transaction;
+use red::storage::buffer;
+use red::storage::files::{FileStorage, TABLE_FILE_DATA_EXTENSION};
+use red::storage::heap::{encode_tuple, HEAP_PAGE_SIZE};
+use red::storage::mvcc::Snapshot;
+use red::storage::persistence::DataHandler;
+use red::storage::wal;
+
+mod common;
+use crate::common::{setup, ROOT_DIR};
+
+// a database with accounts 1 to 3 holding 100 each, in a directory of its own
+fn create_database(dir_name: &str) -> (Database, Table) {
+    setup();
+    let path = format!("{}/{}", ROOT_DIR, dir_name);
+    if std::fs::metadata(&path).is_ok() {
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+    std::fs::create_dir(&path).unwrap();
+    let mut database = Database::new("bank", FileStorage::new(&path));
+    let mut accounts = Table::new("accounts", Box::new(database.clone()));
+    accounts.add_column(Column::new("id", DataType::Integer, true, false).unwrap());
+    accounts.add_column(Column::new("owner", DataType::Text(255), false, false).unwrap());
+    accounts.add_column(Column::new("amount", DataType::Integer, false, false).unwrap());
+    database.create_table(accounts.clone()).unwrap();
+    database.create_index(accounts.clone(), Index::new("accounts_owner", &["owner"])).unwrap();
+    let mut data_handler = DataHandler::new_from_storage(database.get_storage().clone());
+    for (id, owner) in [(1, "John"), (2, "Jane"), (3, "Jack")] {
+        data_handler.insert(record(&accounts, id, owner, 100)).unwrap();
+    }
+    (database, accounts)
+}
+
+fn record(table: &Table, id: i64, owner: &str, amount: i64) -> Record {
+    let values = vec![Value::Integer(id), Value::Text(owner.to_string()), Value::Integer(amount)];
+    Record::new(table.clone(), table.get_columns().iter().cloned().zip(values).collect())
+}
+
+fn amounts(dml: &dyn DML, sql: &str) -> Vec<i64> {
+    dml.select(Query::new(sql)).unwrap().get_records().iter()
+        .map(|record| match record.get_value("amount").unwrap().1 {
+            Value::Integer(amount) => amount,
+            _ => panic!("Expected an integer amount"),
+        })
+        .collect()
+}
+
+// move an amount between two accounts, one statement per account
+fn transfer(transaction: &mut Transaction, accounts: &Table, from: i64, to: i64, amount: i64) {
+    for (id, change) in [(from, -amount), (to, amount)] {
+        let sql = format!("UPDATE accounts SET amount = amount + {} WHERE id = {}", change, id);
+        transaction.update(Record::new(accounts.clone(), Vec::new()), Query::new(&sql)).unwrap();
+    }
+}
+
+#[test]
+fn test_reads_see_committed_work_only() {
+    let (database, accounts) = create_database("test_reads_see_committed_work_only");
+    let data_handler = DataHandler::new_from_storage(database.get_storage().clone());
+    let mut transaction = database.begin_transaction().unwrap();
+    transaction.insert(record(&accounts, 4, "Jill", 50)).unwrap();
+    transfer(&mut transaction, &accounts, 1, 2, 30);
+    transaction.delete(Query::new("DELETE FROM accounts WHERE id = 3")).unwrap();
+
+    // the transaction sees its work, other handles see the rows as they were, through the index too
+    assert_eq!(amounts(&transaction, "SELECT * FROM accounts ORDER BY id"), vec![70, 130, 50]);
+    assert_eq!(amounts(&data_handler, "SELECT * FROM accounts ORDER BY id"), vec![100, 100, 100]);
+    assert_eq!(amounts(&data_handler, "SELECT * FROM accounts WHERE owner = 'John'"), vec![100]);
+    assert_eq!(amounts(&data_handler, "SELECT * FROM accounts WHERE owner = 'Jill'"), Vec::<i64>::new());
+    assert_eq!(amounts(&transaction, "SELECT * FROM accounts WHERE owner = 'Jack'"), Vec::<i64>::new());
+    transaction.commit().unwrap();
+
+    assert_eq!(amounts(&data_handler, "SELECT * FROM accounts ORDER BY id"), vec![70, 130, 50]);
+    assert_eq!(amounts(&data_handler, "SELECT * FROM accounts WHERE owner = 'John'"), vec![70]);
+}
+
+#[test]
+fn test_snapshot_outlives_commit() {
+    let (database, accounts) = create_database("test_snapshot_outlives_commit");
+    let storage = database.get_storage();
+    let mut data_handler = DataHandler::new_from_storage(storage.clone());
+    let snapshot = storage.take_snapshot(None).unwrap();
+    data_handler.update(Record::new(accounts.clone(), Vec::new()), Query::new("UPDATE accounts SET amount = 0 WHERE id < 3")).unwrap();
+    data_handler.delete(Query::new("DELETE FROM accounts WHERE id = 3")).unwrap();
+
+    // the old versions are kept as long as a snapshot needs them
+    let visible = |snapshot: &Snapshot| data_handler.load_visible_data(&accounts, snapshot).unwrap().len();
+    assert_eq!(visible(&snapshot), 3);
+    assert_eq!(amounts(&data_handler, "SELECT * FROM accounts"), vec![0, 0]);
+    assert_eq!(data_handler.vacuum("accounts").unwrap(), 0);
+    assert_eq!(visible(&snapshot), 3);
+    drop(snapshot);
+    assert_eq!(data_handler.vacuum("accounts").unwrap(), 3);
+    assert_eq!(data_handler.vacuum("accounts").unwrap(), 0);
+    assert_eq!(visible(&storage.take_snapshot(None).unwrap()), 2);
+    assert_eq!(amounts(&data_handler, "SELECT * FROM accounts WHERE owner = 'Jane'"), vec![0]);
+}
+
+#[test]
+fn test_transaction_ids_survive_restart() {
+    let (database, _) = create_database("test_transaction_ids_survive_restart");
+    let storage = database.get_storage();
+    let mut data_handler = DataHandler::new_from_storage(storage.clone());
+    data_handler.delete(Query::new("DELETE FROM accounts WHERE id = 1")).unwrap();
+
+    // work committed before a restart stays visible, ids given after it are higher
+    buffer::release_buffer_pool(storage.get_root_dir());
+    wal::release_write_ahead_log(storage.get_root_dir());
+    let storage = FileStorage::new(storage.get_root_dir());
+    let data_handler = DataHandler::new_from_storage(storage.clone());
+    assert_eq!(amounts(&data_handler, "SELECT * FROM accounts"), vec![100, 100]);
+    let transaction = Transaction::begin(storage.clone()).unwrap();
+    assert!(transaction.get_id() > 4);
+    transaction.rollback().unwrap();
+}
+
+#[test]
+fn test_long_read_sees_consistent_rows() {
+    let (database, _) = create_database("test_long_read_sees_consistent_rows");
+    let storage = database.get_storage().clone();
+    let done = Arc::new(AtomicBool::new(false));
+    let writer = {
+        let storage = storage.clone();
+        let done = done.clone();
+        std::thread::spawn(move || {
+            let accounts = DataHandler::new_from_storage(storage.clone()).load_table_descriptor("accounts").unwrap();
+            for round in 0..30 {
+                let mut transaction = Transaction::begin(storage.clone()).unwrap();
+                transfer(&mut transaction, &accounts, round % 3 + 1, (round + 1) % 3 + 1, 10);
+                transaction.commit().unwrap();
+            }
+            done.store(true, Ordering::SeqCst);
+        })
+    };
+
+    // a read never sees a transfer half done
+    let data_handler = DataHandler::new_from_storage(storage);
+    let mut reads = 0;
+    while !done.load(Ordering::SeqCst) || reads == 0 {
+        assert_eq!(amounts(&data_handler, "SELECT * FROM accounts").iter().sum::<i64>(), 300);
+        reads += 1;
+    }
+    writer.join().unwrap();
+}
+
+#[test]
+fn test_unversioned_heap_migration() {
+    let (database, accounts) = create_database("test_unversioned_heap_migration");
+    let storage = database.get_storage();
+    let mut data_handler = DataHandler::new_from_storage(storage.clone());
+    data_handler.delete(Query::new("DELETE FROM accounts")).unwrap();
+
+    // a heap file of the first format, with one page of tuples without row versions
+    let mut header = b"REDHEAP1".to_vec();
+    header.extend_from_slice(&1u32.to_le_bytes());
+    header.extend_from_slice(&2u64.to_le_bytes());
+    header.resize(HEAP_PAGE_SIZE, 0);
+    let mut page = vec![0; HEAP_PAGE_SIZE];
+    let mut free_end = HEAP_PAGE_SIZE;
+    for (slot, (id, owner)) in [(7, "Jill"), (8, "Joe")].into_iter().enumerate() {
+        let tuple = encode_tuple(&[Value::Integer(id), Value::Text(owner.to_string()), Value::Integer(id * 10)]).unwrap();
+        free_end -= tuple.len();
+        page[free_end..free_end + tuple.len()].copy_from_slice(&tuple);
+        page[4 + slot * 4..6 + slot * 4].copy_from_slice(&(free_end as u16).to_le_bytes());
+        page[6 + slot * 4..8 + slot * 4].copy_from_slice(&(tuple.len() as u16).to_le_bytes());
+    }
+    page[0..2].copy_from_slice(&2u16.to_le_bytes());
+    page[2..4].copy_from_slice(&(free_end as u16).to_le_bytes());
+    header.extend(page);
+    buffer::release_buffer_pool(storage.get_root_dir());
+    wal::release_write_ahead_log(storage.get_root_dir());
+    let path = format!("{}/accounts.{}", storage.get_root_dir(), TABLE_FILE_DATA_EXTENSION);
+    std::fs::write(&path, header).unwrap();
+
+    // the rows are read once the file is migrated, and the index rebuilt
+    let mut data_handler = DataHandler::new_from_storage(FileStorage::new(storage.get_root_dir()));
+    assert_eq!(amounts(&data_handler, "SELECT * FROM accounts ORDER BY id"), vec![70, 80]);
+    assert!(std::fs::read(&path).unwrap().starts_with(b"REDHEAP2"));
+    assert_eq!(amounts(&data_handler, "SELECT * FROM accounts WHERE owner = 'Joe'"), vec![80]);
+    data_handler.insert(record(&accounts, 9, "Jim", 90)).unwrap();
+    assert_eq!(amounts(&data_handler, "SELECT * FROM accounts"), vec![70, 80, 90]);
+}
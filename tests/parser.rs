@@ -0,0 +1,265 @@
+use red::database::abstraction::{AlterTableOperation, DataType, Query, ReferentialAction};
+use red::database::parser::{
+    self, AlterTableAction, BinaryOperator, ColumnConstraint, Expression, Literal, SelectItem,
+    Statement,
+};
+
+#[test]
+fn test_parse_select() {
+    let query = Query::new("SELECT id, name AS user_name FROM users WHERE id >= 7 AND name IS NOT NULL ORDER BY name DESC LIMIT 10 OFFSET 5;");
+    let statement = query.parse().unwrap();
+    match statement {
+        Statement::Select { projection, table, selection, order_by, limit, offset } => {
+            assert_eq!(table, "users");
+            assert_eq!(projection.len(), 2);
+            assert_eq!(projection[1], SelectItem::Expression {
+                expression: Expression::Column("name".to_string()),
+                alias: Some("user_name".to_string())
+            });
+            assert_eq!(order_by.len(), 1);
+            assert!(!order_by[0].ascending);
+            assert_eq!(limit, Some(10));
+            assert_eq!(offset, Some(5));
+            match selection.unwrap() {
+                Expression::Binary { left, operator, right } => {
+                    assert_eq!(operator, BinaryOperator::And);
+                    assert_eq!(*left, Expression::Binary {
+                        left: Box::new(Expression::Column("id".to_string())),
+                        operator: BinaryOperator::GreaterOrEqual,
+                        right: Box::new(Expression::Literal(Literal::Integer(7)))
+                    });
+                    assert_eq!(*right, Expression::IsNull {
+                        operand: Box::new(Expression::Column("name".to_string())),
+                        negated: true
+                    });
+                }
+                _ => panic!("Expected a binary expression")
+            }
+        }
+        _ => panic!("Expected a select statement")
+    }
+}
+
+#[test]
+fn test_parse_operator_precedence() {
+    // AND binds tighter than OR, NOT tighter than AND
+    let expression = parser::parse_expression("a = 1 OR NOT b = 2 AND c = 3").unwrap();
+    match expression {
+        Expression::Binary { operator, right, .. } => {
+            assert_eq!(operator, BinaryOperator::Or);
+            match *right {
+                Expression::Binary { left, operator, .. } => {
+                    assert_eq!(operator, BinaryOperator::And);
+                    assert!(matches!(*left, Expression::Unary { .. }));
+                }
+                _ => panic!("Expected a binary expression")
+            }
+        }
+        _ => panic!("Expected a binary expression")
+    }
+
+    let expression = parser::parse_expression("1 + 2 * 3").unwrap();
+    match expression {
+        Expression::Binary { operator, .. } => assert_eq!(operator, BinaryOperator::Plus),
+        _ => panic!("Expected a binary expression")
+    }
+}
+
+#[test]
+fn test_parse_insert_update_delete() {
+    let statement = parser::parse_statement("INSERT INTO users (id, name) VALUES (1, 'John'), (2, 'O''Neil')").unwrap();
+    assert_eq!(statement, Statement::Insert {
+        table: "users".to_string(),
+        columns: vec!["id".to_string(), "name".to_string()],
+        rows: vec![
+            vec![Expression::Literal(Literal::Integer(1)), Expression::Literal(Literal::Text("John".to_string()))],
+            vec![Expression::Literal(Literal::Integer(2)), Expression::Literal(Literal::Text("O'Neil".to_string()))],
+        ]
+    });
+
+    let statement = parser::parse_statement("UPDATE users SET name = 'Jane', score = -1.5 WHERE id = 1").unwrap();
+    match statement {
+        Statement::Update { table, assignments, selection } => {
+            assert_eq!(table, "users");
+            assert_eq!(assignments[1], ("score".to_string(), Expression::Literal(Literal::Real(-1.5))));
+            assert!(selection.is_some());
+        }
+        _ => panic!("Expected an update statement")
+    }
+
+    let statement = parser::parse_statement("delete from users").unwrap();
+    assert_eq!(statement, Statement::Delete { table: "users".to_string(), selection: None });
+    assert_eq!(statement.get_table_name(), Some("users"));
+}
+
+#[test]
+fn test_parse_create_drop_table() {
+    let statement = parser::parse_statement(
+        "CREATE TABLE IF NOT EXISTS users (
+            id INTEGER PRIMARY KEY,
+            name VARCHAR(255) NOT NULL,
+            avatar BLOB
+        )").unwrap();
+    match statement {
+        Statement::CreateTable { table, if_not_exists, columns } => {
+            assert_eq!(table, "users");
+            assert!(if_not_exists);
+            assert_eq!(columns.len(), 3);
+            assert_eq!(columns[0].constraints, vec![ColumnConstraint::PrimaryKey]);
+            assert_eq!(columns[1].data_type, DataType::Text(255));
+            assert_eq!(columns[1].constraints, vec![ColumnConstraint::NotNull]);
+            assert_eq!(columns[2].data_type, DataType::Blob);
+        }
+        _ => panic!("Expected a create table statement")
+    }
+
+    let statement = parser::parse_statement("DROP TABLE IF EXISTS users").unwrap();
+    assert_eq!(statement, Statement::DropTable { table: "users".to_string(), if_exists: true });
+}
+
+#[test]
+fn test_parse_alter_table() {
+    let statement = parser::parse_statement(
+        "ALTER TABLE users ADD COLUMN age INTEGER, DROP COLUMN avatar, RENAME name TO full_name, ALTER COLUMN age SET NOT NULL"
+    ).unwrap();
+    match statement {
+        Statement::AlterTable { table, actions } => {
+            assert_eq!(table, "users");
+            assert_eq!(actions.len(), 4);
+            assert!(matches!(&actions[0], AlterTableAction::AddColumn(column) if column.name == "age"));
+            assert_eq!(actions[1], AlterTableAction::DropColumn("avatar".to_string()));
+            assert_eq!(actions[2], AlterTableAction::RenameColumn { old_name: "name".to_string(), new_name: "full_name".to_string() });
+            assert_eq!(actions[3], AlterTableAction::SetNotNull("age".to_string()));
+        }
+        _ => panic!("Expected an alter table statement")
+    }
+}
+
+#[test]
+fn test_parse_create_drop_index() {
+    let statements = parser::parse("CREATE INDEX IF NOT EXISTS users_name ON users (last_name, first_name); DROP INDEX users_name").unwrap();
+    assert_eq!(statements, vec![
+        Statement::CreateIndex {
+            name: "users_name".to_string(),
+            table: "users".to_string(),
+            if_not_exists: true,
+            columns: vec!["last_name".to_string(), "first_name".to_string()],
+        },
+        Statement::DropIndex { name: "users_name".to_string(), if_exists: false },
+    ]);
+    assert_eq!(statements[0].get_table_name(), Some("users"));
+    assert!(parser::parse_statement("CREATE INDEX users_name ON users").is_err());
+}
+
+#[test]
+fn test_parse_explain() {
+    let statement = parser::parse_statement("EXPLAIN SELECT * FROM users WHERE id = 1").unwrap();
+    let select = parser::parse_statement("SELECT * FROM users WHERE id = 1").unwrap();
+    assert_eq!(statement, Statement::Explain(Box::new(select)));
+    assert_eq!(statement.get_table_name(), Some("users"));
+    assert!(parser::parse_statement("EXPLAIN EXPLAIN SELECT * FROM users").is_err());
+    assert!(parser::parse_statement("EXPLAIN").is_err());
+}
+
+#[test]
+fn test_parse_create_drop_database() {
+    let statements = parser::parse("CREATE DATABASE customer; DROP DATABASE IF EXISTS customer;").unwrap();
+    assert_eq!(statements, vec![
+        Statement::CreateDatabase { name: "customer".to_string(), if_not_exists: false },
+        Statement::DropDatabase { name: "customer".to_string(), if_exists: true },
+    ]);
+}
+
+#[test]
+fn test_parse_transaction_control() {
+    let statements = parser::parse("BEGIN; INSERT INTO users VALUES (1); COMMIT TRANSACTION; begin transaction; ROLLBACK").unwrap();
+    assert_eq!(statements[0], Statement::Begin);
+    assert_eq!(statements[2], Statement::Commit);
+    assert_eq!(statements[3], Statement::Begin);
+    assert_eq!(statements[4], Statement::Rollback);
+    assert_eq!(statements[4].get_table_name(), None);
+    assert!(parser::parse_statement("BEGIN WORK").is_err());
+}
+
+#[test]
+fn test_parse_error_position() {
+    let error = parser::parse_statement("SELECT id\nFROM users\nWHERE id = ").unwrap_err();
+    assert_eq!(error.get_line(), 3);
+    assert_eq!(error.get_column(), 12);
+
+    let error = parser::parse_statement("SELECT * FROM users WHERE name = 'unterminated").unwrap_err();
+    assert_eq!(error.get_line(), 1);
+    assert_eq!(error.get_column(), 34);
+
+    let error = parser::parse_statement("SELECT * FORM users").unwrap_err();
+    assert_eq!(error.get_column(), 10);
+    assert_eq!(error.to_string(), "Expected FROM, found 'FORM' at line 1, column 10");
+}
+
+#[test]
+fn test_alter_table_operation_from_action() {
+    let statement = parser::parse_statement("ALTER TABLE users ADD age INTEGER NOT NULL, ALTER age DROP NOT NULL").unwrap();
+    let actions = match statement {
+        Statement::AlterTable { actions, .. } => actions,
+        _ => panic!("Expected an alter table statement")
+    };
+    match AlterTableOperation::from_action(&actions[0]).unwrap() {
+        AlterTableOperation::AddColumn { column, default } => {
+            assert_eq!(column.get_name(), "age");
+            assert!(!column.is_nullable());
+            assert!(default.is_null());
+        }
+        _ => panic!("Expected an add column operation")
+    }
+    assert_eq!(AlterTableOperation::from_action(&actions[1]).unwrap(), AlterTableOperation::SetNullable { name: "age".to_string(), is_nullable: true });
+}
+
+#[test]
+fn test_parse_column_constraints() {
+    let statement = parser::parse_statement(
+        "CREATE TABLE accounts (email TEXT UNIQUE NOT NULL, balance REAL DEFAULT -1.5 CHECK (balance >= 0 OR balance IS NULL))"
+    ).unwrap();
+    let columns = match statement {
+        Statement::CreateTable { columns, .. } => columns,
+        _ => panic!("Expected a create table statement")
+    };
+    assert_eq!(columns[0].constraints, vec![ColumnConstraint::Unique, ColumnConstraint::NotNull]);
+    assert_eq!(columns[1].constraints[0], ColumnConstraint::Default(Expression::Literal(Literal::Real(-1.5))));
+    assert!(matches!(&columns[1].constraints[1], ColumnConstraint::Check(Expression::Binary { operator: BinaryOperator::Or, .. })));
+
+    let statement = parser::parse_statement(
+        "CREATE TABLE orders (customer_id INTEGER REFERENCES customers (id) ON DELETE SET NULL, product_id INTEGER REFERENCES products)"
+    ).unwrap();
+    let columns = match statement {
+        Statement::CreateTable { columns, .. } => columns,
+        _ => panic!("Expected a create table statement")
+    };
+    assert_eq!(columns[0].constraints, vec![ColumnConstraint::References {
+        table: "customers".to_string(),
+        column: Some("id".to_string()),
+        on_delete: ReferentialAction::SetNull
+    }]);
+    assert_eq!(columns[1].constraints, vec![ColumnConstraint::References {
+        table: "products".to_string(),
+        column: None,
+        on_delete: ReferentialAction::Restrict
+    }]);
+    let error = parser::parse_statement("CREATE TABLE orders (customer_id INTEGER REFERENCES customers ON DELETE IGNORE)").unwrap_err();
+    assert_eq!(error.get_message(), "Expected RESTRICT, NO ACTION, CASCADE or SET NULL, found 'IGNORE'");
+}
+
+#[test]
+fn test_expression_display_round_trip() {
+    for sql in [
+        "(a + 1) * 2 = 'it''s'",
+        "NOT (a OR b) AND c IS NOT NULL",
+        "a - (b - c)",
+        "- (-a)",
+        "name NOT LIKE 'J%' AND id BETWEEN 1 AND 10 OR id IN (1, 2.5, X'0aff', NULL)",
+        "\"select\" || 'x'",
+    ] {
+        let expression = parser::parse_expression(sql).unwrap();
+        let displayed = expression.to_string();
+        assert_eq!(parser::parse_expression(&displayed).unwrap(), expression, "{}", displayed);
+    }
+}
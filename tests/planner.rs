@@ -0,0 +1,116 @@
+use red::database::abstraction::{Column, DataType, Database, Index, Table, Value};
+use red::database::parser::{self, Expression};
+use red::database::planner::{self, PlanNode};
+use red::storage::files::FileStorage;
+
+// customers(id, city, name, age) with an index on (city, name) and one on age
+fn customers_table() -> Table {
+    let mut table = Table::new("customers", Box::new(Database::new("customer", FileStorage::new("customer"))));
+    table.add_column(Column::new("id", DataType::Integer, true, false).unwrap());
+    table.add_column(Column::new("city", DataType::Text(64), false, true).unwrap());
+    table.add_column(Column::new("name", DataType::Text(64), false, true).unwrap());
+    table.add_column(Column::new("age", DataType::Integer, false, true).unwrap());
+    table.set_indexes(vec![
+        Index::new("customers_id", &["id"]),
+        Index::new("customers_city", &["city", "name"]),
+        Index::new("customers_age", &["age"]),
+    ]);
+    table
+}
+
+fn plan(table: &Table, sql: &str) -> PlanNode {
+    planner::plan(&parser::parse_statement(sql).unwrap(), table, 1000).unwrap()
+}
+
+fn plan_of(sql: &str) -> PlanNode {
+    plan(&customers_table(), sql)
+}
+
+// the scan node below the other nodes of a plan
+fn scan(plan: &PlanNode) -> &PlanNode {
+    match plan.get_input() {
+        Some(input) => scan(input),
+        None => plan,
+    }
+}
+
+fn expression(sql: &str) -> Expression {
+    match parser::parse_statement(&format!("SELECT * FROM t WHERE {}", sql)).unwrap().get_selection() {
+        Some(expression) => expression.clone(),
+        None => panic!("Expected a WHERE clause"),
+    }
+}
+
+#[test]
+fn test_plan_sequential_scan() {
+    let table = customers_table();
+    let plan = plan(&table, "SELECT * FROM customers WHERE name = 'John'");
+    match scan(&plan) {
+        PlanNode::SeqScan { table, filter, estimated_rows } => {
+            assert_eq!(table, "customers");
+            assert_eq!(filter, &Some(expression("name = 'John'")));
+            assert_eq!(*estimated_rows, 100);
+        }
+        node => panic!("Expected a sequential scan, found {:?}", node),
+    }
+    // a constant of another kind than the column is compared by the evaluator rules only
+    assert!(matches!(scan(&plan_of("SELECT * FROM customers WHERE city = 75")), PlanNode::SeqScan { .. }));
+    assert!(matches!(scan(&plan_of("SELECT * FROM customers WHERE age = NULL")), PlanNode::SeqScan { .. }));
+    assert!(matches!(scan(&plan_of("SELECT * FROM customers WHERE age > 3 OR id = 1")), PlanNode::SeqScan { .. }));
+    assert_eq!(plan_of("SELECT * FROM customers").get_estimated_rows(), 1000);
+}
+
+#[test]
+fn test_plan_index_scan() {
+    let plan = plan_of("SELECT * FROM customers WHERE age > 30 AND city = 'Paris' AND name = 'John'");
+    match scan(&plan) {
+        PlanNode::IndexScan { index, prefix, lower, upper, condition, filter, estimated_rows, .. } => {
+            // both columns of the (city, name) index are matched by equalities
+            assert_eq!(index.get_name(), "customers_city");
+            assert_eq!(prefix, &vec![Value::Text("Paris".to_string()), Value::Text("John".to_string())]);
+            assert_eq!((lower, upper), (&None, &None));
+            assert_eq!(condition, &vec![expression("city = 'Paris'"), expression("name = 'John'")]);
+            assert_eq!(filter, &Some(expression("age > 30")));
+            assert_eq!(*estimated_rows, 4);
+        }
+        node => panic!("Expected an index scan, found {:?}", node),
+    }
+
+    // bounds on the first column, the constant may come first
+    let plan = plan_of("SELECT * FROM customers WHERE 18 <= age AND age < 65 AND age < 30");
+    match scan(&plan) {
+        PlanNode::IndexScan { index, prefix, lower, upper, filter, .. } => {
+            assert_eq!(index.get_name(), "customers_age");
+            assert!(prefix.is_empty());
+            assert_eq!(lower, &Some(Value::Integer(18)));
+            assert_eq!(upper, &Some(Value::Integer(30)));
+            assert_eq!(filter, &None);
+        }
+        node => panic!("Expected an index scan, found {:?}", node),
+    }
+    let plan = plan_of("SELECT * FROM customers WHERE age BETWEEN -5 AND 5.5");
+    assert!(matches!(scan(&plan), PlanNode::IndexScan { lower: Some(Value::Integer(-5)), upper: Some(Value::Real(_)), .. }));
+
+    // the primary key gives a single row, a second column alone does not use an index
+    assert_eq!(scan(&plan_of("SELECT * FROM customers WHERE id = 7")).get_estimated_rows(), 1);
+    assert!(matches!(scan(&plan_of("SELECT * FROM customers WHERE name = 'John'")), PlanNode::SeqScan { .. }));
+}
+
+#[test]
+fn test_plan_display() {
+    let plan = plan_of("SELECT id, name AS customer FROM customers WHERE city = 'Paris' AND age > 30 ORDER BY name DESC LIMIT 10 OFFSET 5");
+    assert_eq!(plan.to_string(), "\
+Project id, name AS customer (rows=10)
+  Limit 10 offset 5 (rows=10)
+    Sort by name DESC (rows=34)
+      IndexScan using customers_city on customers (rows=34)
+        Index Cond: city = 'Paris'
+        Filter: age > 30
+");
+    assert_eq!(plan_of("SELECT * FROM customers LIMIT 5 OFFSET 998").to_string(), "\
+Project * (rows=2)
+  Limit 5 offset 998 (rows=2)
+    SeqScan on customers (rows=1000)
+");
+    assert!(planner::plan(&parser::parse_statement("DELETE FROM customers").unwrap(), &customers_table(), 10).is_err());
+}
@@ -0,0 +1,214 @@
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+use red::database::abstraction::{Column, DataType, Database, Index, Query, Record, Table, Value, DML};
+use red::database::shared::SharedDatabase;
+use red::storage::files::FileStorage;
+use red::storage::persistence::DataHandler;
+
+mod common;
+use crate::common::{setup, ROOT_DIR};
+
+// a shared database with an indexed table of items, in a directory of its own
+fn create_database(dir_name: &str) -> (Arc<SharedDatabase>, Table) {
+    setup();
+    let path = format!("{}/{}", ROOT_DIR, dir_name);
+    if std::fs::metadata(&path).is_ok() {
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+    std::fs::create_dir(&path).unwrap();
+    let database = SharedDatabase::new(Database::new("shop", FileStorage::new(&path)));
+    let mut items = Table::new("items", Box::new(Database::new("shop", FileStorage::new(&path))));
+    items.add_column(Column::new("id", DataType::Integer, true, false).unwrap());
+    items.add_column(Column::new("name", DataType::Text(255), false, false).unwrap());
+    items.add_column(Column::new("stock", DataType::Integer, false, false).unwrap());
+    database.create_table(items.clone()).unwrap();
+    database.create_index(items.clone(), Index::new("items_name", &["name"])).unwrap();
+    (database, items)
+}
+
+fn record(table: &Table, id: i64, name: &str, stock: i64) -> Record {
+    let values = vec![Value::Integer(id), Value::Text(name.to_string()), Value::Integer(stock)];
+    Record::new(table.clone(), table.get_columns().iter().cloned().zip(values).collect())
+}
+
+fn count(database: &SharedDatabase, sql: &str) -> usize {
+    database.select(Query::new(sql)).unwrap().get_records().len()
+}
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn test_concurrent_inserts() {
+    assert_send_sync::<SharedDatabase>();
+    let (database, items) = create_database("test_concurrent_inserts");
+    let threads: Vec<_> = (0..8)
+        .map(|thread| {
+            let database = database.clone();
+            let items = items.clone();
+            std::thread::spawn(move || {
+                for i in 0..25 {
+                    let id = thread * 100 + i;
+                    database.insert(record(&items, id, &format!("item {}", id), 1)).unwrap();
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    // no row is lost, in the data file or in the index
+    assert_eq!(count(&database, "SELECT * FROM items"), 200);
+    assert_eq!(count(&database, "SELECT * FROM items WHERE name = 'item 724'"), 1);
+    let data_handler = DataHandler::new_from_storage(database.get_storage().clone());
+    let index_table = data_handler.load_index_table("items_name").unwrap().unwrap();
+    let mut tree = data_handler.load_index(index_table.get_index("items_name").unwrap()).unwrap();
+    assert_eq!(tree.iter().count(), 200);
+}
+
+#[test]
+fn test_reads_during_writes() {
+    let (database, items) = create_database("test_reads_during_writes");
+    for id in 0..20 {
+        database.insert(record(&items, id, &format!("item {}", id), 0)).unwrap();
+    }
+    let writer = {
+        let database = database.clone();
+        let items = items.clone();
+        std::thread::spawn(move || {
+            for _ in 0..20 {
+                database.update(Record::new(items.clone(), Vec::new()), Query::new("UPDATE items SET stock = stock + 1")).unwrap();
+            }
+        })
+    };
+
+    // every read finds each row once, through the data file or the index, with the stock of one update
+    let readers: Vec<_> = (0..2)
+        .map(|_| {
+            let database = database.clone();
+            std::thread::spawn(move || {
+                for _ in 0..20 {
+                    for sql in ["SELECT * FROM items", "SELECT * FROM items WHERE name > 'item'"] {
+                        let records = database.select(Query::new(sql)).unwrap();
+                        assert_eq!(records.get_records().len(), 20);
+                        let stock = &records.get_records()[0].get_value("stock").unwrap().1;
+                        assert!(records.get_records().iter().all(|record| record.get_value("stock").unwrap().1 == *stock));
+                    }
+                }
+            })
+        })
+        .collect();
+    writer.join().unwrap();
+    for reader in readers {
+        reader.join().unwrap();
+    }
+    assert_eq!(count(&database, "SELECT * FROM items WHERE stock = 20"), 20);
+}
+
+#[test]
+fn test_transaction_holds_other_writers() {
+    let (database, items) = create_database("test_transaction_holds_other_writers");
+    let mut transaction = database.begin_transaction().unwrap();
+    transaction.insert(record(&items, 1, "book", 1)).unwrap();
+
+    // the insert of another thread waits for the transaction, its rollback leaves the other row alone
+    let writer = {
+        let database = database.clone();
+        let items = items.clone();
+        std::thread::spawn(move || database.insert(record(&items, 2, "pen", 1)).unwrap())
+    };
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(!writer.is_finished());
+    assert_eq!(count(&database, "SELECT * FROM items"), 0);
+    transaction.rollback().unwrap();
+    writer.join().unwrap();
+    assert_eq!(count(&database, "SELECT * FROM items WHERE name = 'pen'"), 1);
+    assert_eq!(count(&database, "SELECT * FROM items"), 1);
+}
+
+// a table of orders next to the items, created through the shared handle
+fn create_orders(database: &SharedDatabase) -> Table {
+    let mut orders = Table::new("orders", Box::new(Database::new("shop", database.get_storage().clone())));
+    orders.add_column(Column::new("id", DataType::Integer, true, false).unwrap());
+    orders.add_column(Column::new("name", DataType::Text(255), false, false).unwrap());
+    orders.add_column(Column::new("stock", DataType::Integer, false, false).unwrap());
+    database.create_table(orders.clone()).unwrap();
+    orders
+}
+
+#[test]
+fn test_writers_of_other_tables() {
+    let (database, items) = create_database("test_writers_of_other_tables");
+    let orders = create_orders(&database);
+    let mut transaction = database.begin_transaction().unwrap();
+    transaction.insert(record(&items, 1, "book", 1)).unwrap();
+
+    // the transaction owns the items only, orders are written meanwhile and outlive its rollback
+    let writer = {
+        let database = database.clone();
+        let orders = orders.clone();
+        std::thread::spawn(move || database.insert(record(&orders, 1, "John", 1)).unwrap())
+    };
+    writer.join().unwrap();
+    assert_eq!(count(&database, "SELECT * FROM orders"), 1);
+    transaction.rollback().unwrap();
+    assert_eq!(count(&database, "SELECT * FROM items"), 0);
+    assert_eq!(count(&database, "SELECT * FROM orders"), 1);
+}
+
+#[test]
+fn test_deadlock_between_transactions() {
+    let (database, items) = create_database("test_deadlock_between_transactions");
+    let orders = create_orders(&database);
+    let mut first = database.begin_transaction().unwrap();
+    first.insert(record(&items, 1, "book", 1)).unwrap();
+    let (ready_sender, ready) = mpsc::channel();
+    let (go, go_receiver) = mpsc::channel();
+    let second = {
+        let database = database.clone();
+        let (items, orders) = (items.clone(), orders.clone());
+        std::thread::spawn(move || {
+            let mut second = database.begin_transaction().unwrap();
+            second.insert(record(&orders, 1, "Jane", 1)).unwrap();
+            ready_sender.send(()).unwrap();
+            go_receiver.recv().unwrap();
+            let error = second.insert(record(&items, 2, "pen", 1)).err().unwrap().to_string();
+            second.rollback().unwrap();
+            error
+        })
+    };
+    ready.recv().unwrap();
+
+    // the first transaction moves to a thread waiting for the orders of the second one, the second one
+    // asking for the items would wait for it in turn and gets an error instead
+    let waiting = {
+        let orders = orders.clone();
+        std::thread::spawn(move || {
+            first.insert(record(&orders, 2, "John", 1)).unwrap();
+            first.commit().unwrap();
+        })
+    };
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(!waiting.is_finished());
+    go.send(()).unwrap();
+    assert_eq!(second.join().unwrap(), "Deadlock on table items, it is owned by work waiting for this one");
+    waiting.join().unwrap();
+    assert_eq!(count(&database, "SELECT * FROM items"), 1);
+    assert_eq!(count(&database, "SELECT * FROM orders"), 1);
+    assert_eq!(count(&database, "SELECT * FROM orders WHERE name = 'John'"), 1);
+}
+
+#[test]
+fn test_schema_changes_list_tables() {
+    let (database, items) = create_database("test_schema_changes_list_tables");
+    let names = |database: &SharedDatabase| database.get_tables().iter().map(|table| table.get_name().to_string()).collect::<Vec<_>>();
+    assert_eq!(names(&database), vec!["items"]);
+    let orders = create_orders(&database);
+    let mut listed = names(&database);
+    listed.sort();
+    assert_eq!(listed, vec!["items", "orders"]);
+    database.drop_table(orders).unwrap();
+    database.drop_table(items).unwrap();
+    assert!(database.get_tables().is_empty());
+}
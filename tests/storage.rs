@@ -1,5 +1,5 @@
 mod common;
-use red::{database::abstraction::{Column, DataType, Database, Table}, storage::{self, files::{FileExtension,TABLE_FILE_DATA_EXTENSION, TABLE_FILE_DESCRIPTOR_EXTENSION}, persistence::DataHandler}};
+use red::{database::abstraction::{Column, DataType, Database, Table}, storage::{self, files::{FileExtension,TABLE_FILE_DATA_EXTENSION, TABLE_FILE_DESCRIPTOR_EXTENSION, TEMP_FILE_EXTENSION}, persistence::DataHandler, wal::Durability}};
 
 use crate::common::{setup, ROOT_DIR};
 
@@ -297,4 +297,58 @@ fn test_save_file_descriptor() {
     let persist_result = data_handler.persist_table_descriptor(&table);
     assert!(persist_result.is_ok());
 
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_read_write_block() {
+    setup();
+    let file_name = "test_read_write_block";
+    let test_file = format!("{}/{}", ROOT_DIR, file_name);
+    if std::fs::metadata(&test_file).is_ok() {
+        std::fs::remove_file(&test_file).unwrap();
+    }
+
+    let storage = storage::files::FileStorage::new(ROOT_DIR);
+    storage.create_file(file_name).unwrap();
+
+    // writing past the end grows the file, the gap reads as zeros
+    storage.write_block(file_name, 8, b"block").unwrap();
+    storage.write_block(file_name, 0, b"head").unwrap();
+    assert_eq!(storage.read_block(file_name, 0, 13).unwrap(), b"head\0\0\0\0block".to_vec());
+    assert_eq!(storage.read_block(file_name, 8, 5).unwrap(), b"block".to_vec());
+    assert!(storage.read_block(file_name, 10, 8).is_err());
+}
+
+#[test]
+fn test_write_file_replaces_content() {
+    setup();
+    let file_name = "test_write_file_replaces_content";
+    let storage = storage::files::FileStorage::new(ROOT_DIR);
+    storage.write_file(file_name, "first").unwrap();
+    storage.write_file(file_name, "second").unwrap();
+    assert_eq!(storage.read_file(file_name).unwrap(), "second");
+
+    // the new content goes through a temporary file which does not outlive the write
+    let temp_file = format!("{}/{}.{}", ROOT_DIR, file_name, TEMP_FILE_EXTENSION);
+    assert!(std::fs::metadata(temp_file).is_err());
+}
+
+#[test]
+fn test_durability() {
+    setup();
+    let path = format!("{}/test_durability", ROOT_DIR);
+    if std::fs::metadata(&path).is_ok() {
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+    std::fs::create_dir(&path).unwrap();
+    let storage = storage::files::FileStorage::new(&path);
+
+    // tests do not sync their writes, the level is shared by the storages of a directory
+    assert_eq!(storage.get_durability().unwrap(), Durability::Off);
+    storage.set_durability(Durability::Full).unwrap();
+    let other = storage::files::FileStorage::new(&path);
+    assert_eq!(other.get_durability().unwrap(), Durability::Full);
+    other.write_file("synced", "content").unwrap();
+    other.checkpoint().unwrap();
+    assert_eq!(storage.read_file("synced").unwrap(), "content");
+}
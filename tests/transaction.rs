@@ -0,0 +1,205 @@
+use red::database::abstraction::{AlterTableOperation, Column, DataType, Database, Index, Query, Record, Table, Value, DDL, DML};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use red::storage::buffer;
+use red::storage::files::FileStorage;
+use red::storage::persistence::DataHandler;
+use red::storage::wal;
+
+mod common;
+use crate::common::{setup, ROOT_DIR};
+
+// a database with orders and their line items, in a directory of its own
+fn create_database(dir_name: &str) -> (Database, Table, Table) {
+    setup();
+    let path = format!("{}/{}", ROOT_DIR, dir_name);
+    if std::fs::metadata(&path).is_ok() {
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+    std::fs::create_dir(&path).unwrap();
+    let mut database = Database::new("shop", FileStorage::new(&path));
+    let mut orders = Table::new("orders", Box::new(database.clone()));
+    orders.add_column(Column::new("id", DataType::Integer, true, false).unwrap());
+    orders.add_column(Column::new("customer", DataType::Text(255), false, false).unwrap());
+    let mut items = Table::new("items", Box::new(database.clone()));
+    items.add_column(Column::new("id", DataType::Integer, true, false).unwrap());
+    items.add_column(Column::new("order_id", DataType::Integer, false, false).unwrap());
+    items.add_column(Column::new("product", DataType::Text(255), false, false).unwrap());
+    database.create_table(orders.clone()).unwrap();
+    database.create_table(items.clone()).unwrap();
+    database.create_index(items.clone(), Index::new("items_order", &["order_id"])).unwrap();
+    (database, orders, items)
+}
+
+fn record(table: &Table, values: Vec<Value>) -> Record {
+    Record::new(table.clone(), table.get_columns().iter().cloned().zip(values).collect())
+}
+
+fn text(value: &str) -> Value {
+    Value::Text(value.to_string())
+}
+
+fn count(dml: &dyn DML, sql: &str) -> usize {
+    dml.select(Query::new(sql)).unwrap().get_records().len()
+}
+
+#[test]
+fn test_transaction_commit() {
+    let (database, orders, items) = create_database("test_transaction_commit");
+    let mut transaction = database.begin_transaction().unwrap();
+    transaction.insert(record(&orders, vec![Value::Integer(1), text("John")])).unwrap();
+    transaction.insert(record(&items, vec![Value::Integer(1), Value::Integer(1), text("book")])).unwrap();
+    transaction.insert(record(&items, vec![Value::Integer(2), Value::Integer(1), text("pen")])).unwrap();
+    assert_eq!(count(&transaction, "SELECT * FROM items WHERE order_id = 1"), 2);
+    transaction.commit().unwrap();
+
+    // committed rows survive a crash
+    let storage = database.get_storage();
+    buffer::release_buffer_pool(storage.get_root_dir());
+    wal::release_write_ahead_log(storage.get_root_dir());
+    let data_handler = DataHandler::new_from_storage(FileStorage::new(storage.get_root_dir()));
+    assert_eq!(count(&data_handler, "SELECT * FROM orders"), 1);
+    assert_eq!(count(&data_handler, "SELECT * FROM items WHERE order_id = 1"), 2);
+}
+
+#[test]
+fn test_transaction_rollback() {
+    let (database, orders, items) = create_database("test_transaction_rollback");
+    let mut data_handler = DataHandler::new_from_storage(database.get_storage().clone());
+    data_handler.insert(record(&orders, vec![Value::Integer(1), text("John")])).unwrap();
+    data_handler.insert(record(&items, vec![Value::Integer(1), Value::Integer(1), text("book")])).unwrap();
+
+    // inserts, updates and deletes across tables are all undone
+    let mut transaction = database.begin_transaction().unwrap();
+    transaction.insert(record(&orders, vec![Value::Integer(2), text("Jane")])).unwrap();
+    transaction.insert(record(&items, vec![Value::Integer(2), Value::Integer(2), text("pen")])).unwrap();
+    transaction.update(record(&orders, vec![Value::Integer(1), text("Jack")]), Query::new("UPDATE orders SET customer = 'Jack' WHERE id = 1")).unwrap();
+    transaction.delete(Query::new("DELETE FROM items WHERE id = 1")).unwrap();
+    assert_eq!(count(&transaction, "SELECT * FROM items"), 1);
+    transaction.rollback().unwrap();
+
+    assert_eq!(count(&data_handler, "SELECT * FROM orders WHERE customer = 'John'"), 1);
+    assert_eq!(count(&data_handler, "SELECT * FROM orders"), 1);
+    assert_eq!(count(&data_handler, "SELECT * FROM items WHERE order_id = 1"), 1);
+    assert_eq!(count(&data_handler, "SELECT * FROM items WHERE order_id = 2"), 0);
+
+    // so is a transaction dropped without a commit
+    {
+        let mut transaction = database.begin_transaction().unwrap();
+        transaction.insert(record(&orders, vec![Value::Integer(3), text("Jill")])).unwrap();
+    }
+    assert_eq!(count(&data_handler, "SELECT * FROM orders"), 1);
+    assert_eq!(database.get_storage().get_transaction().unwrap(), None);
+}
+
+#[test]
+fn test_transaction_failed_statement() {
+    let (database, orders, items) = create_database("test_transaction_failed_statement");
+    let mut transaction = database.begin_transaction().unwrap();
+    transaction.insert(record(&orders, vec![Value::Integer(1), text("John")])).unwrap();
+    transaction.insert(record(&items, vec![Value::Integer(1), Value::Integer(1), text("book")])).unwrap();
+
+    // a duplicate key only undoes its own statement, the transaction goes on
+    assert!(transaction.insert(record(&items, vec![Value::Integer(1), Value::Integer(1), text("pen")])).is_err());
+    transaction.insert(record(&items, vec![Value::Integer(2), Value::Integer(1), text("pen")])).unwrap();
+    transaction.commit().unwrap();
+
+    let data_handler = DataHandler::new_from_storage(database.get_storage().clone());
+    assert_eq!(count(&data_handler, "SELECT * FROM items WHERE order_id = 1"), 2);
+    assert_eq!(count(&data_handler, "SELECT * FROM items WHERE product = 'book'"), 1);
+}
+
+#[test]
+fn test_transaction_crash() {
+    let (database, orders, items) = create_database("test_transaction_crash");
+    let storage = database.get_storage();
+    let mut transaction = database.begin_transaction().unwrap();
+    // the pages of every statement reach the data files before the transaction commits
+    for id in 0..50 {
+        transaction.insert(record(&orders, vec![Value::Integer(id), text("John")])).unwrap();
+        transaction.insert(record(&items, vec![Value::Integer(id), Value::Integer(id), text("book")])).unwrap();
+    }
+    std::mem::forget(transaction);
+
+    buffer::release_buffer_pool(storage.get_root_dir());
+    wal::release_write_ahead_log(storage.get_root_dir());
+    let data_handler = DataHandler::new_from_storage(FileStorage::new(storage.get_root_dir()));
+    assert_eq!(count(&data_handler, "SELECT * FROM orders"), 0);
+    assert_eq!(count(&data_handler, "SELECT * FROM items WHERE order_id = 1"), 0);
+}
+
+#[test]
+fn test_transaction_statements() {
+    let (mut database, orders, items) = create_database("test_transaction_statements");
+    let mut data_handler = DataHandler::new_from_storage(database.get_storage().clone());
+    let mut other = DataHandler::new_from_storage(database.get_storage().clone());
+    assert!(data_handler.execute_transaction_control(Query::new("COMMIT")).is_err());
+
+    // statements of the handle run between BEGIN and ROLLBACK are undone, other handles never see them
+    data_handler.execute_transaction_control(Query::new("BEGIN TRANSACTION")).unwrap();
+    assert!(data_handler.execute_transaction_control(Query::new("BEGIN")).is_err());
+    data_handler.insert(record(&orders, vec![Value::Integer(1), text("John")])).unwrap();
+    assert_eq!(count(&data_handler, "SELECT * FROM orders"), 1);
+    assert_eq!(count(&other, "SELECT * FROM orders"), 0);
+    // the thread of the transaction does not write through other handles, it would wait for itself
+    let expected = "A transaction is open on this database, its statements run through it";
+    assert_eq!(other.insert(record(&orders, vec![Value::Integer(2), text("Jane")])).err().unwrap().to_string(), expected);
+    assert!(database.begin_transaction().is_err());
+    assert!(database.create_index(orders.clone(), Index::new("orders_customer", &["customer"])).is_err());
+    assert!(other.execute_transaction_control(Query::new("ROLLBACK")).is_err());
+    data_handler.execute_transaction_control(Query::new("ROLLBACK")).unwrap();
+    assert_eq!(count(&data_handler, "SELECT * FROM orders"), 0);
+
+    data_handler.execute_transaction_control(Query::new("BEGIN;")).unwrap();
+    data_handler.insert(record(&orders, vec![Value::Integer(1), text("John")])).unwrap();
+    data_handler.execute_transaction_control(Query::new("COMMIT")).unwrap();
+    assert_eq!(count(&other, "SELECT * FROM orders"), 1);
+    assert!(data_handler.execute_transaction_control(Query::new("SELECT * FROM orders")).is_err());
+
+    // a transaction left open is rolled back along with its handle
+    data_handler.execute_transaction_control(Query::new("BEGIN")).unwrap();
+    data_handler.insert(record(&orders, vec![Value::Integer(2), text("Jane")])).unwrap();
+    drop(data_handler);
+    assert_eq!(count(&other, "SELECT * FROM orders"), 1);
+
+    // the schema is not changed from within a transaction handle either
+    let transaction = database.begin_transaction().unwrap();
+    assert_eq!(database.drop_table(items.clone()).err().unwrap().to_string(), expected);
+    transaction.commit().unwrap();
+    database.drop_table(items).unwrap();
+}
+
+#[test]
+fn test_transaction_alter_table() {
+    let (database, orders, _) = create_database("test_transaction_alter_table");
+    let mut data_handler = DataHandler::new_from_storage(database.get_storage().clone());
+    data_handler.insert(record(&orders, vec![Value::Integer(1), text("John")])).unwrap();
+    let width = |dml: &dyn DML| dml.select(Query::new("SELECT * FROM orders")).unwrap().get_records()[0].get_values().len();
+
+    // the rewritten rows and descriptor belong to the transaction, readers of other handles wait for its end
+    data_handler.execute_transaction_control(Query::new("BEGIN")).unwrap();
+    let column = Column::new("total", DataType::Integer, false, true).unwrap();
+    data_handler.alter_table("orders", &[AlterTableOperation::AddColumn { column, default: Value::Integer(0) }]).unwrap();
+    assert_eq!(width(&data_handler), 3);
+    let other = DataHandler::new_from_storage(database.get_storage().clone());
+    let (sender, receiver) = mpsc::channel();
+    let reader = thread::spawn(move || {
+        let records = other.select(Query::new("SELECT * FROM orders")).unwrap().get_records().clone();
+        sender.send(records.len()).unwrap();
+        records[0].get_values().len()
+    });
+    assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+    data_handler.execute_transaction_control(Query::new("COMMIT")).unwrap();
+    assert_eq!(receiver.recv().unwrap(), 1);
+    assert_eq!(reader.join().unwrap(), 3);
+
+    // a rolled back change leaves the table as it was
+    data_handler.execute_transaction_control(Query::new("BEGIN")).unwrap();
+    data_handler.alter_table("orders", &[AlterTableOperation::DropColumn("customer".to_string())]).unwrap();
+    assert_eq!(width(&data_handler), 2);
+    data_handler.execute_transaction_control(Query::new("ROLLBACK")).unwrap();
+    assert_eq!(width(&data_handler), 3);
+    let other = DataHandler::new_from_storage(database.get_storage().clone());
+    assert_eq!(width(&other), 3);
+}
@@ -0,0 +1,259 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+use red::database::abstraction::{Column, DataType, Database, Index, Query, Record, Table, Value, DDL, DML};
+use red::storage::buffer;
+use red::storage::files::FileStorage;
+use red::storage::persistence::DataHandler;
+use red::storage::wal::{self, LogRecord, WriteAheadLog, TRASH_FILE_EXTENSION, WAL_FILE_NAME};
+
+mod common;
+use crate::common::{setup, ROOT_DIR};
+
+// directory of the crashing child process
+const CRASH_DIR_VARIABLE: &str = "RED_WAL_CRASH_DIR";
+
+fn create_storage(dir_name: &str) -> FileStorage {
+    setup();
+    let path = format!("{}/{}", ROOT_DIR, dir_name);
+    if std::fs::metadata(&path).is_ok() {
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+    std::fs::create_dir(&path).unwrap();
+    FileStorage::new(&path)
+}
+
+// forget what this process knows of a directory, as if it had crashed, the next storage recovers it
+fn crash(storage: FileStorage) -> FileStorage {
+    let root_dir = storage.get_root_dir().to_string();
+    buffer::release_buffer_pool(&root_dir);
+    wal::release_write_ahead_log(&root_dir);
+    storage.close();
+    FileStorage::new(&root_dir)
+}
+
+fn trash_files(storage: &FileStorage) -> Vec<String> {
+    std::fs::read_dir(storage.get_root_dir()).unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.ends_with(TRASH_FILE_EXTENSION))
+        .collect()
+}
+
+fn log_length(storage: &FileStorage) -> u64 {
+    std::fs::metadata(format!("{}/{}", storage.get_root_dir(), WAL_FILE_NAME)).map_or(0, |metadata| metadata.len())
+}
+
+#[test]
+fn test_uncommitted_work_is_undone() {
+    let storage = create_storage("test_uncommitted_work_is_undone");
+    storage.write_file("kept", "committed").unwrap();
+    storage.write_file("gone", "committed").unwrap();
+    storage.create_file("pages").unwrap();
+    storage.write_page("pages", 0, &[1; 8]).unwrap();
+    storage.checkpoint().unwrap();
+    assert_eq!(log_length(&storage), 0);
+
+    // every kind of change reaches the disk before the crash, a page by being evicted
+    storage.write_file("kept", "changed").unwrap();
+    storage.delete_file("gone").unwrap();
+    storage.create_file("new").unwrap();
+    storage.set_buffer_pool_budget(8).unwrap();
+    storage.write_page("pages", 0, &[2; 8]).unwrap();
+    storage.write_page("pages", 8, &[3; 8]).unwrap();
+    assert_eq!(storage.read_block("pages", 0, 8).unwrap(), vec![2; 8]);
+    assert_eq!(trash_files(&storage).len(), 1);
+    assert!(storage.get_write_ahead_log().lock().unwrap().has_pending_work());
+
+    let storage = crash(storage);
+    assert_eq!(storage.read_file("kept").unwrap(), "committed");
+    assert_eq!(storage.read_file("gone").unwrap(), "committed");
+    assert!(!storage.file_exists("new"));
+    assert_eq!(storage.read_page("pages", 0, 8).unwrap(), vec![1; 8]);
+    assert!(storage.read_block("pages", 8, 8).is_err());
+    assert!(trash_files(&storage).is_empty());
+    assert_eq!(log_length(&storage), 0);
+}
+
+#[test]
+fn test_committed_work_is_kept() {
+    let storage = create_storage("test_committed_work_is_kept");
+    storage.write_file("kept", "first").unwrap();
+    storage.write_file("gone", "first").unwrap();
+    storage.checkpoint().unwrap();
+    storage.write_file("kept", "second").unwrap();
+    storage.delete_file("gone").unwrap();
+    storage.checkpoint().unwrap();
+    assert!(trash_files(&storage).is_empty());
+
+    let storage = crash(storage);
+    assert_eq!(storage.read_file("kept").unwrap(), "second");
+    assert!(!storage.file_exists("gone"));
+
+    // a crash after the commit record leaves the trash behind, it is deleted on open
+    let mut log = WriteAheadLog::new(storage.get_root_dir());
+    let record = log.delete_record("kept");
+    let trash = match &record {
+        LogRecord::DeleteFile { trash, .. } => trash.clone(),
+        _ => unreachable!(),
+    };
+    log.append(vec![record, LogRecord::Commit]).unwrap();
+    std::fs::rename(format!("{}/kept", storage.get_root_dir()), format!("{}/{}", storage.get_root_dir(), trash)).unwrap();
+    let storage = crash(storage);
+    assert!(!storage.file_exists("kept"));
+    assert!(trash_files(&storage).is_empty());
+}
+
+#[test]
+fn test_interleaved_work() {
+    let storage = create_storage("test_interleaved_work");
+    storage.write_file("mine", "first").unwrap();
+    storage.write_file("other", "first").unwrap();
+    storage.checkpoint().unwrap();
+
+    // the work of another thread is left in the log, the commit of this thread keeps it there
+    storage.write_file("mine", "second").unwrap();
+    {
+        let storage = storage.clone();
+        std::thread::spawn(move || storage.write_file("other", "second").unwrap()).join().unwrap();
+    }
+    storage.checkpoint().unwrap();
+    assert!(log_length(&storage) > 0);
+
+    // a crash undoes the work not committed only
+    let storage = crash(storage);
+    assert_eq!(storage.read_file("mine").unwrap(), "second");
+    assert_eq!(storage.read_file("other").unwrap(), "first");
+    assert_eq!(log_length(&storage), 0);
+}
+
+#[test]
+fn test_torn_log_tail() {
+    let storage = create_storage("test_torn_log_tail");
+    storage.write_file("kept", "committed").unwrap();
+    storage.checkpoint().unwrap();
+    storage.write_file("kept", "changed").unwrap();
+
+    // a record cut by the crash ends the log, the records before it are still undone
+    let mut log = std::fs::OpenOptions::new().append(true).open(format!("{}/{}", storage.get_root_dir(), WAL_FILE_NAME)).unwrap();
+    log.write_all(&[200, 0, 0, 0, 1, 2, 3]).unwrap();
+    let storage = crash(storage);
+    assert_eq!(storage.read_file("kept").unwrap(), "committed");
+    assert_eq!(log_length(&storage), 0);
+}
+
+#[test]
+fn test_crash_before_file_replaced() {
+    let storage = create_storage("test_crash_before_file_replaced");
+    storage.write_file("kept", "committed").unwrap();
+    storage.checkpoint().unwrap();
+
+    // a write cut before its temporary file replaced the file leaves the file as it was
+    let log = storage.get_write_ahead_log();
+    let mut log = log.lock().unwrap();
+    let records = vec![log.file_record("kept").unwrap(), log.create_record("kept.tmp")];
+    log.append(records).unwrap();
+    drop(log);
+    std::fs::write(format!("{}/kept.tmp", storage.get_root_dir()), "half").unwrap();
+    let storage = crash(storage);
+    assert_eq!(storage.read_file("kept").unwrap(), "committed");
+    assert!(!storage.file_exists("kept.tmp"));
+}
+
+#[test]
+fn test_failed_statement_is_rolled_back() {
+    let storage = create_storage("test_failed_statement_is_rolled_back");
+    let mut database = Database::new("customer", storage.clone());
+    let mut table = Table::new("users", Box::new(database.clone()));
+    table.add_column(Column::new("id", DataType::Integer, true, false).unwrap());
+    database.create_table(table.clone()).unwrap();
+    let mut data_handler = DataHandler::new_from_storage(storage.clone());
+    data_handler.insert(Record::new(table.clone(), vec![(table.get_columns()[0].clone(), Value::Integer(1))])).unwrap();
+
+    // the duplicate key leaves neither the row nor pending work
+    assert!(data_handler.insert(Record::new(table.clone(), vec![(table.get_columns()[0].clone(), Value::Integer(1))])).is_err());
+    assert!(!storage.get_write_ahead_log().lock().unwrap().has_pending_work());
+    let storage = crash(storage);
+    let data_handler = DataHandler::new_from_storage(storage);
+    assert_eq!(data_handler.select(Query::new("SELECT * FROM users")).unwrap().get_records().len(), 1);
+}
+
+// inserts rows until killed, run by test_crash_recovery in a child process
+#[test]
+#[ignore]
+fn wal_crash_child() {
+    let root_dir = match std::env::var(CRASH_DIR_VARIABLE) {
+        Ok(root_dir) => root_dir,
+        Err(_) => return,
+    };
+    let mut data_handler = DataHandler::new_from_storage(FileStorage::new(&root_dir));
+    let table = data_handler.load_table_descriptor("items").unwrap();
+    let mut id = data_handler.select(Query::new("SELECT * FROM items")).unwrap().get_records().len() as i64;
+    loop {
+        let values = vec![
+            (table.get_columns()[0].clone(), Value::Integer(id)),
+            (table.get_columns()[1].clone(), Value::Text(format!("item {}", id))),
+        ];
+        data_handler.insert(Record::new(table.clone(), values)).unwrap();
+        println!("committed {}", id);
+        id += 1;
+    }
+}
+
+#[test]
+fn test_crash_recovery() {
+    let storage = create_storage("test_crash_recovery");
+    let mut database = Database::new("customer", storage.clone());
+    let mut table = Table::new("items", Box::new(database.clone()));
+    table.add_column(Column::new("id", DataType::Integer, true, false).unwrap());
+    table.add_column(Column::new("name", DataType::Text(255), false, false).unwrap());
+    database.create_table(table.clone()).unwrap();
+    database.create_index(table, Index::new("items_name", &["name"])).unwrap();
+    // the child processes take the directory over, this process lets it go
+    let root_dir = storage.get_root_dir().to_string();
+    database.close();
+    storage.close();
+
+    let mut committed: i64 = 0;
+    for round in 0..5 {
+        let mut child = Command::new(std::env::current_exe().unwrap())
+            .args(["wal_crash_child", "--exact", "--ignored", "--nocapture", "--test-threads=1"])
+            .env(CRASH_DIR_VARIABLE, &root_dir)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+        let mut seen = 0;
+        for line in lines.by_ref() {
+            if let Some(id) = line.unwrap().strip_prefix("committed ") {
+                committed = id.parse::<i64>().unwrap() + 1;
+                seen += 1;
+                if seen == 10 + round * 15 {
+                    break;
+                }
+            }
+        }
+        child.kill().unwrap();
+        for line in lines {
+            if let Some(id) = line.unwrap().strip_prefix("committed ") {
+                committed = id.parse::<i64>().unwrap() + 1;
+            }
+        }
+        child.wait().unwrap();
+
+        // every reported row is there, the row being inserted when killed may be too
+        let storage = FileStorage::new(&root_dir);
+        let data_handler = DataHandler::new_from_storage(storage.clone());
+        let rows = data_handler.select(Query::new("SELECT * FROM items")).unwrap().get_records().len() as i64;
+        assert!(rows == committed || rows == committed + 1, "{} rows after {} were committed", rows, committed);
+        committed = rows;
+        for id in [0, rows - 1] {
+            let query = Query::new(&format!("SELECT * FROM items WHERE name = 'item {}'", id));
+            assert_eq!(data_handler.select(query).unwrap().get_records().len(), 1);
+        }
+        let index_table = data_handler.load_index_table("items_name").unwrap().unwrap();
+        let mut tree = data_handler.load_index(index_table.get_index("items_name").unwrap()).unwrap();
+        assert_eq!(tree.iter().count() as i64, rows);
+        assert!(!storage.get_write_ahead_log().lock().unwrap().has_pending_work());
+    }
+}